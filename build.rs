@@ -0,0 +1,28 @@
+use std::{env, process::Command};
+
+use chrono::{SecondsFormat, Utc};
+
+/// Embeds the git sha and build time for `GET /api/version`. `GIT_SHA` takes precedence over
+/// asking git so builds without a checkout, e.g. in Docker, can still report it.
+fn main() {
+    let git_sha = env::var("GIT_SHA")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .or_else(|| {
+            Command::new("git")
+                .args(["rev-parse", "HEAD"])
+                .output()
+                .ok()
+                .filter(|o| o.status.success())
+                .and_then(|o| String::from_utf8(o.stdout).ok())
+                .map(|s| s.trim().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    let build_time = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+
+    println!("cargo:rustc-env=OPEN_EDC_GIT_SHA={git_sha}");
+    println!("cargo:rustc-env=OPEN_EDC_BUILD_TIME={build_time}");
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
@@ -0,0 +1 @@
+DROP INDEX IF EXISTS organizations_name_lower_key;
@@ -0,0 +1,14 @@
+-- Names differing only in case would stop the index from being created. The earliest added
+-- organization keeps its name and the others get their id appended so they stay recognisable.
+UPDATE organizations
+SET name = organizations.name || ' (' || organizations.id || ')'
+FROM (
+    SELECT
+        id,
+        row_number() OVER (PARTITION BY lower(name) ORDER BY date_added, id) AS position
+    FROM organizations
+) AS ranked
+WHERE organizations.id = ranked.id
+  AND ranked.position > 1;
+
+CREATE UNIQUE INDEX IF NOT EXISTS organizations_name_lower_key ON organizations (lower(name));
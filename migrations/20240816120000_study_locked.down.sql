@@ -0,0 +1 @@
+ALTER TABLE studies DROP COLUMN IF EXISTS locked;
@@ -0,0 +1 @@
+ALTER TABLE studies ADD COLUMN IF NOT EXISTS locked BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,2 @@
+-- Email normalization is not reversible, the original casing and whitespace are not kept.
+SELECT 1;
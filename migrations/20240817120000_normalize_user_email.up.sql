@@ -0,0 +1 @@
+UPDATE users SET email = lower(trim(email));
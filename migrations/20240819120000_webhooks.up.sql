@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS webhooks(
+  id TEXT PRIMARY KEY,
+  url TEXT NOT NULL,
+  secret TEXT NOT NULL,
+  event_types TEXT[] NOT NULL,
+  date_added TIMESTAMP with time zone NOT NULL,
+  date_modified TIMESTAMP with time zone NOT NULL
+);
@@ -0,0 +1 @@
+ALTER TABLE users ALTER COLUMN access_level DROP DEFAULT;
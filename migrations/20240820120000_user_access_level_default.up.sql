@@ -0,0 +1,3 @@
+UPDATE users SET access_level = 'user' WHERE access_level IS NULL;
+ALTER TABLE users ALTER COLUMN access_level SET DEFAULT 'user';
+ALTER TABLE users ALTER COLUMN access_level SET NOT NULL;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS study_amendments;
+ALTER TABLE studies DROP COLUMN IF EXISTS protocol_version;
@@ -0,0 +1,9 @@
+ALTER TABLE studies ADD COLUMN IF NOT EXISTS protocol_version TEXT NOT NULL DEFAULT '1.0';
+
+CREATE TABLE IF NOT EXISTS study_amendments(
+  id TEXT PRIMARY KEY,
+  study_id TEXT REFERENCES studies(id) ON DELETE CASCADE NOT NULL,
+  protocol_version TEXT NOT NULL,
+  note TEXT NOT NULL,
+  date_added TIMESTAMP with time zone NOT NULL
+);
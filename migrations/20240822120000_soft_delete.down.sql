@@ -0,0 +1,18 @@
+-- Deleted rows would break the unique constraints being restored below
+DELETE FROM users WHERE deleted_at IS NOT NULL;
+DELETE FROM studies WHERE deleted_at IS NOT NULL;
+DELETE FROM organizations WHERE deleted_at IS NOT NULL;
+
+DROP INDEX IF EXISTS users_user_name_key;
+ALTER TABLE users ADD CONSTRAINT users_user_name_key UNIQUE (user_name);
+
+DROP INDEX IF EXISTS studies_study_id_key;
+ALTER TABLE studies ADD CONSTRAINT studies_study_id_key UNIQUE (study_id);
+
+DROP INDEX IF EXISTS organizations_name_lower_key;
+CREATE UNIQUE INDEX IF NOT EXISTS organizations_name_lower_key ON organizations (lower(name));
+ALTER TABLE organizations ADD CONSTRAINT organizations_name_key UNIQUE (name);
+
+ALTER TABLE users DROP COLUMN IF EXISTS deleted_at;
+ALTER TABLE studies DROP COLUMN IF EXISTS deleted_at;
+ALTER TABLE organizations DROP COLUMN IF EXISTS deleted_at;
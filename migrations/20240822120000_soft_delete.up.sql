@@ -0,0 +1,17 @@
+ALTER TABLE organizations ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP with time zone;
+ALTER TABLE studies ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP with time zone;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP with time zone;
+
+-- Deleted rows are kept, so names only have to be unique among the rows that aren't deleted
+ALTER TABLE organizations DROP CONSTRAINT IF EXISTS organizations_name_key;
+DROP INDEX IF EXISTS organizations_name_lower_key;
+CREATE UNIQUE INDEX IF NOT EXISTS organizations_name_lower_key ON organizations (lower(name))
+  WHERE deleted_at IS NULL;
+
+ALTER TABLE studies DROP CONSTRAINT IF EXISTS studies_study_id_key;
+CREATE UNIQUE INDEX IF NOT EXISTS studies_study_id_key ON studies (study_id)
+  WHERE deleted_at IS NULL;
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_user_name_key;
+CREATE UNIQUE INDEX IF NOT EXISTS users_user_name_key ON users (user_name)
+  WHERE deleted_at IS NULL;
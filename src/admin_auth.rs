@@ -0,0 +1,124 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use subtle::ConstantTimeEq;
+
+use crate::{
+    models::messages::GenericMessage,
+    state::AppState,
+    tls::{require_tls_for_sensitive, OverTls},
+};
+
+/// Lets a request through only when it carries `ADMIN_API_KEY` as a bearer token. When no key is
+/// configured the routes behind it are refused outright rather than left open. The key, and the
+/// webhook secrets registered behind it, are secrets so `REQUIRE_TLS_FOR_SENSITIVE` applies too.
+pub async fn require_admin(
+    State(state): State<Arc<AppState>>,
+    over_tls: OverTls,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Err(e) = require_tls_for_sensitive(&state.config, over_tls, "Admin requests") {
+        tracing::debug!(
+            "Refusing {} {}: {}",
+            request.method(),
+            request.uri(),
+            e.to_string()
+        );
+        return (
+            StatusCode::FORBIDDEN,
+            Json(GenericMessage {
+                detail: e.to_string(),
+            }),
+        )
+            .into_response();
+    }
+
+    let Some(admin_api_key) = &state.config.admin_api_key else {
+        tracing::debug!(
+            "Refusing {} {}, ADMIN_API_KEY is not set",
+            request.method(),
+            request.uri()
+        );
+        return (
+            StatusCode::FORBIDDEN,
+            Json(GenericMessage {
+                detail: "Admin endpoints are disabled, ADMIN_API_KEY is not set".to_string(),
+            }),
+        )
+            .into_response();
+    };
+
+    if bearer_token(request.headers()).is_some_and(|t| keys_match(t, admin_api_key)) {
+        return next.run(request).await;
+    }
+
+    tracing::debug!(
+        "Rejecting {} {} without the admin API key",
+        request.method(),
+        request.uri()
+    );
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+        Json(GenericMessage {
+            detail: "A valid admin API key is required".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// Token from an `Authorization: Bearer <token>` header, the scheme matched in any case
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let (scheme, token) = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .split_once(' ')?;
+
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then_some(token.trim())
+}
+
+/// Compares in constant time so response timing doesn't give the key away
+fn keys_match(token: &str, admin_api_key: &str) -> bool {
+    token.as_bytes().ct_eq(admin_api_key.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(authorization: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static(authorization),
+        );
+        headers
+    }
+
+    #[test]
+    fn bearer_token_parsed() {
+        assert_eq!(bearer_token(&headers("Bearer abc")), Some("abc"));
+        assert_eq!(bearer_token(&headers("bearer  abc ")), Some("abc"));
+        assert_eq!(bearer_token(&headers("Basic abc")), None);
+        assert_eq!(bearer_token(&headers("abc")), None);
+        assert_eq!(bearer_token(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn keys_compared() {
+        assert!(keys_match("secret-key", "secret-key"));
+        assert!(!keys_match("secret-kez", "secret-key"));
+        assert!(!keys_match("secret", "secret-key"));
+    }
+}
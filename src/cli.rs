@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
 
 #[derive(Debug, Parser)]
@@ -10,5 +12,56 @@ pub struct Cli {
 #[derive(Debug, Subcommand)]
 pub enum Command {
     /// Start the server
-    Start {},
+    Start {
+        /// Address to bind to, overrides SERVER_URL
+        #[clap(long)]
+        url: Option<String>,
+
+        /// Port to listen on, overrides PORT
+        #[clap(long)]
+        port: Option<u16>,
+    },
+
+    /// Write the OpenAPI spec to a file
+    GenerateOpenapi {
+        /// Path of the generated spec
+        #[clap(long, default_value = "openapi.json")]
+        out: PathBuf,
+
+        /// Check that the file at the path is up to date instead of writing it
+        #[clap(long)]
+        check: bool,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn start_overrides() {
+        let cli = Cli::try_parse_from(["open-edc", "start", "--url", "0.0.0.0", "--port", "8080"])
+            .unwrap();
+        let Command::Start { url, port } = cli.command else {
+            panic!("Expected the start command");
+        };
+        let config = Config::new().with_server_overrides(url, port);
+
+        assert_eq!(config.server_url, "0.0.0.0");
+        assert_eq!(config.port, 8080);
+    }
+
+    #[test]
+    fn start_no_overrides() {
+        let cli = Cli::try_parse_from(["open-edc", "start"]).unwrap();
+        let Command::Start { url, port } = cli.command else {
+            panic!("Expected the start command");
+        };
+        let expected = Config::new();
+        let config = Config::new().with_server_overrides(url, port);
+
+        assert_eq!(config.server_url, expected.server_url);
+        assert_eq!(config.port, expected.port);
+    }
 }
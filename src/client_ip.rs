@@ -0,0 +1,171 @@
+use std::{
+    convert::Infallible,
+    fmt,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, HeaderMap},
+};
+use ipnet::IpNet;
+
+use crate::state::AppState;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_REAL_IP: &str = "x-real-ip";
+
+/// Address of the client that made the request. Forwarding headers are only honoured when the
+/// connecting peer is one of the configured `TRUSTED_PROXIES`, anyone else could spoof them.
+/// `None` when the server isn't tracking peer addresses, as in tests.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl fmt::Display for ClientIp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(ip) => write!(f, "{ip}"),
+            None => write!(f, "unknown"),
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        Ok(Self(peer.map(|p| {
+            resolve_client_ip(p, &parts.headers, &state.config.trusted_proxies)
+        })))
+    }
+}
+
+/// Whether `ip` is one of the configured `TRUSTED_PROXIES`
+pub fn is_trusted(ip: IpAddr, trusted_proxies: &[IpNet]) -> bool {
+    trusted_proxies.iter().any(|net| net.contains(&ip))
+}
+
+/// Works out the client address for a request from `peer`. When the peer is a trusted proxy
+/// `X-Forwarded-For` is walked from the right, skipping further trusted proxies, and the first
+/// untrusted hop is the client. `X-Real-IP` is used when there is no usable `X-Forwarded-For`.
+pub fn resolve_client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpNet]) -> IpAddr {
+    if !is_trusted(peer, trusted_proxies) {
+        return peer;
+    }
+
+    let hops: Vec<&str> = headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .collect();
+
+    let mut forwarded = None;
+    for hop in hops.iter().rev() {
+        match hop.parse::<IpAddr>() {
+            Ok(ip) => {
+                forwarded = Some(ip);
+                if !is_trusted(ip, trusted_proxies) {
+                    break;
+                }
+            }
+            // Anything left of a malformed hop can't be attributed to a trusted proxy
+            Err(_) => break,
+        }
+    }
+
+    forwarded
+        .or_else(|| {
+            headers
+                .get(X_REAL_IP)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse().ok())
+        })
+        .unwrap_or(peer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn trusted() -> Vec<IpNet> {
+        vec!["10.0.0.0/8".parse().unwrap()]
+    }
+
+    fn headers(forwarded_for: Option<&str>, real_ip: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(f) = forwarded_for {
+            headers.insert(X_FORWARDED_FOR, HeaderValue::from_str(f).unwrap());
+        }
+        if let Some(r) = real_ip {
+            headers.insert(X_REAL_IP, HeaderValue::from_str(r).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn untrusted_peer_ignores_headers() {
+        let peer: IpAddr = "203.0.113.7".parse().unwrap();
+        let headers = headers(Some("198.51.100.1"), Some("198.51.100.2"));
+
+        assert_eq!(resolve_client_ip(peer, &headers, &trusted()), peer);
+    }
+
+    #[test]
+    fn trusted_peer_uses_forwarded_for() {
+        let peer: IpAddr = "10.0.0.1".parse().unwrap();
+        let headers = headers(Some("198.51.100.9, 198.51.100.1, 10.0.0.2"), None);
+
+        assert_eq!(
+            resolve_client_ip(peer, &headers, &trusted()),
+            "198.51.100.1".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn trusted_peer_uses_real_ip() {
+        let peer: IpAddr = "10.0.0.1".parse().unwrap();
+        let headers = headers(None, Some("198.51.100.2"));
+
+        assert_eq!(
+            resolve_client_ip(peer, &headers, &trusted()),
+            "198.51.100.2".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn trusted_peer_without_headers() {
+        let peer: IpAddr = "10.0.0.1".parse().unwrap();
+
+        assert_eq!(resolve_client_ip(peer, &HeaderMap::new(), &trusted()), peer);
+    }
+
+    #[test]
+    fn trusted_peer_malformed_forwarded_for() {
+        let peer: IpAddr = "10.0.0.1".parse().unwrap();
+        let headers = headers(Some("not-an-ip"), None);
+
+        assert_eq!(resolve_client_ip(peer, &headers, &trusted()), peer);
+    }
+
+    #[test]
+    fn no_trusted_proxies() {
+        let peer: IpAddr = "10.0.0.1".parse().unwrap();
+        let headers = headers(Some("198.51.100.1"), None);
+
+        assert_eq!(resolve_client_ip(peer, &headers, &[]), peer);
+    }
+}
@@ -1,5 +1,51 @@
-use std::env;
+use std::{collections::HashMap, env, fs, sync::OnceLock};
 
+use anyhow::{bail, Result};
+use axum::http::HeaderValue;
+use chrono::DateTime;
+use ipnet::IpNet;
+use regex::Regex;
+use toml_edit::{Document, Item, Table, Value};
+
+use crate::password_hasher::PASSWORD_HASH_ALGORITHMS;
+
+const BOOL_ENV_VARS: [&str; 8] = [
+    "EMAIL_ENABLED",
+    "METRICS_ENABLED",
+    "CORS_ALLOW_CREDENTIALS",
+    "MAINTENANCE_MODE",
+    "STRICT_JSON",
+    "SECURITY_HEADERS",
+    "REQUIRE_TLS_FOR_SENSITIVE",
+    "IDEMPOTENT_DELETES",
+];
+
+const U32_ENV_VARS: [&str; 9] = [
+    "DATABASE_STATEMENT_TIMEOUT_MS",
+    "DATABASE_IDLE_TIMEOUT_SECS",
+    "DATABASE_MAX_LIFETIME_SECS",
+    "SLOW_QUERY_MS",
+    "CORS_MAX_AGE_SECS",
+    "DOCS_CACHE_MAX_AGE_SECS",
+    "HSTS_MAX_AGE_SECS",
+    "CACHE_FRESH_SECS",
+    "CACHE_STALE_SECS",
+];
+
+const U16_ENV_VARS: [&str; 7] = [
+    "PORT",
+    "DATABASE_PORT",
+    "DB_KEEPALIVE_INTERVAL",
+    "VALKEY_PORT",
+    "DEFAULT_PAGE_SIZE",
+    "MAX_PAGE_SIZE",
+    "PASSWORD_HASH_CONCURRENCY",
+];
+
+/// Route groups that can be turned off with `FEATURES`, everything else is always mounted
+pub const FEATURES: [&str; 4] = ["organizations", "studies", "users", "webhooks"];
+
+#[derive(Clone)]
 pub struct Config {
     pub server_url: String,
     pub port: u16,
@@ -8,9 +54,77 @@ pub struct Config {
     pub database_user: String,
     pub database_password: String,
     pub database_port: u16,
+    pub database_replica_url: Option<String>,
+    /// Milliseconds a statement may run before Postgres cancels it, 0 disables the limit
+    pub database_statement_timeout_ms: u32,
+    /// Seconds a pooled connection may sit unused before it is closed, 0 never closes idle
+    /// connections. Keep it below any idle limit the server or a proxy in between enforces
+    pub database_idle_timeout_secs: u32,
+    /// Seconds before a pooled connection is replaced however busy it is, 0 never replaces them
+    pub database_max_lifetime_secs: u32,
+    /// Milliseconds after which a statement is logged as slow, 0 disables the log
+    pub slow_query_ms: u32,
+    /// Seconds between background database pings
+    pub db_keepalive_interval: u16,
     pub valkey_address: String,
     pub valkey_password: String,
     pub valkey_port: u16,
+    /// Seconds a cached value is served without being refreshed, 0 never refreshes cached values
+    pub cache_fresh_secs: u32,
+    /// Seconds after `cache_fresh_secs` a cached value is still served while it is refreshed in
+    /// the background, after that it is read from the database again
+    pub cache_stale_secs: u32,
+    pub default_page_size: u16,
+    pub max_page_size: u16,
+    /// Password hashes allowed to run at once, further requests wait their turn
+    pub password_hash_concurrency: u16,
+    /// Algorithm new password hashes are made with, one of `PASSWORD_HASH_ALGORITHMS`
+    pub password_hash_algorithm: String,
+    /// Whether outgoing email is turned on for this deployment
+    pub email_enabled: bool,
+    /// Whether metrics collection is turned on for this deployment
+    pub metrics_enabled: bool,
+    /// Origins allowed to make cross origin requests, `*` allows any. CORS is off when empty
+    pub cors_allowed_origins: Vec<String>,
+    /// Whether cross origin requests may include cookies and auth headers
+    pub cors_allow_credentials: bool,
+    /// Seconds browsers may cache a preflight response
+    pub cors_max_age_secs: u32,
+    /// Proxies whose `X-Forwarded-For` and `X-Real-IP` headers are believed, as CIDR ranges
+    pub trusted_proxies: Vec<IpNet>,
+    /// Rejects requests carrying passwords unless a trusted proxy says they were sent over HTTPS
+    pub require_tls_for_sensitive: bool,
+    /// Bearer token admin endpoints and webhook registration require, they are refused when
+    /// unset
+    pub admin_api_key: Option<String>,
+    /// Blocks writes from startup, see `routes::admin::maintenance_guard`
+    pub maintenance_mode: bool,
+    /// Message for clients to show users at login
+    pub banner_message: Option<String>,
+    /// Route paths, as declared in the router, that respond with a `Deprecation` header
+    pub deprecated_routes: Vec<String>,
+    /// HTTP date sent as the `Sunset` header on deprecated routes
+    pub deprecation_sunset: Option<String>,
+    /// Seconds browsers may cache the OpenAPI spec and Swagger UI assets, 0 sends `no-cache`
+    pub docs_cache_max_age_secs: u32,
+    /// Deleting something that doesn't exist returns 204 instead of 404, so retried deletes
+    /// succeed
+    pub idempotent_deletes: bool,
+    /// Rejects request bodies with fields the endpoint doesn't accept, see `strict_json`
+    pub strict_json: bool,
+    /// Whether `security_headers` adds its headers to responses
+    pub security_headers: bool,
+    /// Seconds browsers should only use HTTPS for this host, 0 leaves out the HSTS header
+    pub hsts_max_age_secs: u32,
+    /// New and updated study ids must match this, any study id is accepted when unset
+    pub study_id_pattern: Option<Regex>,
+    /// New and updated user names must match this, any user name is accepted when unset
+    pub user_name_pattern: Option<Regex>,
+    /// Route groups from `FEATURES` that are mounted, all of them when `FEATURES` isn't set
+    pub features: Vec<String>,
+
+    /// Environment variables that were set but could not be parsed
+    invalid_values: Vec<String>,
 }
 
 impl Config {
@@ -20,14 +134,75 @@ impl Config {
         let api_prefix = env_to_string_config("API_PREFIX", "/api".to_string());
         let database_address = env_to_string_config("DATABASE_ADDRESS", "127.0.0.1".to_string());
         let database_user = env_to_string_config("DATABASE_USER", "postgres".to_string());
-        let database_password = env_to_string_config_no_default("DATABASE_PASSWORD", "No database password provided. The DATABASE_PASSWORD environment vairable needs to be set");
+        let mut secret_problems = Vec::new();
+        let database_password = secret_to_string_config("DATABASE_PASSWORD", &mut secret_problems);
         let database_port = env_to_u16_config("DATABASE_PORT", 5432);
+        let database_replica_url = env_to_optional_string_config("DATABASE_REPLICA_URL");
+        let database_statement_timeout_ms =
+            env_to_u32_config("DATABASE_STATEMENT_TIMEOUT_MS", 10000);
+        let database_idle_timeout_secs = env_to_u32_config("DATABASE_IDLE_TIMEOUT_SECS", 600);
+        let database_max_lifetime_secs = env_to_u32_config("DATABASE_MAX_LIFETIME_SECS", 1800);
+        let slow_query_ms = env_to_u32_config("SLOW_QUERY_MS", 1000);
+        let db_keepalive_interval = env_to_u16_config("DB_KEEPALIVE_INTERVAL", 30);
         let valkey_address = env_to_string_config("VALKEY_ADDRESS", "127.0.0.1".to_string());
-        let valkey_password = env_to_string_config_no_default(
-            "VALKEY_PASSWORD",
-            "No valkey password provided. The VALKEY_PASSWORD vairable needs to be set",
-        );
+        let valkey_password = secret_to_string_config("VALKEY_PASSWORD", &mut secret_problems);
         let valkey_port = env_to_u16_config("VALKEY_PORT", 6379);
+        let cache_fresh_secs = env_to_u32_config("CACHE_FRESH_SECS", 0);
+        let cache_stale_secs = env_to_u32_config("CACHE_STALE_SECS", 60);
+        let default_page_size = env_to_u16_config("DEFAULT_PAGE_SIZE", 50);
+        let max_page_size = env_to_u16_config("MAX_PAGE_SIZE", 200);
+        let password_hash_concurrency = env_to_u16_config("PASSWORD_HASH_CONCURRENCY", 4);
+        let password_hash_algorithm =
+            env_to_string_config("PASSWORD_HASH_ALGORITHM", "argon2".to_string());
+        let email_enabled = env_to_bool_config("EMAIL_ENABLED", false);
+        let metrics_enabled = env_to_bool_config("METRICS_ENABLED", false);
+        let cors_allowed_origins = env_to_list_config("CORS_ALLOWED_ORIGINS");
+        let cors_allow_credentials = env_to_bool_config("CORS_ALLOW_CREDENTIALS", false);
+        let cors_max_age_secs = env_to_u32_config("CORS_MAX_AGE_SECS", 3600);
+        let maintenance_mode = env_to_bool_config("MAINTENANCE_MODE", false);
+        let banner_message = env_to_optional_string_config("BANNER_MESSAGE");
+        let deprecated_routes = env_to_list_config("DEPRECATED_ROUTES");
+        let deprecation_sunset = env_to_optional_string_config("DEPRECATION_SUNSET");
+        let docs_cache_max_age_secs = env_to_u32_config("DOCS_CACHE_MAX_AGE_SECS", 3600);
+        let idempotent_deletes = env_to_bool_config("IDEMPOTENT_DELETES", false);
+        let strict_json = env_to_bool_config("STRICT_JSON", false);
+        let security_headers = env_to_bool_config("SECURITY_HEADERS", true);
+        let hsts_max_age_secs = env_to_u32_config("HSTS_MAX_AGE_SECS", 31536000);
+        let mut proxy_problems = Vec::new();
+        let trusted_proxies = env_to_cidr_list_config("TRUSTED_PROXIES", &mut proxy_problems);
+        let require_tls_for_sensitive = env_to_bool_config("REQUIRE_TLS_FOR_SENSITIVE", false);
+        let admin_api_key = match read_secret("ADMIN_API_KEY") {
+            Ok(key) => key.filter(|k| !k.is_empty()),
+            Err(e) => {
+                secret_problems.push(e.to_string());
+                None
+            }
+        };
+        let mut pattern_problems = Vec::new();
+        let study_id_pattern = env_to_regex_config("STUDY_ID_PATTERN", &mut pattern_problems);
+        let user_name_pattern = env_to_regex_config("USER_NAME_PATTERN", &mut pattern_problems);
+        let features = match config_var("FEATURES") {
+            Ok(_) => env_to_list_config("FEATURES"),
+            Err(_) => FEATURES.iter().map(|f| f.to_string()).collect(),
+        };
+        let invalid_values = U16_ENV_VARS
+            .iter()
+            .filter_map(|env_var| invalid_u16_env(env_var))
+            .chain(
+                U32_ENV_VARS
+                    .iter()
+                    .filter_map(|env_var| invalid_u32_env(env_var)),
+            )
+            .chain(
+                BOOL_ENV_VARS
+                    .iter()
+                    .filter_map(|env_var| invalid_bool_env(env_var)),
+            )
+            .chain(config_file().problem.clone())
+            .chain(secret_problems)
+            .chain(proxy_problems)
+            .chain(pattern_problems)
+            .collect();
 
         Self {
             server_url,
@@ -37,23 +212,401 @@ impl Config {
             database_user,
             database_password,
             database_port,
+            database_replica_url,
+            database_statement_timeout_ms,
+            database_idle_timeout_secs,
+            database_max_lifetime_secs,
+            slow_query_ms,
+            db_keepalive_interval,
             valkey_address,
             valkey_password,
             valkey_port,
+            cache_fresh_secs,
+            cache_stale_secs,
+            default_page_size,
+            max_page_size,
+            password_hash_concurrency,
+            password_hash_algorithm,
+            email_enabled,
+            metrics_enabled,
+            cors_allowed_origins,
+            cors_allow_credentials,
+            cors_max_age_secs,
+            trusted_proxies,
+            require_tls_for_sensitive,
+            admin_api_key,
+            maintenance_mode,
+            banner_message,
+            deprecated_routes,
+            deprecation_sunset,
+            docs_cache_max_age_secs,
+            idempotent_deletes,
+            strict_json,
+            security_headers,
+            hsts_max_age_secs,
+            study_id_pattern,
+            user_name_pattern,
+            features,
+            invalid_values,
+        }
+    }
+
+    /// Replaces the server address and port with values given on the command line, which take
+    /// precedence over the environment.
+    pub fn with_server_overrides(mut self, url: Option<String>, port: Option<u16>) -> Self {
+        if let Some(u) = url {
+            self.server_url = u;
+        }
+
+        if let Some(p) = port {
+            self.port = p;
+        }
+
+        self
+    }
+
+    /// Whether the routes for `feature`, one of `FEATURES`, are mounted
+    pub fn feature_enabled(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+
+    /// Checks every setting up front and reports all problems at once so misconfiguration is
+    /// caught at startup instead of on first use.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = self.invalid_values.clone();
+
+        if self.server_url.is_empty() {
+            problems.push("SERVER_URL must not be empty".to_string());
+        }
+
+        if self.port == 0 {
+            problems.push("PORT must be greater than 0".to_string());
+        }
+
+        if !self.api_prefix.starts_with('/') || self.api_prefix.ends_with('/') {
+            problems.push("API_PREFIX must start with a / and not end with one".to_string());
+        }
+
+        if self.database_address.is_empty() {
+            problems.push("DATABASE_ADDRESS must not be empty".to_string());
+        }
+
+        if self.database_user.is_empty() {
+            problems.push("DATABASE_USER must not be empty".to_string());
+        }
+
+        if self.database_password.is_empty() {
+            problems.push("DATABASE_PASSWORD must be set".to_string());
+        }
+
+        if self.database_port == 0 {
+            problems.push("DATABASE_PORT must be greater than 0".to_string());
+        }
+
+        if self.db_keepalive_interval == 0 {
+            problems.push("DB_KEEPALIVE_INTERVAL must be greater than 0".to_string());
+        }
+
+        if self.valkey_address.is_empty() {
+            problems.push("VALKEY_ADDRESS must not be empty".to_string());
+        }
+
+        if self.valkey_password.is_empty() {
+            problems.push("VALKEY_PASSWORD must be set".to_string());
+        }
+
+        if self.valkey_port == 0 {
+            problems.push("VALKEY_PORT must be greater than 0".to_string());
+        } else if redis::Client::open(format!(
+            "redis://:{}@{}:{}",
+            self.valkey_password, self.valkey_address, self.valkey_port
+        ))
+        .is_err()
+        {
+            problems.push("VALKEY_ADDRESS does not form a valid valkey url".to_string());
+        }
+
+        if self.default_page_size == 0 {
+            problems.push("DEFAULT_PAGE_SIZE must be greater than 0".to_string());
+        }
+
+        if self.max_page_size < self.default_page_size {
+            problems.push("MAX_PAGE_SIZE must not be less than DEFAULT_PAGE_SIZE".to_string());
+        }
+
+        if self.password_hash_concurrency == 0 {
+            problems.push("PASSWORD_HASH_CONCURRENCY must be greater than 0".to_string());
+        }
+
+        if !PASSWORD_HASH_ALGORITHMS.contains(&self.password_hash_algorithm.as_str()) {
+            problems.push(format!(
+                "PASSWORD_HASH_ALGORITHM must be one of {}, got {}",
+                PASSWORD_HASH_ALGORITHMS.join(", "),
+                self.password_hash_algorithm
+            ));
+        }
+
+        for origin in self.cors_allowed_origins.iter().filter(|o| *o != "*") {
+            if HeaderValue::from_str(origin).is_err() {
+                problems.push(format!(
+                    "CORS_ALLOWED_ORIGINS contains an invalid origin {origin}"
+                ));
+            }
+        }
+
+        if self.cors_allow_credentials && self.cors_allowed_origins.iter().any(|o| o == "*") {
+            problems.push(
+                "CORS_ALLOWED_ORIGINS must list explicit origins when CORS_ALLOW_CREDENTIALS is true"
+                    .to_string(),
+            );
+        }
+
+        if self.require_tls_for_sensitive && self.trusted_proxies.is_empty() {
+            problems.push(
+                "REQUIRE_TLS_FOR_SENSITIVE needs TRUSTED_PROXIES, only a trusted proxy can report that a request used HTTPS"
+                    .to_string(),
+            );
+        }
+
+        for route in &self.deprecated_routes {
+            if !route.starts_with(&self.api_prefix) {
+                problems.push(format!(
+                    "DEPRECATED_ROUTES contains {route} which is not under API_PREFIX"
+                ));
+            }
+        }
+
+        if let Some(sunset) = &self.deprecation_sunset {
+            if DateTime::parse_from_rfc2822(sunset).is_err() {
+                problems.push(format!(
+                    "DEPRECATION_SUNSET must be an HTTP date such as Sat, 01 Nov 2025 00:00:00 GMT, got {sunset}"
+                ));
+            }
+        }
+
+        for feature in &self.features {
+            if !FEATURES.contains(&feature.as_str()) {
+                problems.push(format!(
+                    "FEATURES contains {feature} which is not one of {}",
+                    FEATURES.join(", ")
+                ));
+            }
+        }
+
+        if !problems.is_empty() {
+            bail!(format!(
+                "Invalid configuration:\n  - {}",
+                problems.join("\n  - ")
+            ));
         }
+
+        Ok(())
+    }
+}
+
+/// Config file read when `CONFIG_FILE` isn't set. Unlike a file named in `CONFIG_FILE` it may be
+/// missing, in which case only the environment is used.
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+#[derive(Default)]
+struct ConfigFile {
+    /// Values from the file keyed by the environment variable they stand in for
+    values: HashMap<String, String>,
+    /// Why the file couldn't be used, reported by `validate`
+    problem: Option<String>,
+}
+
+/// The TOML config file, read once. Its keys are the environment variable names in any case,
+/// and tables prefix the keys in them, so `[database]` with `user = "postgres"` sets
+/// `DATABASE_USER`. Lists are joined with commas. Environment variables override the file.
+fn config_file() -> &'static ConfigFile {
+    static CONFIG_FILE: OnceLock<ConfigFile> = OnceLock::new();
+
+    CONFIG_FILE.get_or_init(|| {
+        let (path, required) = match env::var("CONFIG_FILE") {
+            Ok(p) => (p, true),
+            Err(_) => (DEFAULT_CONFIG_FILE.to_string(), false),
+        };
+        let contents = match fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(_) if !required => return ConfigFile::default(),
+            Err(e) => {
+                return ConfigFile {
+                    values: HashMap::new(),
+                    problem: Some(format!("CONFIG_FILE could not be read from {path}: {e}")),
+                }
+            }
+        };
+
+        match parse_config_file(&contents) {
+            Ok(values) => ConfigFile {
+                values,
+                problem: None,
+            },
+            Err(e) => ConfigFile {
+                values: HashMap::new(),
+                problem: Some(format!("Config file {path} is invalid: {e}")),
+            },
+        }
+    })
+}
+
+/// Flattens a TOML config file into values keyed by environment variable name
+fn parse_config_file(contents: &str) -> Result<HashMap<String, String>> {
+    let document = contents.parse::<Document>()?;
+    let mut values = HashMap::new();
+    flatten_config_table(document.as_table(), "", &mut values)?;
+
+    Ok(values)
+}
+
+fn flatten_config_table(
+    table: &Table,
+    prefix: &str,
+    values: &mut HashMap<String, String>,
+) -> Result<()> {
+    for (key, item) in table.iter() {
+        let name = format!("{prefix}{}", key.to_uppercase());
+        match item {
+            Item::Table(t) => flatten_config_table(t, &format!("{name}_"), values)?,
+            Item::Value(Value::Array(a)) => {
+                let items = a
+                    .iter()
+                    .map(|v| config_scalar(&name, v))
+                    .collect::<Result<Vec<_>>>()?;
+                values.insert(name, items.join(","));
+            }
+            Item::Value(v) => {
+                let value = config_scalar(&name, v)?;
+                values.insert(name, value);
+            }
+            _ => bail!(format!("{name} must be a value, a list or a table")),
+        }
+    }
+
+    Ok(())
+}
+
+fn config_scalar(name: &str, value: &Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.value().clone()),
+        Value::Integer(i) => Ok(i.value().to_string()),
+        Value::Float(f) => Ok(f.value().to_string()),
+        Value::Boolean(b) => Ok(b.value().to_string()),
+        _ => bail!(format!("{name} must be a string, number or boolean")),
+    }
+}
+
+/// Reads `env_var` from the environment, falling back to the config file
+fn config_var(env_var: &str) -> Result<String, env::VarError> {
+    config_var_from(env_var, &config_file().values)
+}
+
+fn config_var_from(
+    env_var: &str,
+    file_values: &HashMap<String, String>,
+) -> Result<String, env::VarError> {
+    match env::var(env_var) {
+        Err(env::VarError::NotPresent) => file_values
+            .get(env_var)
+            .cloned()
+            .ok_or(env::VarError::NotPresent),
+        value => value,
     }
 }
 
 fn env_to_string_config(env_var: &str, default: String) -> String {
-    env::var(env_var).unwrap_or(default)
+    config_var(env_var).unwrap_or(default)
 }
 
-fn env_to_string_config_no_default(env_var: &str, error_msg: &str) -> String {
-    env::var(env_var).expect(error_msg)
+/// Reads a secret from the `name` environment variable or, when that isn't set, from the file
+/// at the path in `{name}_FILE` as mounted by Docker and Kubernetes secrets. A single trailing
+/// newline is stripped from file contents.
+pub fn read_secret(name: &str) -> Result<Option<String>> {
+    if let Ok(value) = config_var(name) {
+        return Ok(Some(value));
+    }
+
+    let file_var = format!("{name}_FILE");
+    let Ok(path) = config_var(&file_var) else {
+        return Ok(None);
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => Ok(Some(
+            contents
+                .strip_suffix('\n')
+                .map(|c| c.strip_suffix('\r').unwrap_or(c))
+                .unwrap_or(&contents)
+                .to_string(),
+        )),
+        Err(e) => bail!(format!("{file_var} could not be read from {path}: {e}")),
+    }
+}
+
+fn secret_to_string_config(env_var: &str, problems: &mut Vec<String>) -> String {
+    match read_secret(env_var) {
+        Ok(value) => value.unwrap_or_default(),
+        Err(e) => {
+            problems.push(e.to_string());
+            "".to_string()
+        }
+    }
+}
+
+fn env_to_optional_string_config(env_var: &str) -> Option<String> {
+    config_var(env_var).ok().filter(|v| !v.is_empty())
+}
+
+/// Reads a comma separated list, ignoring blank entries
+fn env_to_list_config(env_var: &str) -> Vec<String> {
+    config_var(env_var)
+        .map(|v| {
+            v.split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Reads a comma separated list of CIDR ranges, a bare address is treated as a single host
+fn env_to_cidr_list_config(env_var: &str, problems: &mut Vec<String>) -> Vec<IpNet> {
+    env_to_list_config(env_var)
+        .into_iter()
+        .filter_map(|item| match parse_cidr(&item) {
+            Some(net) => Some(net),
+            None => {
+                problems.push(format!("{env_var} contains an invalid CIDR range {item}"));
+                None
+            }
+        })
+        .collect()
+}
+
+/// Compiles the regular expression in `env_var`, `None` when it isn't set
+fn env_to_regex_config(env_var: &str, problems: &mut Vec<String>) -> Option<Regex> {
+    let pattern = env_to_optional_string_config(env_var)?;
+    match Regex::new(&pattern) {
+        Ok(regex) => Some(regex),
+        Err(e) => {
+            problems.push(format!(
+                "{env_var} is not a valid regular expression {pattern}: {e}"
+            ));
+            None
+        }
+    }
+}
+
+fn parse_cidr(value: &str) -> Option<IpNet> {
+    value
+        .parse::<IpNet>()
+        .ok()
+        .or_else(|| value.parse::<std::net::IpAddr>().ok().map(IpNet::from))
 }
 
 fn env_to_u16_config(env_var: &str, default: u16) -> u16 {
-    if let Ok(port) = env::var(env_var) {
+    if let Ok(port) = config_var(env_var) {
         if let Ok(p) = port.parse::<u16>() {
             p
         } else {
@@ -64,6 +617,58 @@ fn env_to_u16_config(env_var: &str, default: u16) -> u16 {
     }
 }
 
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn env_to_bool_config(env_var: &str, default: bool) -> bool {
+    if let Ok(value) = config_var(env_var) {
+        parse_bool(&value).unwrap_or(default)
+    } else {
+        default
+    }
+}
+
+fn invalid_bool_env(env_var: &str) -> Option<String> {
+    match config_var(env_var) {
+        Ok(value) if parse_bool(&value).is_none() => Some(format!(
+            "{env_var} must be true, false, 1, or 0, got {value}"
+        )),
+        _ => None,
+    }
+}
+
+fn env_to_u32_config(env_var: &str, default: u32) -> u32 {
+    if let Ok(value) = config_var(env_var) {
+        value.parse::<u32>().unwrap_or(default)
+    } else {
+        default
+    }
+}
+
+fn invalid_u32_env(env_var: &str) -> Option<String> {
+    match config_var(env_var) {
+        Ok(value) if value.parse::<u32>().is_err() => Some(format!(
+            "{env_var} must be a number between 0 and {}, got {value}",
+            u32::MAX
+        )),
+        _ => None,
+    }
+}
+
+fn invalid_u16_env(env_var: &str) -> Option<String> {
+    match config_var(env_var) {
+        Ok(value) if value.parse::<u16>().is_err() => Some(format!(
+            "{env_var} must be a number between 0 and 65535, got {value}"
+        )),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,6 +692,335 @@ mod tests {
         assert_eq!(got, expected.to_string());
     }
 
+    #[test]
+    fn config_file_fixture() {
+        let values = parse_config_file(include_str!("../fixtures/config.toml")).unwrap();
+
+        assert_eq!(values["SERVER_URL"], "0.0.0.0");
+        assert_eq!(values["DATABASE_USER"], "open_edc");
+        assert_eq!(values["DATABASE_PORT"], "5433");
+        assert_eq!(values["STRICT_JSON"], "true");
+        assert_eq!(values["FEATURES"], "organizations,studies");
+        assert_eq!(values["TRUSTED_PROXIES"], "10.0.0.0/8");
+    }
+
+    #[test]
+    fn config_file_env_overrides() {
+        let mut values = parse_config_file(include_str!("../fixtures/config.toml")).unwrap();
+        let overridden = format!("OVERRIDDEN_{}", Uuid::new_v4().simple());
+        let file_only = format!("FILE_ONLY_{}", Uuid::new_v4().simple());
+        values.insert(overridden.clone(), "file".to_string());
+        values.insert(file_only.clone(), "file".to_string());
+        env::set_var(&overridden, "env");
+
+        assert_eq!(config_var_from(&overridden, &values).unwrap(), "env");
+        assert_eq!(config_var_from(&file_only, &values).unwrap(), "file");
+        assert!(config_var_from(&Uuid::new_v4().to_string(), &values).is_err());
+    }
+
+    #[test]
+    fn config_file_invalid() {
+        assert!(parse_config_file("[database").is_err());
+        assert!(parse_config_file("[[database]]\nuser = \"postgres\"").is_err());
+        assert!(parse_config_file("features = [[\"studies\"]]").is_err());
+    }
+
+    #[test]
+    fn read_secret_from_file() {
+        let name = format!("SECRET_{}", Uuid::new_v4().simple());
+        let path = env::temp_dir().join(&name);
+        fs::write(&path, "file_secret\n").unwrap();
+        env::set_var(format!("{name}_FILE"), &path);
+        let got = read_secret(&name).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(got, Some("file_secret".to_string()));
+    }
+
+    #[test]
+    fn read_secret_env_wins() {
+        let name = format!("SECRET_{}", Uuid::new_v4().simple());
+        let path = env::temp_dir().join(&name);
+        fs::write(&path, "file_secret").unwrap();
+        env::set_var(&name, "env_secret");
+        env::set_var(format!("{name}_FILE"), &path);
+        let got = read_secret(&name).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(got, Some("env_secret".to_string()));
+    }
+
+    #[test]
+    fn read_secret_missing_file() {
+        let name = format!("SECRET_{}", Uuid::new_v4().simple());
+        env::set_var(
+            format!("{name}_FILE"),
+            env::temp_dir().join(Uuid::new_v4().to_string()),
+        );
+
+        assert!(read_secret(&name).is_err());
+    }
+
+    #[test]
+    fn read_secret_unset() {
+        let name = format!("SECRET_{}", Uuid::new_v4().simple());
+
+        assert_eq!(read_secret(&name).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_bool() {
+        assert_eq!(parse_bool("TRUE"), Some(true));
+        assert_eq!(parse_bool("1"), Some(true));
+        assert_eq!(parse_bool("false"), Some(false));
+        assert_eq!(parse_bool("0"), Some(false));
+        assert_eq!(parse_bool("yes"), None);
+    }
+
+    fn valid_config() -> Config {
+        Config {
+            server_url: "127.0.0.1".to_string(),
+            port: 3000,
+            api_prefix: "/api".to_string(),
+            database_address: "127.0.0.1".to_string(),
+            database_user: "postgres".to_string(),
+            database_password: "test_password".to_string(),
+            database_port: 5432,
+            database_replica_url: None,
+            database_statement_timeout_ms: 10000,
+            database_idle_timeout_secs: 600,
+            database_max_lifetime_secs: 1800,
+            slow_query_ms: 1000,
+            db_keepalive_interval: 30,
+            valkey_address: "127.0.0.1".to_string(),
+            valkey_password: "valkeypassword".to_string(),
+            valkey_port: 6379,
+            cache_fresh_secs: 0,
+            cache_stale_secs: 60,
+            default_page_size: 50,
+            max_page_size: 200,
+            password_hash_concurrency: 4,
+            password_hash_algorithm: "argon2".to_string(),
+            email_enabled: false,
+            metrics_enabled: false,
+            cors_allowed_origins: Vec::new(),
+            cors_allow_credentials: false,
+            cors_max_age_secs: 3600,
+            trusted_proxies: Vec::new(),
+            require_tls_for_sensitive: false,
+            admin_api_key: None,
+            maintenance_mode: false,
+            banner_message: None,
+            deprecated_routes: Vec::new(),
+            deprecation_sunset: None,
+            docs_cache_max_age_secs: 3600,
+            idempotent_deletes: false,
+            strict_json: false,
+            security_headers: true,
+            hsts_max_age_secs: 31536000,
+            study_id_pattern: None,
+            user_name_pattern: None,
+            features: FEATURES.iter().map(|f| f.to_string()).collect(),
+            invalid_values: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn validate_valid() {
+        assert!(valid_config().validate().is_ok());
+    }
+
+    #[test]
+    fn validate_missing_passwords() {
+        let mut config = valid_config();
+        config.database_password = "".to_string();
+        config.valkey_password = "".to_string();
+        let err = config.validate().unwrap_err().to_string();
+
+        assert!(err.contains("DATABASE_PASSWORD must be set"));
+        assert!(err.contains("VALKEY_PASSWORD must be set"));
+    }
+
+    #[test]
+    fn validate_zero_ports() {
+        let mut config = valid_config();
+        config.port = 0;
+        config.database_port = 0;
+        let err = config.validate().unwrap_err().to_string();
+
+        assert!(err.contains("PORT must be greater than 0"));
+        assert!(err.contains("DATABASE_PORT must be greater than 0"));
+    }
+
+    #[test]
+    fn validate_zero_password_hash_concurrency() {
+        let mut config = valid_config();
+        config.password_hash_concurrency = 0;
+        let err = config.validate().unwrap_err().to_string();
+
+        assert!(err.contains("PASSWORD_HASH_CONCURRENCY must be greater than 0"));
+    }
+
+    #[test]
+    fn validate_password_hash_algorithm() {
+        let mut config = valid_config();
+        config.password_hash_algorithm = "pbkdf2-sha256".to_string();
+        assert!(config.validate().is_ok());
+
+        config.password_hash_algorithm = "md5".to_string();
+        let err = config.validate().unwrap_err().to_string();
+
+        assert!(err.contains("PASSWORD_HASH_ALGORITHM must be one of argon2, pbkdf2-sha256"));
+    }
+
+    #[test]
+    fn validate_unparsable_value() {
+        let mut config = valid_config();
+        config
+            .invalid_values
+            .push("PORT must be a number between 0 and 65535, got abc".to_string());
+        let err = config.validate().unwrap_err().to_string();
+
+        assert!(err.contains("got abc"));
+    }
+
+    #[test]
+    fn validate_page_sizes() {
+        let mut config = valid_config();
+        config.default_page_size = 100;
+        config.max_page_size = 10;
+        let err = config.validate().unwrap_err().to_string();
+
+        assert!(err.contains("MAX_PAGE_SIZE must not be less than DEFAULT_PAGE_SIZE"));
+    }
+
+    #[test]
+    fn test_parse_cidr() {
+        assert_eq!(
+            parse_cidr("10.0.0.0/8"),
+            Some("10.0.0.0/8".parse().unwrap())
+        );
+        assert_eq!(parse_cidr("10.0.0.1"), Some("10.0.0.1/32".parse().unwrap()));
+        assert_eq!(parse_cidr("::1"), Some("::1/128".parse().unwrap()));
+        assert_eq!(parse_cidr("10.0.0.0/33"), None);
+        assert_eq!(parse_cidr("proxy"), None);
+    }
+
+    #[test]
+    fn validate_deprecation() {
+        let mut config = valid_config();
+        config.deprecated_routes = vec!["/organization".to_string()];
+        config.deprecation_sunset = Some("2025-11-01".to_string());
+        let err = config.validate().unwrap_err().to_string();
+
+        assert!(err.contains("DEPRECATED_ROUTES contains /organization"));
+        assert!(err.contains("DEPRECATION_SUNSET must be an HTTP date"));
+
+        config.deprecated_routes = vec!["/api/organization/:id".to_string()];
+        config.deprecation_sunset = Some("Sat, 01 Nov 2025 00:00:00 GMT".to_string());
+
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_cors_credentials_with_wildcard() {
+        let mut config = valid_config();
+        config.cors_allowed_origins = vec!["*".to_string()];
+        config.cors_allow_credentials = true;
+        let err = config.validate().unwrap_err().to_string();
+
+        assert!(err.contains("CORS_ALLOWED_ORIGINS must list explicit origins"));
+    }
+
+    #[test]
+    fn validate_cors_credentials_with_origins() {
+        let mut config = valid_config();
+        config.cors_allowed_origins = vec!["https://example.com".to_string()];
+        config.cors_allow_credentials = true;
+
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn env_to_list_config_splits() {
+        let env_var = Uuid::new_v4().to_string();
+        env::set_var(&env_var, "https://a.example.com, ,https://b.example.com");
+
+        assert_eq!(
+            env_to_list_config(&env_var),
+            vec!["https://a.example.com", "https://b.example.com"]
+        );
+    }
+
+    #[test]
+    fn env_to_regex_config_compiles() {
+        let env_var = Uuid::new_v4().to_string();
+        env::set_var(&env_var, r"^[A-Z]{2,4}-\d{4}$");
+        let mut problems = Vec::new();
+        let regex = env_to_regex_config(&env_var, &mut problems).unwrap();
+
+        assert!(problems.is_empty());
+        assert!(regex.is_match("ONC-0042"));
+    }
+
+    #[test]
+    fn env_to_regex_config_invalid() {
+        let env_var = Uuid::new_v4().to_string();
+        env::set_var(&env_var, "[A-Z");
+        let mut problems = Vec::new();
+
+        assert!(env_to_regex_config(&env_var, &mut problems).is_none());
+        assert!(problems[0].contains("is not a valid regular expression [A-Z"));
+    }
+
+    #[test]
+    fn env_to_regex_config_unset() {
+        let mut problems = Vec::new();
+
+        assert!(env_to_regex_config(&Uuid::new_v4().to_string(), &mut problems).is_none());
+        assert!(problems.is_empty());
+    }
+
+    #[test]
+    fn validate_require_tls_without_trusted_proxies() {
+        let mut config = valid_config();
+        config.require_tls_for_sensitive = true;
+        let err = config.validate().unwrap_err().to_string();
+
+        assert!(err.contains("REQUIRE_TLS_FOR_SENSITIVE needs TRUSTED_PROXIES"));
+
+        config.trusted_proxies = vec!["10.0.0.0/8".parse().unwrap()];
+
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_unknown_feature() {
+        let mut config = valid_config();
+        config.features = vec!["studies".to_string(), "subjects".to_string()];
+        let err = config.validate().unwrap_err().to_string();
+
+        assert!(err.contains("FEATURES contains subjects"));
+    }
+
+    #[test]
+    fn feature_enabled() {
+        let mut config = valid_config();
+        assert!(FEATURES.iter().all(|f| config.feature_enabled(f)));
+
+        config.features = vec!["studies".to_string()];
+        assert!(config.feature_enabled("studies"));
+        assert!(!config.feature_enabled("webhooks"));
+    }
+
+    #[test]
+    fn validate_api_prefix() {
+        let mut config = valid_config();
+        config.api_prefix = "api/".to_string();
+
+        assert!(config.validate().is_err());
+    }
+
     #[test]
     fn env_to_u16_config_default() {
         let expected = 1111;
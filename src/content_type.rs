@@ -0,0 +1,91 @@
+use axum::{
+    body::{Body, HttpBody},
+    extract::Request,
+    http::{header, HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::models::messages::GenericMessage;
+
+/// Media types request bodies can be sent as
+const ACCEPTED_CONTENT_TYPES: [&str; 1] = ["application/json"];
+
+/// Rejects POST, PUT and PATCH requests whose body isn't sent as one of
+/// `ACCEPTED_CONTENT_TYPES` with a 415 before a handler tries to parse it. Requests without a
+/// body, like activating a user, don't need a `Content-Type`.
+pub async fn require_json_content_type(request: Request, next: Next) -> Response {
+    let mutating = matches!(
+        *request.method(),
+        Method::POST | Method::PUT | Method::PATCH
+    );
+
+    if mutating && has_body(request.body()) && !accepted_content_type(request.headers()) {
+        let content_type = request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|c| c.to_str().ok())
+            .unwrap_or("none");
+        tracing::debug!("Rejecting request body with content type {content_type}");
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Json(GenericMessage {
+                detail: format!(
+                    "Unsupported content type {content_type}, expected {}",
+                    ACCEPTED_CONTENT_TYPES.join(" or ")
+                ),
+            }),
+        )
+            .into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Whether the request might carry a body, going by `Content-Length` or the body's size so far
+fn has_body(body: &Body) -> bool {
+    body.size_hint().upper() != Some(0)
+}
+
+/// Whether the `Content-Type` is accepted, ignoring parameters such as `charset`
+fn accepted_content_type(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|c| c.to_str().ok())
+        .and_then(|c| c.split(';').next())
+        .is_some_and(|c| {
+            ACCEPTED_CONTENT_TYPES
+                .iter()
+                .any(|a| a.eq_ignore_ascii_case(c.trim()))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(content_type: Option<&'static str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(c) = content_type {
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(c));
+        }
+        headers
+    }
+
+    #[test]
+    fn json_with_parameters_accepted() {
+        assert!(accepted_content_type(&headers(Some(
+            "Application/JSON; charset=utf-8"
+        ))));
+        assert!(!accepted_content_type(&headers(Some("text/plain"))));
+        assert!(!accepted_content_type(&headers(None)));
+    }
+
+    #[test]
+    fn empty_body() {
+        assert!(!has_body(&Body::empty()));
+        assert!(has_body(&Body::from("{}")));
+    }
+}
@@ -1,4 +1,12 @@
-use std::time::Duration;
+use std::{
+    future::Future,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use anyhow::Result;
 use axum::{
@@ -6,15 +14,20 @@ use axum::{
     extract::{FromRef, FromRequestParts},
     http::{request::Parts, StatusCode},
 };
+use log::LevelFilter;
 use sqlx::{
     pool::PoolConnection,
-    postgres::{PgPool, PgPoolOptions},
-    Postgres,
+    postgres::{PgConnectOptions, PgPool, PgPoolOptions},
+    ConnectOptions, Connection, Postgres,
 };
 
 #[derive(Clone, Debug)]
 pub struct DbClient {
     pub uri: String,
+    pub statement_timeout_ms: Option<u32>,
+    pub slow_query_ms: Option<u32>,
+    pub idle_timeout_secs: Option<u32>,
+    pub max_lifetime_secs: Option<u32>,
 }
 
 impl DbClient {
@@ -22,7 +35,51 @@ impl DbClient {
         let uri = format!("postgresql://{user_name}:{password}@{url}:{port}/{db_name}");
         tracing::debug!("{uri}");
 
-        DbClient { uri }
+        DbClient {
+            uri,
+            statement_timeout_ms: None,
+            slow_query_ms: None,
+            idle_timeout_secs: None,
+            max_lifetime_secs: None,
+        }
+    }
+
+    pub fn from_uri(uri: &str) -> Self {
+        DbClient {
+            uri: uri.to_string(),
+            statement_timeout_ms: None,
+            slow_query_ms: None,
+            idle_timeout_secs: None,
+            max_lifetime_secs: None,
+        }
+    }
+
+    /// Have Postgres cancel any statement on the pool's connections that runs longer than
+    /// `timeout_ms`. 0 disables the limit.
+    pub fn with_statement_timeout(mut self, timeout_ms: u32) -> Self {
+        self.statement_timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    /// Log statements that take longer than `threshold_ms` at WARN with the query and how long it
+    /// took. 0 turns the log off.
+    pub fn with_slow_query_threshold(mut self, threshold_ms: u32) -> Self {
+        self.slow_query_ms = Some(threshold_ms);
+        self
+    }
+
+    /// Close connections that have sat unused in the pool for `timeout_secs`. 0 keeps idle
+    /// connections open indefinitely.
+    pub fn with_idle_timeout(mut self, timeout_secs: u32) -> Self {
+        self.idle_timeout_secs = Some(timeout_secs);
+        self
+    }
+
+    /// Replace connections once they have been open for `lifetime_secs`, so no single session
+    /// holds on to server memory for the life of the process. 0 lets connections live forever.
+    pub fn with_max_lifetime(mut self, lifetime_secs: u32) -> Self {
+        self.max_lifetime_secs = Some(lifetime_secs);
+        self
     }
 
     pub async fn create_pool(
@@ -36,16 +93,178 @@ impl DbClient {
         } else {
             Duration::from_secs(5)
         };
-        let pool = PgPoolOptions::new()
+        let mut connect_options = PgConnectOptions::from_str(&self.uri)?;
+        if let Some(t) = self.statement_timeout_ms {
+            connect_options = connect_options.options([("statement_timeout", t.to_string())]);
+        }
+        match self.slow_query_ms {
+            Some(0) => {
+                connect_options =
+                    connect_options.log_slow_statements(LevelFilter::Off, Duration::ZERO);
+            }
+            Some(t) => {
+                connect_options = connect_options
+                    .log_slow_statements(LevelFilter::Warn, Duration::from_millis(t.into()));
+            }
+            None => {}
+        }
+        let mut pool_options = PgPoolOptions::new()
             .max_connections(connections)
-            .acquire_timeout(timeout)
-            .connect(&self.uri)
-            .await?;
+            .acquire_timeout(timeout);
+        if let Some(t) = self.idle_timeout_secs {
+            pool_options = pool_options.idle_timeout(non_zero_secs(t));
+        }
+        if let Some(l) = self.max_lifetime_secs {
+            pool_options = pool_options.max_lifetime(non_zero_secs(l));
+        }
+        let pool = pool_options.connect_with(connect_options).await?;
 
         Ok(pool)
     }
 }
 
+/// `secs` as a duration, with 0 meaning no limit
+fn non_zero_secs(secs: u32) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs.into()))
+}
+
+/// Postgres cancels statements that run past statement_timeout with SQLSTATE 57014
+/// (query_canceled).
+pub fn is_statement_timeout(error: &anyhow::Error) -> bool {
+    has_sqlstate(error, "57014")
+}
+
+/// SQLSTATE 23505 (unique_violation)
+pub fn is_unique_violation(error: &anyhow::Error) -> bool {
+    has_sqlstate(error, "23505")
+}
+
+/// SQLSTATE 40001 (serialization_failure), raised when a serializable transaction conflicts with
+/// a concurrent one. The transaction can be retried.
+pub fn is_serialization_failure(error: &anyhow::Error) -> bool {
+    has_sqlstate(error, "40001")
+}
+
+fn has_sqlstate(error: &anyhow::Error, code: &str) -> bool {
+    match error.downcast_ref::<sqlx::Error>() {
+        Some(sqlx::Error::Database(e)) => e.code().as_deref() == Some(code),
+        _ => false,
+    }
+}
+
+/// Database errors services handle themselves rather than passing on
+#[derive(Clone, Debug, PartialEq)]
+pub enum DbErrorKind {
+    /// SQLSTATE 23505, with the name of the unique constraint or index that was violated
+    UniqueViolation { constraint: Option<String> },
+    /// SQLSTATE 23503, with the name of the foreign key that was violated
+    ForeignKeyViolation { constraint: Option<String> },
+    /// SQLSTATE 23502
+    NotNullViolation,
+    /// SQLSTATE 23514, with the name of the check constraint that was violated
+    CheckViolation { constraint: Option<String> },
+    /// SQLSTATE 57014, see `is_statement_timeout`
+    StatementTimeout,
+    /// SQLSTATE 40001, see `is_serialization_failure`
+    SerializationFailure,
+    /// Any other error, including ones that didn't come from Postgres itself
+    Other,
+}
+
+/// Sorts `error` by its SQLSTATE so callers can match on what went wrong, and which constraint
+/// caused it, instead of searching the error message.
+pub fn classify_error(error: &sqlx::Error) -> DbErrorKind {
+    let sqlx::Error::Database(e) = error else {
+        return DbErrorKind::Other;
+    };
+    let constraint = e.constraint().map(str::to_string);
+
+    match e.code().as_deref() {
+        Some("23505") => DbErrorKind::UniqueViolation { constraint },
+        Some("23503") => DbErrorKind::ForeignKeyViolation { constraint },
+        Some("23502") => DbErrorKind::NotNullViolation,
+        Some("23514") => DbErrorKind::CheckViolation { constraint },
+        Some("57014") => DbErrorKind::StatementTimeout,
+        Some("40001") => DbErrorKind::SerializationFailure,
+        _ => DbErrorKind::Other,
+    }
+}
+
+/// Runs `job` only if this process can take the Postgres advisory lock named `key`, so a
+/// background job runs on one replica at a time. Returns `None` without running `job` when
+/// another session holds the lock. The lock belongs to the session it was taken on, so once it's
+/// held the connection is detached from the pool and closed after `job`. If `job` panics or the
+/// future is dropped the connection is dropped with it, which ends the session and releases the
+/// lock instead of a pooled connection holding it indefinitely.
+#[allow(dead_code)]
+pub async fn with_advisory_lock<F, Fut, T>(db_pool: &PgPool, key: &str, job: F) -> Result<Option<T>>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = T>,
+{
+    let mut conn = db_pool.acquire().await?;
+    let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock(hashtextextended($1, 0))")
+        .bind(key)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    if !locked {
+        tracing::debug!("Advisory lock {key} is held by another session, skipping job");
+        return Ok(None);
+    }
+
+    let mut conn = conn.detach();
+    let output = job().await;
+
+    // Released explicitly so the lock is free as soon as this returns, closing the session
+    // releases it as well but the server may not have noticed yet
+    let unlocked: Result<bool, sqlx::Error> =
+        sqlx::query_scalar("SELECT pg_advisory_unlock(hashtextextended($1, 0))")
+            .bind(key)
+            .fetch_one(&mut conn)
+            .await;
+    if !matches!(unlocked, Ok(true)) {
+        tracing::error!(
+            "Error releasing advisory lock {key}, it's released as the connection closes"
+        );
+    }
+    if let Err(e) = conn.close().await {
+        tracing::error!("Error closing connection: {}", e.to_string());
+    }
+
+    Ok(Some(output))
+}
+
+/// Pings the database on a fixed interval so dead connections are replaced before a request
+/// needs them. The result is stored in `healthy` so the readiness check doesn't have to query the
+/// database itself.
+pub async fn db_keepalive(db_pool: PgPool, healthy: Arc<AtomicBool>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+        let is_healthy = sqlx::query("SELECT 1").execute(&db_pool).await.is_ok();
+        record_db_health(&healthy, is_healthy);
+    }
+}
+
+/// Stores the latest health check result, returning true when it differs from the previous one.
+fn record_db_health(healthy: &AtomicBool, is_healthy: bool) -> bool {
+    let was_healthy = healthy.swap(is_healthy, Ordering::Relaxed);
+
+    if was_healthy == is_healthy {
+        return false;
+    }
+
+    if is_healthy {
+        tracing::info!("Database connection recovered");
+    } else {
+        tracing::error!("Database connection lost");
+    }
+
+    true
+}
+
 #[allow(dead_code)]
 struct DbManager(PoolConnection<Postgres>);
 
@@ -71,3 +290,267 @@ where
 {
     (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{atomic::AtomicUsize, Mutex};
+    use tokio::sync::oneshot;
+    use tracing::{Event, Level, Subscriber};
+    use tracing_subscriber::{
+        layer::{Context, SubscriberExt},
+        Layer,
+    };
+
+    /// Records the level of every event sqlx logs about a query
+    struct QueryLogCapture(Arc<Mutex<Vec<Level>>>);
+
+    impl<S: Subscriber> Layer<S> for QueryLogCapture {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            if event.metadata().target() == "sqlx::query" {
+                self.0.lock().unwrap().push(*event.metadata().level());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn statement_timeout() {
+        let db_pool = DbClient::new("127.0.0.1", "postgres", "test_password", &5432, "open_edc")
+            .with_statement_timeout(100)
+            .create_pool(Some(1), None)
+            .await
+            .unwrap();
+        let result = sqlx::query("SELECT pg_sleep(1)").execute(&db_pool).await;
+        let err = anyhow::Error::from(result.unwrap_err());
+
+        assert!(is_statement_timeout(&err));
+    }
+
+    #[tokio::test]
+    async fn slow_query_logged() {
+        let levels = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry().with(QueryLogCapture(levels.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let db_pool = DbClient::new("127.0.0.1", "postgres", "test_password", &5432, "open_edc")
+            .with_slow_query_threshold(50)
+            .create_pool(Some(1), None)
+            .await
+            .unwrap();
+
+        sqlx::query("SELECT pg_sleep(0.2)")
+            .execute(&db_pool)
+            .await
+            .unwrap();
+
+        assert!(levels.lock().unwrap().contains(&Level::WARN));
+    }
+
+    #[tokio::test]
+    async fn slow_query_log_disabled() {
+        let levels = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry().with(QueryLogCapture(levels.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let db_pool = DbClient::new("127.0.0.1", "postgres", "test_password", &5432, "open_edc")
+            .with_slow_query_threshold(0)
+            .create_pool(Some(1), None)
+            .await
+            .unwrap();
+
+        sqlx::query("SELECT pg_sleep(0.2)")
+            .execute(&db_pool)
+            .await
+            .unwrap();
+
+        assert!(!levels.lock().unwrap().contains(&Level::WARN));
+    }
+
+    #[tokio::test]
+    async fn classify_constraint_violations() {
+        // Temporary tables belong to the session, so keep to a single connection
+        let db_pool = DbClient::new("127.0.0.1", "postgres", "test_password", &5432, "open_edc")
+            .create_pool(Some(1), None)
+            .await
+            .unwrap();
+        for statement in [
+            "CREATE TEMP TABLE classify_parent (id INT PRIMARY KEY)",
+            r#"
+                CREATE TEMP TABLE classify_child (
+                    id INT PRIMARY KEY CONSTRAINT classify_child_positive CHECK (id > 0),
+                    parent_id INT NOT NULL REFERENCES classify_parent (id)
+                )
+            "#,
+            "INSERT INTO classify_parent (id) VALUES (1)",
+        ] {
+            sqlx::query(statement).execute(&db_pool).await.unwrap();
+        }
+        let classify = |statement: &'static str| {
+            let db_pool = db_pool.clone();
+            async move {
+                let err = sqlx::query(statement).execute(&db_pool).await.unwrap_err();
+                classify_error(&err)
+            }
+        };
+
+        assert_eq!(
+            classify("INSERT INTO classify_parent (id) VALUES (1)").await,
+            DbErrorKind::UniqueViolation {
+                constraint: Some("classify_parent_pkey".to_string())
+            }
+        );
+        assert_eq!(
+            classify("INSERT INTO classify_child (id, parent_id) VALUES (1, 2)").await,
+            DbErrorKind::ForeignKeyViolation {
+                constraint: Some("classify_child_parent_id_fkey".to_string())
+            }
+        );
+        assert_eq!(
+            classify("INSERT INTO classify_child (id, parent_id) VALUES (1, NULL)").await,
+            DbErrorKind::NotNullViolation
+        );
+        assert_eq!(
+            classify("INSERT INTO classify_child (id, parent_id) VALUES (-1, 1)").await,
+            DbErrorKind::CheckViolation {
+                constraint: Some("classify_child_positive".to_string())
+            }
+        );
+        assert_eq!(
+            classify("SELECT * FROM classify_missing").await,
+            DbErrorKind::Other
+        );
+    }
+
+    #[test]
+    fn classify_non_database_error() {
+        assert_eq!(
+            classify_error(&sqlx::Error::RowNotFound),
+            DbErrorKind::Other
+        );
+    }
+
+    #[tokio::test]
+    async fn advisory_lock_runs_once() {
+        let db_pool = DbClient::new("127.0.0.1", "postgres", "test_password", &5432, "open_edc")
+            .create_pool(Some(2), None)
+            .await
+            .unwrap();
+        let key = format!("test-job-{}", uuid::Uuid::new_v4());
+        let runs = Arc::new(AtomicUsize::new(0));
+        let (held_tx, held_rx) = oneshot::channel();
+        let (release_tx, release_rx) = oneshot::channel::<()>();
+
+        let first = {
+            let db_pool = db_pool.clone();
+            let key = key.clone();
+            let runs = runs.clone();
+            tokio::spawn(async move {
+                with_advisory_lock(&db_pool, &key, || async move {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                    held_tx.send(()).unwrap();
+                    release_rx.await.unwrap();
+                })
+                .await
+                .unwrap()
+            })
+        };
+        held_rx.await.unwrap();
+
+        let second = with_advisory_lock(&db_pool, &key, || async {
+            runs.fetch_add(1, Ordering::SeqCst);
+        })
+        .await
+        .unwrap();
+        release_tx.send(()).unwrap();
+
+        assert!(first.await.unwrap().is_some());
+        assert!(second.is_none());
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        // Released once the first job finished
+        let third = with_advisory_lock(&db_pool, &key, || async {})
+            .await
+            .unwrap();
+
+        assert!(third.is_some());
+    }
+
+    #[tokio::test]
+    async fn advisory_lock_released_when_job_dropped() {
+        let db_pool = DbClient::new("127.0.0.1", "postgres", "test_password", &5432, "open_edc")
+            .create_pool(Some(2), None)
+            .await
+            .unwrap();
+        let key = format!("test-job-{}", uuid::Uuid::new_v4());
+        let (held_tx, held_rx) = oneshot::channel();
+
+        let job = {
+            let db_pool = db_pool.clone();
+            let key = key.clone();
+            tokio::spawn(async move {
+                with_advisory_lock(&db_pool, &key, || async move {
+                    held_tx.send(()).unwrap();
+                    std::future::pending::<()>().await;
+                })
+                .await
+            })
+        };
+        held_rx.await.unwrap();
+        job.abort();
+        assert!(job.await.unwrap_err().is_cancelled());
+
+        // The server releases the lock once it sees the dropped connection close, which isn't
+        // instant
+        let mut reacquired = None;
+        for _ in 0..50 {
+            reacquired = with_advisory_lock(&db_pool, &key, || async {})
+                .await
+                .unwrap();
+            if reacquired.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        assert!(reacquired.is_some());
+    }
+
+    #[tokio::test]
+    async fn pool_idle_timeout_and_max_lifetime() {
+        let db_pool = DbClient::new("127.0.0.1", "postgres", "test_password", &5432, "open_edc")
+            .with_idle_timeout(60)
+            .with_max_lifetime(300)
+            .create_pool(Some(1), None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db_pool.options().get_idle_timeout(),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            db_pool.options().get_max_lifetime(),
+            Some(Duration::from_secs(300))
+        );
+
+        let db_pool = DbClient::new("127.0.0.1", "postgres", "test_password", &5432, "open_edc")
+            .with_idle_timeout(0)
+            .with_max_lifetime(0)
+            .create_pool(Some(1), None)
+            .await
+            .unwrap();
+
+        assert_eq!(db_pool.options().get_idle_timeout(), None);
+        assert_eq!(db_pool.options().get_max_lifetime(), None);
+    }
+
+    #[test]
+    fn record_db_health_transitions() {
+        let healthy = AtomicBool::new(true);
+
+        assert!(!record_db_health(&healthy, true));
+        assert!(record_db_health(&healthy, false));
+        assert!(!healthy.load(Ordering::Relaxed));
+        assert!(!record_db_health(&healthy, false));
+        assert!(record_db_health(&healthy, true));
+        assert!(healthy.load(Ordering::Relaxed));
+    }
+}
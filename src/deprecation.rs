@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::state::AppState;
+
+const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+
+const SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+/// Marks responses from the routes listed in `DEPRECATED_ROUTES` with a `Deprecation` header, and
+/// a `Sunset` header when a removal date is configured, so clients can find out before the routes
+/// go away.
+pub async fn deprecation_headers(
+    State(state): State<Arc<AppState>>,
+    matched_path: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let deprecated = matched_path.is_some_and(|p| {
+        state
+            .config
+            .deprecated_routes
+            .iter()
+            .any(|r| r == p.as_str())
+    });
+    let mut response = next.run(request).await;
+
+    if deprecated {
+        let headers = response.headers_mut();
+        headers.insert(DEPRECATION, HeaderValue::from_static("true"));
+        if let Some(sunset) = state
+            .config
+            .deprecation_sunset
+            .as_ref()
+            .and_then(|s| HeaderValue::from_str(s).ok())
+        {
+            headers.insert(SUNSET, sunset);
+        }
+    }
+
+    response
+}
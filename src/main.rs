@@ -1,17 +1,28 @@
+mod admin_auth;
 mod cli;
+mod client_ip;
 mod config;
+mod content_type;
+mod cors;
 mod db;
+mod deprecation;
 mod models;
 mod openapi;
+mod password_hasher;
 mod routes;
+mod security_headers;
 mod services;
 mod state;
+mod strict_json;
+#[cfg(test)]
+mod test_harness;
+mod tls;
 mod utils;
 
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use anyhow::Result;
-use axum::{serve, Router};
+use axum::{middleware, serve, Router};
 use clap::Parser;
 use dotenvy::dotenv;
 use tower_http::trace::TraceLayer;
@@ -22,8 +33,12 @@ use utoipa_swagger_ui::SwaggerUi;
 use crate::{
     cli::{Cli, Command},
     config::Config,
-    openapi::ApiDoc,
+    cors::cors_layer,
+    db::db_keepalive,
+    openapi::{check_openapi, write_openapi, ApiDoc, OPENAPI_PATH},
+    services::cache_services::{set_cache_policy, CachePolicy},
     state::AppState,
+    utils::set_password_hash_algorithm,
 };
 
 #[tokio::main]
@@ -41,16 +56,42 @@ async fn main() -> Result<()> {
     let args = Cli::parse();
 
     match args.command {
-        Command::Start {} => {
-            let config = Config::new();
-            let app = app(&config).await;
+        Command::Start { url, port } => {
+            let config = Config::new().with_server_overrides(url, port);
+            config.validate()?;
+            set_password_hash_algorithm(&config.password_hash_algorithm)?;
+            set_cache_policy(CachePolicy {
+                fresh: Duration::from_secs(config.cache_fresh_secs.into()),
+                stale: Duration::from_secs(config.cache_stale_secs.into()),
+            });
+            let state = app_state(&config).await;
+            tokio::spawn(db_keepalive(
+                state.db_state.pool.clone(),
+                state.db_state.healthy.clone(),
+                Duration::from_secs(config.db_keepalive_interval.into()),
+            ));
+            let app = router(state, &config);
             let server_url = &config.server_url;
             let server_port = &config.port;
             let listener = tokio::net::TcpListener::bind(format!("{server_url}:{server_port}"))
                 .await
                 .unwrap();
             tracing::info!("listening on {}", listener.local_addr().unwrap());
-            serve(listener, app).await.unwrap();
+            serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .unwrap();
+        }
+        Command::GenerateOpenapi { out, check } => {
+            if check {
+                check_openapi(&out)?;
+                tracing::info!("{} is up to date", out.display());
+            } else {
+                write_openapi(&out)?;
+                tracing::info!("OpenAPI spec written to {}", out.display());
+            }
         }
     }
 
@@ -58,26 +99,83 @@ async fn main() -> Result<()> {
 }
 
 async fn app(config: &Config) -> Router {
-    let app_state = match AppState::create_state(config).await {
-        Ok(s) => s,
+    router(app_state(config).await, config)
+}
+
+async fn app_state(config: &Config) -> Arc<AppState> {
+    match AppState::create_state(config).await {
+        Ok(s) => Arc::new(s),
         Err(e) => {
             tracing::error!("Error creating state: {}", e.to_string());
             panic!("Error creating state, cannot start server");
         }
-    };
-    let state = Arc::new(app_state);
+    }
+}
+
+/// Routes for the entities turned on in `FEATURES`. The routes of a disabled feature aren't
+/// mounted at all so they fall through to the 404 fallback.
+fn feature_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<AppState>> {
+    let mut router = Router::new();
+
+    if config.feature_enabled("organizations") {
+        router = router.merge(routes::organization::organization_routes(
+            state.clone(),
+            config,
+        ));
+    }
+
+    if config.feature_enabled("studies") {
+        router = router.merge(routes::study::study_routes(state.clone(), config));
+    }
+
+    if config.feature_enabled("users") {
+        router = router.merge(routes::user::user_routes(state.clone(), config));
+    }
+
+    if config.feature_enabled("webhooks") {
+        router = router.merge(routes::webhook::webhook_routes(state.clone(), config));
+    }
+
+    router
+}
 
-    Router::new()
+fn router(state: Arc<AppState>, config: &Config) -> Router {
+    let router = Router::new()
         .layer(TraceLayer::new_for_http())
-        .merge(SwaggerUi::new("/docs").url("/api-doc/openapi.json", ApiDoc::openapi()))
+        .merge(
+            Router::from(SwaggerUi::new("/docs").url(OPENAPI_PATH, ApiDoc::openapi())).layer(
+                middleware::from_fn_with_state(state.clone(), openapi::docs_cache_headers),
+            ),
+        )
+        .merge(routes::admin::admin_routes(state.clone(), config))
+        .merge(routes::config::config_routes(state.clone(), config))
         .merge(routes::health::health_routes(state.clone(), config))
-        .merge(routes::organization::organization_routes(
+        .merge(feature_routes(state.clone(), config))
+        .merge(routes::search::search_routes(state.clone(), config))
+        .merge(routes::version::version_routes(state.clone(), config))
+        .route_layer(middleware::from_fn_with_state(
             state.clone(),
-            config,
+            deprecation::deprecation_headers,
+        ))
+        .route_layer(middleware::from_fn(content_type::require_json_content_type))
+        .fallback(routes::fallback::not_found)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            routes::admin::maintenance_guard,
+        ))
+        .layer(middleware::map_response(
+            routes::fallback::method_not_allowed,
         ))
-        .merge(routes::study::study_routes(state.clone(), config))
-        .merge(routes::user::user_routes(state.clone(), config))
-        .with_state(state)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            security_headers::security_headers,
+        ))
+        .with_state(state);
+
+    match cors_layer(config) {
+        Some(cors) => router.layer(cors),
+        None => router,
+    }
 }
 
 #[cfg(test)]
@@ -85,12 +183,14 @@ mod tests {
     use super::*;
     use axum::{
         body::Body,
+        extract::ConnectInfo,
         http::{self, Request, StatusCode},
     };
     use bb8::Pool;
     use bb8_redis::RedisConnectionManager;
     use http_body_util::BodyExt; // for `collect`
     use serde_json::{json, Value};
+    use sqlx::postgres::PgPool;
     use tower::ServiceExt; // for `oneshot`
     use uuid::Uuid;
 
@@ -98,14 +198,31 @@ mod tests {
         db::DbClient,
         models::{
             organization::{Organization, OrganizationCreate},
-            study::{Study, StudyCreate, StudyInDb},
+            pagination::Pagination,
+            response::ListResponse,
+            sort::Sort,
+            study::{Study, StudyCreate, StudyInDb, StudyPatch, StudyUpdate},
             user::{AccessLevel, User, UserCreate, UserInDb},
+            webhook::{Webhook, WebhookCreate, WebhookEvent},
         },
         services::{
-            organization_services::create_organization_service,
-            study_services::create_study_service, user_services::create_user_service,
+            cache_services::{add_cached_value, delete_cached_value, get_cached_value},
+            organization_services::{
+                count_organizations_service, create_organization_service,
+                delete_organization_service, get_organization_service,
+                get_organizations_by_id_service, get_organizations_service,
+            },
+            study_services::{
+                create_study_service, get_study_service, patch_study_service,
+                set_study_lock_service, update_study_service,
+            },
+            user_services::{add_user_to_study_service, create_user_service, get_user_service},
+            webhook_services::{
+                create_webhook_service, sign_payload, tests::RecordingWebhookClient,
+            },
         },
-        utils::generate_db_id,
+        test_harness::test_pool,
+        utils::{generate_db_id, PasswordHashPermits},
     };
 
     fn db_client() -> DbClient {
@@ -113,11 +230,16 @@ mod tests {
     }
 
     async fn valkey_pool() -> Pool<RedisConnectionManager> {
+        valkey_pool_in(0).await
+    }
+
+    /// Pool on the logical valkey database `db`, keeping what a test stores there from other tests
+    async fn valkey_pool_in(db: u8) -> Pool<RedisConnectionManager> {
         let valkey_address = "127.0.0.1".to_string();
         let valkey_password = "valkeypassword".to_string();
         let valkey_port = 6379;
         let manager = RedisConnectionManager::new(format!(
-            "redis://:{valkey_password}@{valkey_address}:{valkey_port}"
+            "redis://:{valkey_password}@{valkey_address}:{valkey_port}/{db}"
         ))
         .expect("Error creating valkey manager");
 
@@ -132,6 +254,35 @@ mod tests {
         Config::new()
     }
 
+    fn hash_permits() -> PasswordHashPermits {
+        PasswordHashPermits::default()
+    }
+
+    const ADMIN_API_KEY: &str = "test-admin-key";
+
+    /// Config with `ADMIN_API_KEY` set to `ADMIN_API_KEY`
+    fn admin_config() -> Config {
+        let mut config = config();
+        config.admin_api_key = Some(ADMIN_API_KEY.to_string());
+        config
+    }
+
+    /// App whose database pools are `db_pool`, e.g. an isolated `test_pool`
+    async fn test_app(db_pool: &PgPool) -> Router {
+        test_app_with(&config(), db_pool).await
+    }
+
+    async fn test_app_with(config: &Config, db_pool: &PgPool) -> Router {
+        router(Arc::new(test_state(config, db_pool).await), config)
+    }
+
+    async fn test_state(config: &Config, db_pool: &PgPool) -> AppState {
+        let mut state = AppState::create_state(config).await.unwrap();
+        state.db_state.pool = db_pool.clone();
+        state.db_state.read_pool = db_pool.clone();
+        state
+    }
+
     #[tokio::test]
     async fn get_health() {
         let app = app(&config()).await;
@@ -156,76 +307,68 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn create_organization() {
+    async fn get_health_ready() {
         let app = app(&config()).await;
-        let name = Uuid::new_v4().to_string();
         let response = app
             .oneshot(
                 Request::builder()
-                    .method(http::Method::POST)
-                    .uri("/api/organization")
-                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
-                    .body(Body::from(
-                        serde_json::to_vec(&json!({ "name": name })).unwrap(),
-                    ))
+                    .uri("/api/health/ready")
+                    .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
         assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["migrations"], json!("up_to_date"));
     }
 
     #[tokio::test]
-    async fn delete_organization() {
-        let org_name = Uuid::new_v4().to_string();
-        let db_client = db_client();
-        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
-        let valkey_pool = valkey_pool().await;
-        let create_org = OrganizationCreate { name: org_name };
-        let new_org = create_organization_service(&db_pool, &valkey_pool, &create_org)
-            .await
-            .unwrap();
-
-        let app = app(&config()).await;
+    async fn get_config() {
+        let config = config();
+        let app = app(&config).await;
         let response = app
             .oneshot(
                 Request::builder()
-                    .method(http::Method::DELETE)
-                    .uri(&format!("/api/organization/{}", &new_org.id))
+                    .uri("/api/config")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(response.status(), StatusCode::OK);
 
-        let result = sqlx::query_as!(
-            Organization,
-            r#"
-                SELECT id, name, active, date_added, date_modified
-                FROM organizations
-                WHERE id = $1
-            "#,
-            &new_org.id,
-        )
-        .fetch_optional(&db_pool)
-        .await
-        .unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let text = String::from_utf8(body.to_vec()).unwrap().to_lowercase();
 
-        assert!(result.is_none());
+        let secrets = [
+            "password",
+            "secret",
+            config.database_password.as_str(),
+            config.valkey_password.as_str(),
+        ];
+        for secret in secrets.iter().filter(|s| !s.is_empty()) {
+            assert!(!text.contains(&secret.to_lowercase()));
+        }
+
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert!(body["features"]["email"].is_boolean());
+        assert!(body["features"]["cache"].is_boolean());
+        assert!(body["features"]["metrics"].is_boolean());
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
     }
 
     #[tokio::test]
-    async fn delete_organization_not_found() {
-        let org_id = generate_db_id();
+    async fn unknown_route() {
         let app = app(&config()).await;
         let response = app
             .oneshot(
                 Request::builder()
-                    .method(http::Method::DELETE)
-                    .uri(&format!("/api/organization/{}", &org_id))
+                    .uri("/api/not-a-route")
                     .body(Body::empty())
                     .unwrap(),
             )
@@ -233,110 +376,122 @@ mod tests {
             .unwrap();
 
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body["detail"], "No route found for /api/not-a-route");
     }
 
     #[tokio::test]
-    async fn get_organization() {
-        let org_name = Uuid::new_v4().to_string();
-        let db_client = db_client();
-        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
-        let valkey_pool = valkey_pool().await;
-        let create_org = OrganizationCreate { name: org_name };
-        let new_org = create_organization_service(&db_pool, &valkey_pool, &create_org)
-            .await
-            .unwrap();
-
+    async fn method_not_allowed() {
         let app = app(&config()).await;
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri(&format!("/api/organization/{}", &new_org.id))
+                    .method(http::Method::PATCH)
+                    .uri("/api/organization")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        let allow = response
+            .headers()
+            .get(http::header::ALLOW)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(allow.contains("PUT"));
 
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        let body: Organization = serde_json::from_slice(&body).unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
 
-        assert_eq!(body.name, create_org.name);
+        assert!(body["detail"].as_str().unwrap().contains("PUT"));
     }
 
     #[tokio::test]
-    async fn get_organization_not_found() {
-        let org_id = generate_db_id();
-        let app = app(&config()).await;
+    async fn create_organization() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let app = test_app(&db_pool).await;
+        let name = Uuid::new_v4().to_string();
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri(&format!("/api/organization/{}", &org_id))
-                    .body(Body::empty())
+                    .method(http::Method::POST)
+                    .uri("/api/organization")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({ "name": name })).unwrap(),
+                    ))
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.status(), StatusCode::OK);
     }
 
     #[tokio::test]
-    async fn get_organizations() {
-        let org_name = Uuid::new_v4().to_string();
-        let db_client = db_client();
-        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
-        let valkey_pool = valkey_pool().await;
-        let create_org = OrganizationCreate { name: org_name };
-        create_organization_service(&db_pool, &valkey_pool, &create_org)
+    async fn create_organization_unsupported_content_type() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let name = Uuid::new_v4().to_string();
+        let response = test_app(&db_pool)
             .await
-            .unwrap();
-
-        let app = app(&config()).await;
-        let response = app
             .oneshot(
                 Request::builder()
+                    .method(http::Method::POST)
                     .uri("/api/organization")
-                    .body(Body::empty())
+                    .header(http::header::CONTENT_TYPE, mime::TEXT_PLAIN.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({ "name": name })).unwrap(),
+                    ))
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
 
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        let body: Vec<Organization> = serde_json::from_slice(&body).unwrap();
-        println!("{:?}", body);
+        let body: Value = serde_json::from_slice(&body).unwrap();
 
-        assert!(body.iter().any(|item| item.name == create_org.name));
+        assert_eq!(
+            body["detail"],
+            json!("Unsupported content type text/plain, expected application/json")
+        );
     }
 
     #[tokio::test]
-    async fn update_organization() {
+    async fn bootstrap_organization() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
         let org_name = Uuid::new_v4().to_string();
-        let app = app(&config()).await;
-        let db_client = db_client();
-        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
-        let valkey_pool = valkey_pool().await;
-        let create_org = OrganizationCreate { name: org_name };
-        let new_org = create_organization_service(&db_pool, &valkey_pool, &create_org)
+        let user_name = Uuid::new_v4().to_string();
+        let response = test_app(&db_pool)
             .await
-            .unwrap();
-
-        let updated_name = Uuid::new_v4().to_string();
-        let active = false;
-        let response = app
             .oneshot(
                 Request::builder()
-                    .method(http::Method::PUT)
-                    .uri("/api/organization")
+                    .method(http::Method::POST)
+                    .uri("/api/organization/bootstrap")
                     .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
                     .body(Body::from(
-                        serde_json::to_vec(
-                            &json!({"id": new_org.id, "name": updated_name, "active": active }),
-                        )
+                        serde_json::to_vec(&json!({
+                            "name": org_name,
+                            "admin": {
+                                "user_name": user_name,
+                                "first_name": "Arthur",
+                                "last_name": "Dent",
+                                "email": "arthur@heartofgold.com",
+                                "password": "Somepassword1!",
+                            },
+                        }))
                         .unwrap(),
                     ))
                     .unwrap(),
@@ -344,18 +499,31 @@ mod tests {
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::CREATED);
 
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        let body: Organization = serde_json::from_slice(&body).unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
 
-        assert_eq!(body.name, updated_name);
-        assert_eq!(body.active, active);
+        assert_eq!(body["organization"]["name"], org_name);
+        assert_eq!(body["admin"]["user_name"], user_name);
+        assert_eq!(
+            body["admin"]["organization"]["id"],
+            body["organization"]["id"]
+        );
+
+        let access_level = sqlx::query_scalar!(
+            r#"SELECT access_level AS "access_level: AccessLevel" FROM users WHERE user_name = $1"#,
+            user_name,
+        )
+        .fetch_one(&db_pool)
+        .await
+        .unwrap();
+
+        assert!(matches!(access_level, AccessLevel::OrganizationAdmin));
     }
 
     #[tokio::test]
-    async fn create_study() {
-        let app = app(&config()).await;
+    async fn access_level_defaults_to_user() {
         let db_client = db_client();
         let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
         let valkey_pool = valkey_pool().await;
@@ -365,19 +533,84 @@ mod tests {
         let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
             .await
             .unwrap();
-        let study_id = Uuid::new_v4().to_string();
-        let response = app
+        let user_name = Uuid::new_v4().to_string();
+        sqlx::query!(
+            r#"
+                INSERT INTO users (
+                    id,
+                    user_name,
+                    first_name,
+                    last_name,
+                    email,
+                    hashed_password,
+                    organization_id,
+                    active,
+                    date_added,
+                    date_modified
+                )
+                VALUES ($1, $2, 'Imma', 'Person', 'some@email.com', 'hash', $3, true, now(), now())
+            "#,
+            generate_db_id(),
+            user_name,
+            organization.id.as_str(),
+        )
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+        let access_level = sqlx::query_scalar!(
+            r#"SELECT access_level AS "access_level: AccessLevel" FROM users WHERE user_name = $1"#,
+            user_name,
+        )
+        .fetch_one(&db_pool)
+        .await
+        .unwrap();
+
+        assert!(matches!(access_level, AccessLevel::User));
+    }
+
+    #[tokio::test]
+    async fn bootstrap_organization_rolls_back() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let user_create = UserCreate {
+            user_name: Uuid::new_v4().to_string(),
+            first_name: "Imma".to_string(),
+            last_name: "Person".to_string(),
+            email: "some@email.com".to_string(),
+            phone: None,
+            password: "Somepassword1!".to_string(),
+            organization_id: organization.id.to_string(),
+        };
+        create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+            .await
+            .unwrap();
+        let org_name = Uuid::new_v4().to_string();
+
+        let response = test_app(&db_pool)
+            .await
             .oneshot(
                 Request::builder()
                     .method(http::Method::POST)
-                    .uri("/api/study")
+                    .uri("/api/organization/bootstrap")
                     .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
                     .body(Body::from(
                         serde_json::to_vec(&json!({
-                            "study_id": study_id,
-                            "study_name": "Test Study",
-                            "study_description": "Description",
-                            "organization_id": organization.id,
+                            "name": org_name,
+                            "admin": {
+                                "user_name": user_create.user_name,
+                                "first_name": "Arthur",
+                                "last_name": "Dent",
+                                "email": "arthur@heartofgold.com",
+                                "password": "Somepassword1!",
+                            },
                         }))
                         .unwrap(),
                     ))
@@ -386,41 +619,74 @@ mod tests {
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
-        let body = response.into_body().collect().await.unwrap().to_bytes();
-        let body: Study = serde_json::from_slice(&body).unwrap();
+        let org_count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM organizations WHERE name = $1"#,
+            org_name,
+        )
+        .fetch_one(&db_pool)
+        .await
+        .unwrap();
 
-        assert_eq!(body.study_id, study_id);
+        assert_eq!(org_count, 0);
     }
 
     #[tokio::test]
-    async fn delete_study() {
-        let app = app(&config()).await;
-        let db_client = db_client();
-        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+    async fn create_organization_case_insensitive_duplicate() {
+        let org_name = format!("Acme-{}", Uuid::new_v4());
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
         let valkey_pool = valkey_pool().await;
         let create_org = OrganizationCreate {
-            name: Uuid::new_v4().to_string(),
+            name: org_name.clone(),
         };
-        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+        create_organization_service(&db_pool, &valkey_pool, &create_org)
             .await
             .unwrap();
-        let study_create = StudyCreate {
-            study_id: Uuid::new_v4().to_string(),
-            study_name: Some("Study Name".to_string()),
-            study_description: Some("Description".to_string()),
-            organization_id: organization.id,
-        };
-        let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+
+        let app = test_app(&db_pool).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/organization")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({ "name": org_name.to_uppercase() })).unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert!(body["detail"]
+            .as_str()
+            .unwrap()
+            .contains("already exists (case-insensitive)"));
+    }
+
+    #[tokio::test]
+    async fn delete_organization() {
+        let org_name = Uuid::new_v4().to_string();
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate { name: org_name };
+        let new_org = create_organization_service(&db_pool, &valkey_pool, &create_org)
             .await
             .unwrap();
 
+        let app = test_app(&db_pool).await;
         let response = app
             .oneshot(
                 Request::builder()
                     .method(http::Method::DELETE)
-                    .uri(&format!("/api/study/{}", &study.id))
+                    .uri(&format!("/api/organization/{}", &new_org.id))
                     .body(Body::empty())
                     .unwrap(),
             )
@@ -429,134 +695,152 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::NO_CONTENT);
 
-        let result = sqlx::query_as!(
-            StudyInDb,
-            r#"
-                SELECT
-                    id,
-                    study_id,
-                    study_name,
-                    study_description,
-                    organization_id,
-                    date_added,
-                    date_modified
-                FROM studies
-                WHERE id = $1
-            "#,
-            &study.id,
+        let deleted_at = sqlx::query_scalar!(
+            "SELECT deleted_at FROM organizations WHERE id = $1",
+            new_org.id.as_str(),
         )
-        .fetch_optional(&db_pool)
+        .fetch_one(&db_pool)
         .await
         .unwrap();
 
-        assert!(result.is_none());
+        assert!(deleted_at.is_some());
+        assert!(
+            get_organization_service(&db_pool, &valkey_pool, &new_org.id, true)
+                .await
+                .unwrap()
+                .is_none()
+        );
     }
 
     #[tokio::test]
-    async fn get_study() {
-        let app = app(&config()).await;
-        let db_client = db_client();
-        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+    async fn delete_organization_with_dependents() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
         let valkey_pool = valkey_pool().await;
         let create_org = OrganizationCreate {
             name: Uuid::new_v4().to_string(),
         };
-        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+        let new_org = create_organization_service(&db_pool, &valkey_pool, &create_org)
             .await
             .unwrap();
         let study_create = StudyCreate {
             study_id: Uuid::new_v4().to_string(),
             study_name: Some("Study Name".to_string()),
             study_description: Some("Description".to_string()),
-            organization_id: organization.id,
+            organization_id: new_org.id.to_string(),
         };
-        let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+        create_study_service(&db_pool, &valkey_pool, &study_create)
             .await
             .unwrap();
 
+        let app = test_app(&db_pool).await;
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri(&format!("/api/study/{}", &study.id))
+                    .method(http::Method::DELETE)
+                    .uri(&format!("/api/organization/{}", &new_org.id))
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::OK);
-
-        let body = response.into_body().collect().await.unwrap().to_bytes();
-        let body: Study = serde_json::from_slice(&body).unwrap();
-
-        assert_eq!(body.study_id, study_create.study_id);
-    }
+        assert_eq!(response.status(), StatusCode::CONFLICT);
 
-    #[tokio::test]
-    async fn get_study_not_found() {
-        let study_id = generate_db_id();
-        let app = app(&config()).await;
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .uri(&format!("/api/study/{}", &study_id))
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+        let result = sqlx::query_as!(
+            Organization,
+            r#"
+                SELECT id, name, active, date_added, date_modified
+                FROM organizations
+                WHERE id = $1
+            "#,
+            new_org.id.as_str(),
+        )
+        .fetch_optional(&db_pool)
+        .await
+        .unwrap();
 
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(result.is_some());
     }
 
     #[tokio::test]
-    async fn create_user() {
-        let app = app(&config()).await;
-        let db_client = db_client();
-        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+    async fn delete_organization_cascade() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
         let valkey_pool = valkey_pool().await;
         let create_org = OrganizationCreate {
             name: Uuid::new_v4().to_string(),
         };
-        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+        let new_org = create_organization_service(&db_pool, &valkey_pool, &create_org)
             .await
             .unwrap();
-        let user_name = Uuid::new_v4().to_string();
+        let study_create = StudyCreate {
+            study_id: Uuid::new_v4().to_string(),
+            study_name: Some("Study Name".to_string()),
+            study_description: Some("Description".to_string()),
+            organization_id: new_org.id.to_string(),
+        };
+        let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+            .await
+            .unwrap();
+        let other_org = create_organization_service(
+            &db_pool,
+            &valkey_pool,
+            &OrganizationCreate {
+                name: Uuid::new_v4().to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let assigned_user = create_user_in(&db_pool, &valkey_pool, other_org.id.as_str()).await;
+        add_user_to_study_service(&db_pool, &valkey_pool, &assigned_user.id, &study.id)
+            .await
+            .unwrap();
+        // Caches the user with the study in its list
+        let cached_user = get_user_service(&db_pool, &valkey_pool, &assigned_user.id, false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached_user.studies.map(|s| s.len()), Some(1));
+
+        let app = test_app(&db_pool).await;
         let response = app
             .oneshot(
                 Request::builder()
-                    .method(http::Method::POST)
-                    .uri("/api/user")
-                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
-                    .body(Body::from(
-                        serde_json::to_vec(&json!({
-                            "user_name": user_name,
-                            "first_name": "Arthur",
-                            "last_name": "Dent",
-                            "email": "arthur@heartofgold.com",
-                            "password": "Somepassword1!",
-                            "organization_id": organization.id,
-                        }))
-                        .unwrap(),
-                    ))
+                    .method(http::Method::DELETE)
+                    .uri(&format!("/api/organization/{}?cascade=true", &new_org.id))
+                    .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
 
-        let body = response.into_body().collect().await.unwrap().to_bytes();
-        let body: User = serde_json::from_slice(&body).unwrap();
+        let deleted_at =
+            sqlx::query_scalar!("SELECT deleted_at FROM studies WHERE id = $1", &study.id,)
+                .fetch_one(&db_pool)
+                .await
+                .unwrap();
 
-        assert_eq!(body.user_name, user_name);
+        assert!(deleted_at.is_some());
+        assert!(get_study_service(&db_pool, &valkey_pool, &study.id, false)
+            .await
+            .unwrap()
+            .is_none());
+
+        let user = get_user_service(&db_pool, &valkey_pool, &assigned_user.id, false)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(user.studies.is_none());
     }
 
     #[tokio::test]
-    async fn delete_user() {
-        let app = app(&config()).await;
-        let db_client = db_client();
-        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+    async fn create_in_deleted_organization() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
         let valkey_pool = valkey_pool().await;
         let create_org = OrganizationCreate {
             name: Uuid::new_v4().to_string(),
@@ -564,61 +848,70 @@ mod tests {
         let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
             .await
             .unwrap();
+        delete_organization_service(&db_pool, &valkey_pool, &organization.id, false)
+            .await
+            .unwrap();
+        // As if the creates had read the organization from the cache before the delete
+        add_cached_value(&valkey_pool, &organization).await.unwrap();
+
+        let study_create = StudyCreate {
+            study_id: Uuid::new_v4().to_string(),
+            study_name: None,
+            study_description: None,
+            organization_id: organization.id.to_string(),
+        };
+        let err = create_study_service(&db_pool, &valkey_pool, &study_create)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("No organization with id {} found", organization.id)
+        );
+
         let user_create = UserCreate {
             user_name: Uuid::new_v4().to_string(),
             first_name: "Imma".to_string(),
             last_name: "Person".to_string(),
             email: "some@email.com".to_string(),
+            phone: None,
             password: "Somepassword1!".to_string(),
-            organization_id: organization.id,
+            organization_id: organization.id.to_string(),
         };
-        let user = create_user_service(&db_pool, &valkey_pool, &user_create)
+        let err = create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("No organization with id {} found", organization.id)
+        );
+
+        delete_cached_value::<Organization>(&valkey_pool, organization.id.as_str())
             .await
             .unwrap();
+    }
 
+    #[tokio::test]
+    async fn delete_organization_not_found() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let org_id = generate_db_id();
+        let app = test_app(&db_pool).await;
         let response = app
             .oneshot(
                 Request::builder()
                     .method(http::Method::DELETE)
-                    .uri(&format!("/api/user/{}", &user.id))
+                    .uri(&format!("/api/organization/{}", &org_id))
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::NO_CONTENT);
-
-        let result = sqlx::query_as!(
-            UserInDb,
-            r#"
-                SELECT
-                    id,
-                    user_name,
-                    first_name,
-                    last_name,
-                    email,
-                    hashed_password,
-                    organization_id,
-                    active,
-                    access_level AS "access_level: AccessLevel",
-                    date_added,
-                    date_modified
-                FROM users
-                WHERE id = $1
-            "#,
-            &user.id,
-        )
-        .fetch_optional(&db_pool)
-        .await
-        .unwrap();
-
-        assert!(result.is_none());
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
-    async fn get_user() {
-        let app = app(&config()).await;
+    async fn delete_organization_idempotent() {
         let db_client = db_client();
         let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
         let valkey_pool = valkey_pool().await;
@@ -628,22 +921,54 @@ mod tests {
         let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
             .await
             .unwrap();
-        let user_create = UserCreate {
-            user_name: Uuid::new_v4().to_string(),
-            first_name: "Imma".to_string(),
-            last_name: "Person".to_string(),
-            email: "some@email.com".to_string(),
-            password: "Somepassword1!".to_string(),
-            organization_id: organization.id,
+        let mut config = config();
+        config.idempotent_deletes = true;
+        let delete = |id: String| {
+            let config = config.clone();
+            async move {
+                app(&config)
+                    .await
+                    .oneshot(
+                        Request::builder()
+                            .method(http::Method::DELETE)
+                            .uri(&format!("/api/organization/{id}"))
+                            .body(Body::empty())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap()
+                    .status()
+            }
         };
-        let user = create_user_service(&db_pool, &valkey_pool, &user_create)
+
+        assert_eq!(
+            delete(organization.id.to_string()).await,
+            StatusCode::NO_CONTENT
+        );
+        // Repeating the delete, or deleting something that never existed, is a no-op
+        assert_eq!(
+            delete(organization.id.to_string()).await,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(delete(generate_db_id()).await, StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn get_organization() {
+        let org_name = Uuid::new_v4().to_string();
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate { name: org_name };
+        let new_org = create_organization_service(&db_pool, &valkey_pool, &create_org)
             .await
             .unwrap();
 
+        let app = test_app(&db_pool).await;
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri(&format!("/api/user/{}", &user.id))
+                    .uri(&format!("/api/organization/{}", &new_org.id))
                     .body(Body::empty())
                     .unwrap(),
             )
@@ -653,72 +978,406 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
 
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        let body: User = serde_json::from_slice(&body).unwrap();
+        let body: Organization = serde_json::from_slice(&body).unwrap();
 
-        assert_eq!(body.user_name, user_create.user_name);
+        assert_eq!(body.name, create_org.name);
     }
 
     #[tokio::test]
-    async fn get_user_not_found() {
-        let user_id = generate_db_id();
-        let app = app(&config()).await;
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .uri(&format!("/api/user/{}", &user_id))
-                    .body(Body::empty())
+    async fn get_organization_users() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        let mut organizations = Vec::new();
+        for _ in 0..2 {
+            let create_org = OrganizationCreate {
+                name: Uuid::new_v4().to_string(),
+            };
+            organizations.push(
+                create_organization_service(&db_pool, &valkey_pool, &create_org)
+                    .await
                     .unwrap(),
-            )
-            .await
-            .unwrap();
+            );
+        }
+        let mut user_ids = Vec::new();
+        for _ in 0..2 {
+            let user = create_user_in(&db_pool, &valkey_pool, organizations[0].id.as_str()).await;
+            user_ids.push(user.id);
+        }
+        let other = create_user_in(&db_pool, &valkey_pool, organizations[1].id.as_str()).await;
+        let get_users = |organization_id: String| {
+            let db_pool = db_pool.clone();
+            async move {
+                let response = test_app(&db_pool)
+                    .await
+                    .oneshot(
+                        Request::builder()
+                            .uri(&format!("/api/organization/{organization_id}/users"))
+                            .body(Body::empty())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                let status = response.status();
+                let body = response.into_body().collect().await.unwrap().to_bytes();
 
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+                (status, serde_json::from_slice::<Value>(&body).unwrap())
+            }
+        };
+
+        let (status, body) = get_users(organizations[0].id.to_string()).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total"], json!(2));
+
+        let mut ids: Vec<String> = body["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|u| u["id"].as_str().unwrap().to_string())
+            .collect();
+        ids.sort();
+        user_ids.sort();
+
+        assert_eq!(ids, user_ids);
+
+        let (status, body) = get_users(organizations[1].id.to_string()).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total"], json!(1));
+        assert_eq!(body["items"][0]["id"], json!(other.id));
+
+        let (status, _) = get_users(Uuid::new_v4().to_string()).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
-    async fn add_user_to_study() {
-        let app = app(&config()).await;
-        let db_client = db_client();
-        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+    async fn get_organization_corrupt_cache() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
         let valkey_pool = valkey_pool().await;
         let create_org = OrganizationCreate {
             name: Uuid::new_v4().to_string(),
         };
-        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+        let new_org = create_organization_service(&db_pool, &valkey_pool, &create_org)
             .await
             .unwrap();
-        let user_create = UserCreate {
-            user_name: Uuid::new_v4().to_string(),
-            first_name: "Imma".to_string(),
-            last_name: "Person".to_string(),
-            email: "some@email.com".to_string(),
-            password: "Somepassword1!".to_string(),
-            organization_id: organization.id.clone(),
-        };
-        let user = create_user_service(&db_pool, &valkey_pool, &user_create)
+        let mut conn = valkey_pool.get().await.unwrap();
+        let _: () = redis::cmd("HSET")
+            .arg("organizations")
+            .arg(new_org.id.as_str())
+            .arg("{not json")
+            .query_async(&mut *conn)
+            .await
+            .unwrap();
+        drop(conn);
+
+        let response = test_app(&db_pool)
+            .await
+            .oneshot(
+                Request::builder()
+                    .uri(&format!("/api/organization/{}", &new_org.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Organization = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body.name, create_org.name);
+    }
+
+    #[tokio::test]
+    async fn get_organization_not_found() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let org_id = generate_db_id();
+        let app = test_app(&db_pool).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(&format!("/api/organization/{}", &org_id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn get_organizations() {
+        let org_name = Uuid::new_v4().to_string();
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate { name: org_name };
+        create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+
+        let app = test_app(&db_pool).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/organization")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: ListResponse<Organization> = serde_json::from_slice(&body).unwrap();
+        println!("{:?}", body);
+
+        assert!(body.total >= 1);
+        assert_eq!(body.limit, i64::from(config().default_page_size));
+        assert_eq!(body.offset, 0);
+        assert!(body.items.iter().any(|item| item.name == create_org.name));
+        assert!(body
+            .items
+            .windows(2)
+            .all(|w| w[0].date_added <= w[1].date_added));
+    }
+
+    #[tokio::test]
+    async fn get_organizations_sorted() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        for _ in 0..2 {
+            create_organization_service(
+                &db_pool,
+                &valkey_pool,
+                &OrganizationCreate {
+                    name: Uuid::new_v4().to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let app = test_app(&db_pool).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/organization?sort=date_added&dir=desc")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: ListResponse<Organization> = serde_json::from_slice(&body).unwrap();
+
+        assert!(body.items.len() >= 2);
+        assert!(body
+            .items
+            .windows(2)
+            .all(|w| w[0].date_added >= w[1].date_added));
+    }
+
+    #[tokio::test]
+    async fn get_organizations_invalid_sort() {
+        let app = app(&config()).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/organization?sort=password&dir=asc")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn get_organizations_batch() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        let cached = create_organization_service(
+            &db_pool,
+            &valkey_pool,
+            &OrganizationCreate {
+                name: Uuid::new_v4().to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let uncached = create_organization_service(
+            &db_pool,
+            &valkey_pool,
+            &OrganizationCreate {
+                name: Uuid::new_v4().to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        delete_cached_value::<Organization>(&valkey_pool, uncached.id.as_str())
+            .await
+            .unwrap();
+        let body = serde_json::to_vec(&json!({
+            "ids": [cached.id, Uuid::new_v4().to_string(), uncached.id],
+        }))
+        .unwrap();
+
+        let response = test_app(&db_pool)
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/organization/batch")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Vec<Organization> = serde_json::from_slice(&body).unwrap();
+        let ids: Vec<&str> = body.iter().map(|o| o.id.as_str()).collect();
+
+        assert_eq!(ids, vec![cached.id.as_str(), uncached.id.as_str()]);
+        assert!(
+            get_cached_value::<Organization>(&valkey_pool, uncached.id.as_str())
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn get_organizations_batch_too_many() {
+        let ids: Vec<String> = (0..201).map(|_| Uuid::new_v4().to_string()).collect();
+        let body = serde_json::to_vec(&json!({ "ids": ids })).unwrap();
+
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/organization/batch")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn get_organizations_invalid_limit() {
+        let app = app(&config()).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/organization?limit=0")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn update_organization() {
+        let org_name = Uuid::new_v4().to_string();
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let app = test_app(&db_pool).await;
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate { name: org_name };
+        let new_org = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+
+        let updated_name = Uuid::new_v4().to_string();
+        let active = false;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::PUT)
+                    .uri("/api/organization")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(
+                            &json!({"id": new_org.id, "name": updated_name, "active": active }),
+                        )
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Organization = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body.name, updated_name);
+        assert_eq!(body.active, active);
+    }
+
+    #[tokio::test]
+    async fn update_organization_refreshes_cached_study() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
             .await
             .unwrap();
         let study_create = StudyCreate {
             study_id: Uuid::new_v4().to_string(),
-            study_name: Some("Study Name".to_string()),
-            study_description: Some("Description".to_string()),
-            organization_id: organization.id.clone(),
+            study_name: None,
+            study_description: None,
+            organization_id: organization.id.to_string(),
         };
         let study = create_study_service(&db_pool, &valkey_pool, &study_create)
             .await
             .unwrap();
+        assert!(get_cached_value::<Study>(&valkey_pool, &study.id)
+            .await
+            .unwrap()
+            .is_some());
 
-        let response = app
+        let updated_name = Uuid::new_v4().to_string();
+        let response = test_app(&db_pool)
+            .await
             .oneshot(
                 Request::builder()
-                    .method(http::Method::POST)
-                    .uri("/api/user/study")
+                    .method(http::Method::PUT)
+                    .uri("/api/organization")
                     .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
                     .body(Body::from(
-                        serde_json::to_vec(&json!({
-                            "user_id": user.id,
-                            "study_id": study.id,
-                        }))
+                        serde_json::to_vec(
+                            &json!({"id": organization.id, "name": updated_name, "active": true }),
+                        )
                         .unwrap(),
                     ))
                     .unwrap(),
@@ -728,12 +1387,3352 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::OK);
 
+        let response = test_app(&db_pool)
+            .await
+            .oneshot(
+                Request::builder()
+                    .uri(&format!("/api/study/{}", &study.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        let body: User = serde_json::from_slice(&body).unwrap();
+        let body: Study = serde_json::from_slice(&body).unwrap();
 
-        assert_eq!(body.id, user.id);
-        assert!(body.studies.is_some());
-        let studies_test = body.studies.unwrap();
-        assert_eq!(studies_test.len(), 1);
+        assert_eq!(body.organization.name, updated_name);
+    }
+
+    #[tokio::test]
+    async fn create_study() {
+        let app = app(&config()).await;
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let study_id = Uuid::new_v4().to_string();
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/study")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "study_id": study_id,
+                            "study_name": "Test Study",
+                            "study_description": "Description",
+                            "organization_id": organization.id,
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Study = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body.study_id, study_id);
+    }
+
+    #[tokio::test]
+    async fn create_study_organization_not_found() {
+        let app = app(&config()).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/study")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "study_id": Uuid::new_v4().to_string(),
+                            "study_name": "Test Study",
+                            "study_description": "Description",
+                            "organization_id": Uuid::new_v4().to_string(),
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn create_study_blank_study_id() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+
+        let app = app(&config()).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/study")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "study_id": "",
+                            "study_name": "a".repeat(1000),
+                            "study_description": "Description",
+                            "organization_id": organization.id,
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let detail = body["detail"].as_str().unwrap();
+
+        assert!(detail.contains("study_id must not be blank"));
+        assert!(detail.contains("study_name must be at most"));
+    }
+
+    #[tokio::test]
+    async fn delete_study() {
+        let app = app(&config()).await;
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let study_create = StudyCreate {
+            study_id: Uuid::new_v4().to_string(),
+            study_name: Some("Study Name".to_string()),
+            study_description: Some("Description".to_string()),
+            organization_id: organization.id.to_string(),
+        };
+        let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+            .await
+            .unwrap();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::DELETE)
+                    .uri(&format!("/api/study/{}", &study.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let result = sqlx::query_as!(
+            StudyInDb,
+            r#"
+                SELECT
+                    id,
+                    study_id,
+                    study_name,
+                    study_description,
+                    organization_id,
+                    locked,
+                    protocol_version,
+                    date_added,
+                    date_modified
+                FROM studies
+                WHERE id = $1
+            "#,
+            &study.id,
+        )
+        .fetch_optional(&db_pool)
+        .await
+        .unwrap();
+
+        assert!(result.is_none());
+    }
+
+    #[tokio::test]
+    async fn get_study() {
+        let app = app(&config()).await;
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let study_create = StudyCreate {
+            study_id: Uuid::new_v4().to_string(),
+            study_name: Some("Study Name".to_string()),
+            study_description: Some("Description".to_string()),
+            organization_id: organization.id.to_string(),
+        };
+        let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+            .await
+            .unwrap();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(&format!("/api/study/{}", &study.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Study = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body.study_id, study_create.study_id);
+    }
+
+    #[tokio::test]
+    async fn get_study_not_found() {
+        let study_id = generate_db_id();
+        let app = app(&config()).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(&format!("/api/study/{}", &study_id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn lock_study() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let study_create = StudyCreate {
+            study_id: Uuid::new_v4().to_string(),
+            study_name: Some("Study Name".to_string()),
+            study_description: Some("Description".to_string()),
+            organization_id: organization.id.to_string(),
+        };
+        let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+            .await
+            .unwrap();
+        let update_body = serde_json::to_vec(&json!({
+            "id": study.id,
+            "study_id": study.study_id,
+            "study_name": "Updated Name",
+            "study_description": "Description",
+            "organization_id": organization.id,
+        }))
+        .unwrap();
+
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri(&format!("/api/study/{}/lock", &study.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Study = serde_json::from_slice(&body).unwrap();
+        assert!(body.locked);
+
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::PUT)
+                    .uri("/api/study")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(update_body.clone()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::LOCKED);
+
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri(&format!("/api/study/{}/unlock", &study.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::PUT)
+                    .uri("/api/study")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(update_body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn create_user() {
+        let app = app(&config()).await;
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let user_name = Uuid::new_v4().to_string();
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/user")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "user_name": user_name,
+                            "first_name": "Arthur",
+                            "last_name": "Dent",
+                            "email": "arthur@heartofgold.com",
+                            "password": "Somepassword1!",
+                            "organization_id": organization.id,
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: User = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body.user_name, user_name);
+    }
+
+    #[tokio::test]
+    async fn create_user_phone() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let user_name = Uuid::new_v4().to_string();
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/user")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "user_name": user_name,
+                            "first_name": "Arthur",
+                            "last_name": "Dent",
+                            "email": "arthur@heartofgold.com",
+                            "phone": "+15555550123",
+                            "password": "Somepassword1!",
+                            "organization_id": organization.id,
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: User = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body.phone, Some("+15555550123".to_string()));
+    }
+
+    #[tokio::test]
+    async fn create_user_invalid_phone() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/user")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "user_name": Uuid::new_v4().to_string(),
+                            "first_name": "Arthur",
+                            "last_name": "Dent",
+                            "email": "arthur@heartofgold.com",
+                            "phone": "555-0123",
+                            "password": "Somepassword1!",
+                            "organization_id": organization.id,
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn create_user_normalizes_email() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let user_create = UserCreate {
+            user_name: Uuid::new_v4().to_string(),
+            first_name: "Arthur".to_string(),
+            last_name: "Dent".to_string(),
+            email: " Arthur@HeartOfGold.com ".to_string(),
+            phone: None,
+            password: "Somepassword1!".to_string(),
+            organization_id: organization.id.to_string(),
+        };
+        let user = create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+            .await
+            .unwrap();
+
+        assert_eq!(user.email, "arthur@heartofgold.com");
+    }
+
+    #[tokio::test]
+    async fn delete_user() {
+        let app = app(&config()).await;
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let user_create = UserCreate {
+            user_name: Uuid::new_v4().to_string(),
+            first_name: "Imma".to_string(),
+            last_name: "Person".to_string(),
+            email: "some@email.com".to_string(),
+            phone: None,
+            password: "Somepassword1!".to_string(),
+            organization_id: organization.id.to_string(),
+        };
+        let user = create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+            .await
+            .unwrap();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::DELETE)
+                    .uri(&format!("/api/user/{}", &user.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let result = sqlx::query_as!(
+            UserInDb,
+            r#"
+                SELECT
+                    id,
+                    user_name,
+                    first_name,
+                    last_name,
+                    email,
+                    phone,
+                    hashed_password,
+                    organization_id,
+                    active,
+                    access_level AS "access_level: AccessLevel",
+                    date_added,
+                    date_modified
+                FROM users
+                WHERE id = $1
+            "#,
+            &user.id,
+        )
+        .fetch_optional(&db_pool)
+        .await
+        .unwrap();
+
+        assert!(result.is_none());
+    }
+
+    #[tokio::test]
+    async fn get_user() {
+        let app = app(&config()).await;
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let user_create = UserCreate {
+            user_name: Uuid::new_v4().to_string(),
+            first_name: "Imma".to_string(),
+            last_name: "Person".to_string(),
+            email: "some@email.com".to_string(),
+            phone: None,
+            password: "Somepassword1!".to_string(),
+            organization_id: organization.id.to_string(),
+        };
+        let user = create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+            .await
+            .unwrap();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(&format!("/api/user/{}", &user.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: User = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body.user_name, user_create.user_name);
+    }
+
+    #[tokio::test]
+    async fn get_user_studies() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let user_create = UserCreate {
+            user_name: Uuid::new_v4().to_string(),
+            first_name: "Imma".to_string(),
+            last_name: "Person".to_string(),
+            email: "some@email.com".to_string(),
+            phone: None,
+            password: "Somepassword1!".to_string(),
+            organization_id: organization.id.to_string(),
+        };
+        let user = create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            let study_create = StudyCreate {
+                study_id: Uuid::new_v4().to_string(),
+                study_name: Some("Study Name".to_string()),
+                study_description: Some("Description".to_string()),
+                organization_id: organization.id.to_string(),
+            };
+            let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+                .await
+                .unwrap();
+            add_user_to_study_service(&db_pool, &valkey_pool, &user.id, &study.id)
+                .await
+                .unwrap();
+        }
+
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .uri(&format!("/api/user/{}/studies?limit=1", &user.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: ListResponse<Study> = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body.total, 2);
+        assert_eq!(body.items.len(), 1);
+
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .uri(&format!("/api/user/{}", &user.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: User = serde_json::from_slice(&body).unwrap();
+
+        assert!(body.studies.is_none());
+
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .uri(&format!("/api/user/{}?include=studies", &user.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: User = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body.studies.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn get_user_studies_not_found() {
+        let app = app(&config()).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(&format!("/api/user/{}/studies", Uuid::new_v4()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn get_user_fields() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let user_create = UserCreate {
+            user_name: Uuid::new_v4().to_string(),
+            first_name: "Imma".to_string(),
+            last_name: "Person".to_string(),
+            email: "some@email.com".to_string(),
+            phone: None,
+            password: "Somepassword1!".to_string(),
+            organization_id: organization.id.to_string(),
+        };
+        let user = create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+            .await
+            .unwrap();
+
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .uri(&format!("/api/user/{}?fields=id,user_name,email", &user.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(
+            body,
+            json!({"id": user.id, "user_name": user.user_name, "email": user.email})
+        );
+
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .uri(&format!("/api/user/{}?fields=id,password", &user.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn deactivate_user() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let user_create = UserCreate {
+            user_name: Uuid::new_v4().to_string(),
+            first_name: "Imma".to_string(),
+            last_name: "Person".to_string(),
+            email: "some@email.com".to_string(),
+            phone: None,
+            password: "Somepassword1!".to_string(),
+            organization_id: organization.id.to_string(),
+        };
+        let user = create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+            .await
+            .unwrap();
+        assert!(user.active);
+
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri(&format!("/api/user/{}/deactivate", &user.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: User = serde_json::from_slice(&body).unwrap();
+        assert!(!body.active);
+
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri(&format!("/api/user/{}/activate", &user.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: User = serde_json::from_slice(&body).unwrap();
+        assert!(body.active);
+    }
+
+    #[tokio::test]
+    async fn deactivate_user_not_found() {
+        let app = app(&config()).await;
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/user/bad/deactivate")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn get_users() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let app = test_app(&db_pool).await;
+        let valkey_pool = valkey_pool().await;
+        let mut expected: Vec<(User, Option<Study>)> = Vec::new();
+
+        for _ in 0..2 {
+            let create_org = OrganizationCreate {
+                name: Uuid::new_v4().to_string(),
+            };
+            let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+                .await
+                .unwrap();
+            let study_create = StudyCreate {
+                study_id: Uuid::new_v4().to_string(),
+                study_name: Some("Study Name".to_string()),
+                study_description: Some("Description".to_string()),
+                organization_id: organization.id.to_string(),
+            };
+            let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+                .await
+                .unwrap();
+
+            for with_study in [true, false] {
+                let user_create = UserCreate {
+                    user_name: Uuid::new_v4().to_string(),
+                    first_name: "Imma".to_string(),
+                    last_name: "Person".to_string(),
+                    email: "some@email.com".to_string(),
+                    phone: None,
+                    password: "Somepassword1!".to_string(),
+                    organization_id: organization.id.to_string(),
+                };
+                let user =
+                    create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+                        .await
+                        .unwrap();
+
+                if with_study {
+                    add_user_to_study_service(&db_pool, &valkey_pool, &user.id, &study.id)
+                        .await
+                        .unwrap();
+                    expected.push((user, Some(study.clone())));
+                } else {
+                    expected.push((user, None));
+                }
+            }
+        }
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/user")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: ListResponse<User> = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body.items.len(), expected.len());
+
+        for (user, study) in expected.iter() {
+            let found = body.items.iter().find(|u| u.id == user.id).unwrap();
+            assert_eq!(found.organization.id, user.organization.id);
+            match study {
+                Some(s) => {
+                    let studies = found.studies.as_ref().unwrap();
+                    assert_eq!(studies.len(), 1);
+                    assert_eq!(studies[0].id, s.id);
+                    assert_eq!(studies[0].organization.id, user.organization.id);
+                }
+                None => assert!(found.studies.is_none()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn get_user_not_found() {
+        let user_id = generate_db_id();
+        let app = app(&config()).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(&format!("/api/user/{}", &user_id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn get_user_organization_missing() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let user = create_user_in(&db_pool, &valkey_pool, organization.id.as_str()).await;
+        // The organization goes away without taking the user with it, as when it's deleted
+        // while the user is being read
+        sqlx::query!(
+            "UPDATE organizations SET deleted_at = now() WHERE id = $1",
+            organization.id.as_str(),
+        )
+        .execute(&db_pool)
+        .await
+        .unwrap();
+        delete_cached_value::<Organization>(&valkey_pool, organization.id.as_str())
+            .await
+            .unwrap();
+        delete_cached_value::<User>(&valkey_pool, &user.id)
+            .await
+            .unwrap();
+
+        let response = test_app(&db_pool)
+            .await
+            .oneshot(
+                Request::builder()
+                    .uri(&format!("/api/user/{}", &user.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn add_user_to_study() {
+        let app = app(&config()).await;
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let user_create = UserCreate {
+            user_name: Uuid::new_v4().to_string(),
+            first_name: "Imma".to_string(),
+            last_name: "Person".to_string(),
+            email: "some@email.com".to_string(),
+            phone: None,
+            password: "Somepassword1!".to_string(),
+            organization_id: organization.id.to_string(),
+        };
+        let user = create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+            .await
+            .unwrap();
+        let study_create = StudyCreate {
+            study_id: Uuid::new_v4().to_string(),
+            study_name: Some("Study Name".to_string()),
+            study_description: Some("Description".to_string()),
+            organization_id: organization.id.to_string(),
+        };
+        let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+            .await
+            .unwrap();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/user/study")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "user_id": user.id,
+                            "study_id": study.id,
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: User = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body.id, user.id);
+        assert!(body.studies.is_some());
+        let studies_test = body.studies.unwrap();
+        assert_eq!(studies_test.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn create_webhook() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let config = admin_config();
+        let response = test_app_with(&config, &db_pool)
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/webhook")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .header(
+                        http::header::AUTHORIZATION,
+                        format!("Bearer {ADMIN_API_KEY}"),
+                    )
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "url": "https://203.0.113.10/hook",
+                            "secret": "secret",
+                            "event_types": ["study_created", "study_deleted"],
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        let webhook: Webhook = serde_json::from_slice(&body).unwrap();
+
+        assert!(!text.contains("secret"));
+        assert_eq!(webhook.event_types, vec!["study_created", "study_deleted"]);
+
+        let response = test_app_with(&config, &db_pool)
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::DELETE)
+                    .uri(format!("/api/webhook/{}", webhook.id))
+                    .header(
+                        http::header::AUTHORIZATION,
+                        format!("Bearer {ADMIN_API_KEY}"),
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    async fn post_webhook(config: &Config, authorization: Option<&str>, url: &str) -> StatusCode {
+        let mut request = Request::builder()
+            .method(http::Method::POST)
+            .uri("/api/webhook")
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref());
+        if let Some(a) = authorization {
+            request = request.header(http::header::AUTHORIZATION, a);
+        }
+
+        app(config)
+            .await
+            .oneshot(
+                request
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "url": url,
+                            "secret": "secret",
+                            "event_types": ["study_created"],
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn create_webhook_invalid_url() {
+        let config = admin_config();
+        let authorization = format!("Bearer {ADMIN_API_KEY}");
+
+        for url in [
+            "ftp://example.com",
+            "http://127.0.0.1:3000/api/admin/maintenance",
+            "http://169.254.169.254/latest/meta-data",
+            "http://10.0.0.5/hook",
+            "http://localhost/hook",
+        ] {
+            let status = post_webhook(&config, Some(&authorization), url).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{url}");
+        }
+    }
+
+    #[tokio::test]
+    async fn create_webhook_requires_admin() {
+        let url = "https://203.0.113.10/hook";
+
+        let status = post_webhook(&admin_config(), None, url).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let status = post_webhook(&admin_config(), Some("Bearer wrong-key"), url).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let authorization = format!("Bearer {ADMIN_API_KEY}");
+        let status = post_webhook(&config(), Some(&authorization), url).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn create_study_dispatches_webhook() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let config = config();
+        let valkey_pool = valkey_pool().await;
+        let organization = create_organization_service(
+            &db_pool,
+            &valkey_pool,
+            &OrganizationCreate {
+                name: Uuid::new_v4().to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let webhook = create_webhook_service(
+            &db_pool,
+            &WebhookCreate {
+                url: "https://203.0.113.10/hook".to_string(),
+                secret: "secret".to_string(),
+                event_types: vec![WebhookEvent::StudyCreated],
+            },
+        )
+        .await
+        .unwrap();
+
+        let recorder = Arc::new(RecordingWebhookClient::default());
+        let mut state = test_state(&config, &db_pool).await;
+        state.webhook_client = recorder.clone();
+        let app = router(Arc::new(state), &config);
+        let study_id = Uuid::new_v4().to_string();
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/study")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "study_id": study_id,
+                            "study_name": "Test Study",
+                            "study_description": "Description",
+                            "organization_id": organization.id,
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let mut delivery = None;
+        for _ in 0..50 {
+            delivery = recorder
+                .deliveries
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|(url, _, _)| url == &webhook.url)
+                .cloned();
+            if delivery.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        let (_, body, signature) = delivery.expect("Webhook was not delivered");
+        let payload: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(signature, sign_payload("secret", &body));
+        assert_eq!(payload["event"], json!("study_created"));
+        assert_eq!(payload["data"]["study_id"], json!(study_id));
+    }
+
+    #[tokio::test]
+    async fn study_events() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let organization = create_organization_service(
+            &db_pool,
+            &valkey_pool,
+            &OrganizationCreate {
+                name: Uuid::new_v4().to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let study = create_study_service(
+            &db_pool,
+            &valkey_pool,
+            &StudyCreate {
+                study_id: Uuid::new_v4().to_string(),
+                study_name: Some("Study Name".to_string()),
+                study_description: Some("Description".to_string()),
+                organization_id: organization.id.to_string(),
+            },
+        )
+        .await
+        .unwrap();
+
+        let app = app(&config()).await;
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/study/{}/events", &study.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[http::header::CONTENT_TYPE],
+            mime::TEXT_EVENT_STREAM.as_ref()
+        );
+
+        let response_lock = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri(format!("/api/study/{}/lock", &study.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response_lock.status(), StatusCode::OK);
+
+        let mut body = response.into_body();
+        let frame = tokio::time::timeout(Duration::from_secs(5), body.frame())
+            .await
+            .expect("No event received")
+            .unwrap()
+            .unwrap();
+        let text = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+
+        assert!(text.contains("event: study_locked"));
+        assert!(text.contains(&study.id));
+    }
+
+    #[tokio::test]
+    async fn study_events_not_found() {
+        let app = app(&config()).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/study/{}/events", Uuid::new_v4()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn cors_preflight() {
+        let mut config = config();
+        config.cors_allowed_origins = vec!["https://example.com".to_string()];
+        config.cors_allow_credentials = true;
+        config.cors_max_age_secs = 600;
+        let app = app(&config).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::OPTIONS)
+                    .uri("/api/organization")
+                    .header(http::header::ORIGIN, "https://example.com")
+                    .header(http::header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let headers = response.headers();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(headers[http::header::ACCESS_CONTROL_MAX_AGE], "600");
+        assert_eq!(
+            headers[http::header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://example.com"
+        );
+        assert_eq!(
+            headers[http::header::ACCESS_CONTROL_ALLOW_CREDENTIALS],
+            "true"
+        );
+    }
+
+    #[tokio::test]
+    async fn cors_disabled_by_default() {
+        let mut config = config();
+        config.cors_allowed_origins = Vec::new();
+        let app = app(&config).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/health")
+                    .header(http::header::ORIGIN, "https://example.com")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert!(!response
+            .headers()
+            .contains_key(http::header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn user_remove_all_studies() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let user_create = UserCreate {
+            user_name: Uuid::new_v4().to_string(),
+            first_name: "Imma".to_string(),
+            last_name: "Person".to_string(),
+            email: "some@email.com".to_string(),
+            phone: None,
+            password: "Somepassword1!".to_string(),
+            organization_id: organization.id.to_string(),
+        };
+        let user = create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            let study_create = StudyCreate {
+                study_id: Uuid::new_v4().to_string(),
+                study_name: Some("Study Name".to_string()),
+                study_description: Some("Description".to_string()),
+                organization_id: organization.id.to_string(),
+            };
+            let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+                .await
+                .unwrap();
+            add_user_to_study_service(&db_pool, &valkey_pool, &user.id, &study.id)
+                .await
+                .unwrap();
+        }
+
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::DELETE)
+                    .uri(&format!("/api/user/{}/studies", &user.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body["removed"], json!(3));
+
+        let remaining = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM user_studies WHERE user_id = $1"#,
+            user.id,
+        )
+        .fetch_one(&db_pool)
+        .await
+        .unwrap();
+
+        assert_eq!(remaining, 0);
+    }
+
+    #[tokio::test]
+    async fn user_remove_all_studies_not_found() {
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::DELETE)
+                    .uri(&format!("/api/user/{}/studies", Uuid::new_v4()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    async fn create_user_in(
+        db_pool: &PgPool,
+        valkey_pool: &Pool<RedisConnectionManager>,
+        organization_id: &str,
+    ) -> User {
+        let user_create = UserCreate {
+            user_name: Uuid::new_v4().to_string(),
+            first_name: "Imma".to_string(),
+            last_name: "Person".to_string(),
+            email: "some@email.com".to_string(),
+            phone: None,
+            password: "Somepassword1!".to_string(),
+            organization_id: organization_id.to_string(),
+        };
+
+        create_user_service(db_pool, valkey_pool, &hash_permits(), &user_create)
+            .await
+            .unwrap()
+    }
+
+    async fn transfer_studies(db_pool: &PgPool, from: &str, to: &str) -> (StatusCode, Value) {
+        let response = test_app(db_pool)
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri(&format!("/api/user/{from}/transfer-studies"))
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({ "target_user_id": to })).unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    async fn user_study_count(db_pool: &PgPool, user_id: &str) -> i64 {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM user_studies WHERE user_id = $1"#,
+            user_id,
+        )
+        .fetch_one(db_pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn user_transfer_studies() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let source = create_user_in(&db_pool, &valkey_pool, organization.id.as_str()).await;
+        let target = create_user_in(&db_pool, &valkey_pool, organization.id.as_str()).await;
+        for i in 0..2 {
+            let study_create = StudyCreate {
+                study_id: Uuid::new_v4().to_string(),
+                study_name: None,
+                study_description: None,
+                organization_id: organization.id.to_string(),
+            };
+            let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+                .await
+                .unwrap();
+            add_user_to_study_service(&db_pool, &valkey_pool, &source.id, &study.id)
+                .await
+                .unwrap();
+            // The target already being on a study doesn't stop the transfer
+            if i == 0 {
+                add_user_to_study_service(&db_pool, &valkey_pool, &target.id, &study.id)
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let (status, body) = transfer_studies(&db_pool, &source.id, &target.id).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["transferred"], json!(2));
+
+        assert_eq!(user_study_count(&db_pool, &source.id).await, 0);
+        assert_eq!(user_study_count(&db_pool, &target.id).await, 2);
+
+        let cached_source = get_cached_value::<User>(&valkey_pool, &source.id)
+            .await
+            .unwrap()
+            .unwrap();
+        let cached_target = get_cached_value::<User>(&valkey_pool, &target.id)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(cached_source.studies.unwrap_or_default().is_empty());
+        assert_eq!(cached_target.studies.unwrap_or_default().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn user_transfer_studies_other_organization() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        let mut organizations = Vec::new();
+        for _ in 0..2 {
+            let create_org = OrganizationCreate {
+                name: Uuid::new_v4().to_string(),
+            };
+            organizations.push(
+                create_organization_service(&db_pool, &valkey_pool, &create_org)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let source = create_user_in(&db_pool, &valkey_pool, organizations[0].id.as_str()).await;
+        let target = create_user_in(&db_pool, &valkey_pool, organizations[1].id.as_str()).await;
+        let study_create = StudyCreate {
+            study_id: Uuid::new_v4().to_string(),
+            study_name: None,
+            study_description: None,
+            organization_id: organizations[0].id.to_string(),
+        };
+        let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+            .await
+            .unwrap();
+        add_user_to_study_service(&db_pool, &valkey_pool, &source.id, &study.id)
+            .await
+            .unwrap();
+
+        let (status, body) = transfer_studies(&db_pool, &source.id, &target.id).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["detail"]
+            .as_str()
+            .unwrap()
+            .contains("are not both in the organization of study"));
+
+        assert_eq!(user_study_count(&db_pool, &source.id).await, 1);
+
+        let (status, _) = transfer_studies(&db_pool, &source.id, &Uuid::new_v4().to_string()).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn search_studies_and_users() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        let token = Uuid::new_v4().simple().to_string();
+        let mut organizations = Vec::new();
+        let mut studies = Vec::new();
+        for i in 0..2 {
+            let create_org = OrganizationCreate {
+                name: Uuid::new_v4().to_string(),
+            };
+            let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+                .await
+                .unwrap();
+            let study_create = StudyCreate {
+                study_id: format!("{token}-{i}"),
+                study_name: None,
+                study_description: None,
+                organization_id: organization.id.to_string(),
+            };
+            studies.push(
+                create_study_service(&db_pool, &valkey_pool, &study_create)
+                    .await
+                    .unwrap(),
+            );
+            organizations.push(organization);
+        }
+        let user_create = UserCreate {
+            user_name: format!("user-{token}"),
+            first_name: "Imma".to_string(),
+            last_name: "Person".to_string(),
+            email: "some@email.com".to_string(),
+            phone: None,
+            password: "Somepassword1!".to_string(),
+            organization_id: organizations[0].id.to_string(),
+        };
+        let user = create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+            .await
+            .unwrap();
+        let search = |query: String| {
+            let db_pool = db_pool.clone();
+            async move {
+                let response = test_app(&db_pool)
+                    .await
+                    .oneshot(
+                        Request::builder()
+                            .uri(&format!("/api/search?{query}"))
+                            .body(Body::empty())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                let status = response.status();
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+
+                (status, serde_json::from_slice::<Value>(&body).unwrap())
+            }
+        };
+
+        // Scoped to the first organization only its study and user match
+        let (status, body) = search(format!(
+            "q={}&organization_id={}",
+            token.to_uppercase(),
+            organizations[0].id
+        ))
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["organizations"], json!([]));
+        assert_eq!(body["studies"].as_array().unwrap().len(), 1);
+        assert_eq!(body["studies"][0]["id"], json!(studies[0].id));
+        assert_eq!(body["users"].as_array().unwrap().len(), 1);
+        assert_eq!(body["users"][0]["id"], json!(user.id));
+
+        let (status, body) = search(format!("q={token}&types=studies")).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["studies"].as_array().unwrap().len(), 2);
+        assert_eq!(body["users"], json!([]));
+
+        let (status, _) = search(format!("q={token}&types=subjects")).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = search("q=%20".to_string()).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn maintenance_blocks_writes() {
+        let mut config = config();
+        config.maintenance_mode = true;
+        let app = app(&config).await;
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/organization")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({ "name": Uuid::new_v4().to_string() })).unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[http::header::RETRY_AFTER], "300");
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri("/api/organization")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn maintenance_allows_batch_fetch() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        let organization = create_organization_service(
+            &db_pool,
+            &valkey_pool,
+            &OrganizationCreate {
+                name: Uuid::new_v4().to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let mut config = config();
+        config.maintenance_mode = true;
+
+        let response = test_app_with(&config, &db_pool)
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/organization/batch")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({ "ids": [organization.id] })).unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Vec<Organization> = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body.len(), 1);
+        assert_eq!(body[0].id, organization.id);
+    }
+
+    fn maintenance_request(enabled: bool, authorization: Option<&str>) -> Request<Body> {
+        let mut request = Request::builder()
+            .method(http::Method::POST)
+            .uri("/api/admin/maintenance")
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref());
+        if let Some(a) = authorization {
+            request = request.header(http::header::AUTHORIZATION, a);
+        }
+
+        request
+            .body(Body::from(
+                serde_json::to_vec(&json!({ "enabled": enabled })).unwrap(),
+            ))
+            .unwrap()
+    }
+
+    fn create_organization_request() -> Request<Body> {
+        Request::builder()
+            .method(http::Method::POST)
+            .uri("/api/organization")
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                serde_json::to_vec(&json!({ "name": Uuid::new_v4().to_string() })).unwrap(),
+            ))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn maintenance_toggle_allowed_during_maintenance() {
+        let mut config = admin_config();
+        config.maintenance_mode = true;
+        let authorization = format!("Bearer {ADMIN_API_KEY}");
+        let response = app(&config)
+            .await
+            .oneshot(maintenance_request(false, Some(&authorization)))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        // MAINTENANCE_MODE keeps it on whatever the toggle says
+        assert_eq!(body, json!({ "enabled": true }));
+    }
+
+    #[tokio::test]
+    async fn maintenance_toggle_blocks_writes() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let config = admin_config();
+        let authorization = format!("Bearer {ADMIN_API_KEY}");
+        let mut state = test_state(&config, &db_pool).await;
+        // The toggle is shared through valkey, a database of its own keeps it from blocking
+        // writes in the tests running alongside this one
+        state.valkey_state.pool = valkey_pool_in(1).await;
+        let app = router(Arc::new(state), &config);
+
+        let response = app
+            .clone()
+            .oneshot(maintenance_request(true, None))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app
+            .clone()
+            .oneshot(maintenance_request(true, Some(&authorization)))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body, json!({ "enabled": true }));
+
+        let response = app
+            .clone()
+            .oneshot(create_organization_request())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let response = app
+            .clone()
+            .oneshot(maintenance_request(false, Some(&authorization)))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body, json!({ "enabled": false }));
+
+        let response = app.oneshot(create_organization_request()).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    async fn get_count(db_pool: &PgPool, uri: &str) -> i64 {
+        let response = test_app(db_pool)
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        body["count"].as_i64().unwrap()
+    }
+
+    #[tokio::test]
+    async fn organization_count() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+
+        assert_eq!(get_count(&db_pool, "/api/organization/count").await, 0);
+
+        for _ in 0..3 {
+            let create_org = OrganizationCreate {
+                name: Uuid::new_v4().to_string(),
+            };
+            create_organization_service(&db_pool, &valkey_pool, &create_org)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(get_count(&db_pool, "/api/organization/count").await, 3);
+    }
+
+    #[tokio::test]
+    async fn study_count() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+
+        assert_eq!(get_count(&db_pool, "/api/study/count").await, 0);
+
+        for _ in 0..3 {
+            let study_create = StudyCreate {
+                study_id: Uuid::new_v4().to_string(),
+                study_name: Some("Study Name".to_string()),
+                study_description: Some("Description".to_string()),
+                organization_id: organization.id.to_string(),
+            };
+            create_study_service(&db_pool, &valkey_pool, &study_create)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(get_count(&db_pool, "/api/study/count").await, 3);
+    }
+
+    #[tokio::test]
+    async fn user_count() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+
+        assert_eq!(get_count(&db_pool, "/api/user/count").await, 0);
+
+        for _ in 0..3 {
+            let user_create = UserCreate {
+                user_name: Uuid::new_v4().to_string(),
+                first_name: "Imma".to_string(),
+                last_name: "Person".to_string(),
+                email: "some@email.com".to_string(),
+                phone: None,
+                password: "Somepassword1!".to_string(),
+                organization_id: organization.id.to_string(),
+            };
+            create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(get_count(&db_pool, "/api/user/count").await, 3);
+    }
+
+    #[tokio::test]
+    async fn create_organization_caches_response() {
+        let valkey_pool = valkey_pool().await;
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/organization")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({ "name": Uuid::new_v4().to_string() })).unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let cached = get_cached_value::<Organization>(&valkey_pool, body["id"].as_str().unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(serde_json::to_value(&cached).unwrap(), body);
+    }
+
+    #[tokio::test]
+    async fn get_study_by_study_id() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let study_create = StudyCreate {
+            study_id: Uuid::new_v4().to_string(),
+            study_name: Some("Study Name".to_string()),
+            study_description: Some("Description".to_string()),
+            organization_id: organization.id.to_string(),
+        };
+        let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+            .await
+            .unwrap();
+
+        // The second request is served from the study id cache
+        for _ in 0..2 {
+            let response = app(&config())
+                .await
+                .oneshot(
+                    Request::builder()
+                        .method(http::Method::GET)
+                        .uri(&format!("/api/study/by-study-id/{}", study.study_id))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+
+            assert_eq!(body["id"], json!(study.id));
+            assert_eq!(body["study_id"], json!(study.study_id));
+        }
+    }
+
+    #[tokio::test]
+    async fn get_study_by_study_id_not_found() {
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri(&format!("/api/study/by-study-id/{}", Uuid::new_v4()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn user_add_study_concurrent() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let user_create = UserCreate {
+            user_name: Uuid::new_v4().to_string(),
+            first_name: "Imma".to_string(),
+            last_name: "Person".to_string(),
+            email: "some@email.com".to_string(),
+            phone: None,
+            password: "Somepassword1!".to_string(),
+            organization_id: organization.id.to_string(),
+        };
+        let user = create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+            .await
+            .unwrap();
+        let study_create = StudyCreate {
+            study_id: Uuid::new_v4().to_string(),
+            study_name: Some("Study Name".to_string()),
+            study_description: Some("Description".to_string()),
+            organization_id: organization.id.to_string(),
+        };
+        let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+            .await
+            .unwrap();
+        let app = app(&config()).await;
+        let add = |app: Router| {
+            let body = serde_json::to_vec(&json!({
+                "user_id": user.id,
+                "study_id": study.id,
+            }))
+            .unwrap();
+            app.oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/user/study")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+        };
+
+        let (first, second) = tokio::join!(add(app.clone()), add(app));
+        let mut statuses = vec![first.unwrap().status(), second.unwrap().status()];
+        statuses.sort();
+
+        assert_eq!(statuses, vec![StatusCode::OK, StatusCode::BAD_REQUEST]);
+
+        let added = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM user_studies WHERE user_id = $1"#,
+            user.id,
+        )
+        .fetch_one(&db_pool)
+        .await
+        .unwrap();
+
+        assert_eq!(added, 1);
+    }
+
+    #[tokio::test]
+    async fn deprecated_route_headers() {
+        let mut config = config();
+        config.deprecated_routes = vec!["/api/organization/count".to_string()];
+        config.deprecation_sunset = Some("Sat, 01 Nov 2025 00:00:00 GMT".to_string());
+        let app = app(&config).await;
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri("/api/organization/count")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["deprecation"], "true");
+        assert_eq!(
+            response.headers()["sunset"],
+            "Sat, 01 Nov 2025 00:00:00 GMT"
+        );
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri("/api/study/count")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key("deprecation"));
+        assert!(!response.headers().contains_key("sunset"));
+    }
+
+    #[tokio::test]
+    async fn config_banner_message() {
+        let mut config = config();
+        config.banner_message = Some("Scheduled downtime on Saturday".to_string());
+        let response = app(&config)
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri("/api/config")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body["message"], json!("Scheduled downtime on Saturday"));
+    }
+
+    #[tokio::test]
+    async fn list_link_header() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        for _ in 0..2 {
+            let create_org = OrganizationCreate {
+                name: Uuid::new_v4().to_string(),
+            };
+            create_organization_service(&db_pool, &valkey_pool, &create_org)
+                .await
+                .unwrap();
+        }
+        let app = app(&config()).await;
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri("/api/organization?limit=1&offset=0")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let link = response.headers()[http::header::LINK].to_str().unwrap();
+
+        assert!(link.contains("</api/organization?limit=1&offset=1>; rel=\"next\""));
+        assert!(link.contains("rel=\"first\""));
+        assert!(link.contains("rel=\"last\""));
+        assert!(!link.contains("rel=\"prev\""));
+
+        // Other tests add organizations concurrently so page well past the end to be certain
+        // this is the last page
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri("/api/organization?limit=1&offset=1000000000")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let link = response.headers()[http::header::LINK].to_str().unwrap();
+
+        assert!(!link.contains("rel=\"next\""));
+        assert!(link.contains("rel=\"prev\""));
+    }
+
+    async fn patch_study_request(id: &str, body: Value) -> (StatusCode, Value) {
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::PATCH)
+                    .uri(&format!("/api/study/{id}"))
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn patch_study() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let study_create = StudyCreate {
+            study_id: Uuid::new_v4().to_string(),
+            study_name: Some("Study Name".to_string()),
+            study_description: Some("Description".to_string()),
+            organization_id: organization.id.to_string(),
+        };
+        let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+            .await
+            .unwrap();
+
+        // Absent fields are left unchanged
+        let (status, body) = patch_study_request(&study.id, json!({})).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["study_name"], json!("Study Name"));
+        assert_eq!(body["study_description"], json!("Description"));
+
+        // A value replaces the field
+        let (status, body) =
+            patch_study_request(&study.id, json!({ "study_name": "New Name" })).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["study_name"], json!("New Name"));
+        assert_eq!(body["study_description"], json!("Description"));
+
+        // null clears the field
+        let (status, body) =
+            patch_study_request(&study.id, json!({ "study_description": null })).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["study_name"], json!("New Name"));
+        assert_eq!(body["study_description"], Value::Null);
+        assert_eq!(body["study_id"], json!(study.study_id));
+    }
+
+    #[tokio::test]
+    async fn patch_study_not_found() {
+        let (status, _) = patch_study_request(
+            &Uuid::new_v4().to_string(),
+            json!({ "study_name": "New Name" }),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn update_locked_study() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let valkey_pool = valkey_pool().await;
+        let organization = create_organization_service(
+            &db_pool,
+            &valkey_pool,
+            &OrganizationCreate {
+                name: Uuid::new_v4().to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let study = create_study_service(
+            &db_pool,
+            &valkey_pool,
+            &StudyCreate {
+                study_id: Uuid::new_v4().to_string(),
+                study_name: Some("Study Name".to_string()),
+                study_description: None,
+                organization_id: organization.id.to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        set_study_lock_service(&db_pool, &valkey_pool, &study.id, true)
+            .await
+            .unwrap();
+
+        let err = update_study_service(
+            &db_pool,
+            &valkey_pool,
+            &StudyUpdate {
+                id: study.id.clone(),
+                study_id: study.study_id.clone(),
+                study_name: Some("New Name".to_string()),
+                study_description: None,
+                organization_id: organization.id.to_string(),
+            },
+        )
+        .await
+        .unwrap_err();
+
+        assert!(err.to_string().contains("is locked"));
+
+        let err = patch_study_service(
+            &db_pool,
+            &valkey_pool,
+            &study.id,
+            &StudyPatch {
+                study_id: None,
+                study_name: Some(Some("New Name".to_string())),
+                study_description: None,
+            },
+        )
+        .await
+        .unwrap_err();
+
+        assert!(err.to_string().contains("is locked"));
+
+        let study_name: Option<String> =
+            sqlx::query_scalar("SELECT study_name FROM studies WHERE id = $1")
+                .bind(&study.id)
+                .fetch_one(&db_pool)
+                .await
+                .unwrap();
+
+        assert_eq!(study_name.as_deref(), Some("Study Name"));
+    }
+
+    #[tokio::test]
+    async fn cache_stats() {
+        let response = app(&admin_config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri("/api/admin/cache/stats")
+                    .header(
+                        http::header::AUTHORIZATION,
+                        format!("Bearer {ADMIN_API_KEY}"),
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert!(body["hits"].is_u64());
+        assert!(body["misses"].is_u64());
+        assert!(body["ratio"].is_f64());
+    }
+
+    #[tokio::test]
+    async fn admin_requires_tls() {
+        let cache_stats = |forwarded_proto: &'static str| async move {
+            let mut config = admin_config();
+            config.require_tls_for_sensitive = true;
+            config.trusted_proxies = vec!["10.0.0.0/8".parse().unwrap()];
+            let proxy: SocketAddr = "10.0.0.1:443".parse().unwrap();
+            app(&config)
+                .await
+                .oneshot(
+                    Request::builder()
+                        .method(http::Method::GET)
+                        .uri("/api/admin/cache/stats")
+                        .header(
+                            http::header::AUTHORIZATION,
+                            format!("Bearer {ADMIN_API_KEY}"),
+                        )
+                        .header("x-forwarded-proto", forwarded_proto)
+                        .extension(ConnectInfo(proxy))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+        };
+
+        let response = cache_stats("http").await;
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(
+            body["detail"],
+            json!("Admin requests must be sent over HTTPS")
+        );
+
+        let response = cache_stats("https").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn update_user_by_path_id() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let user_create = UserCreate {
+            user_name: Uuid::new_v4().to_string(),
+            first_name: "Imma".to_string(),
+            last_name: "Person".to_string(),
+            email: "some@email.com".to_string(),
+            phone: None,
+            password: "Somepassword1!".to_string(),
+            organization_id: organization.id.to_string(),
+        };
+        let user = create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+            .await
+            .unwrap();
+        let update = |body: Value| {
+            let uri = format!("/api/user/{}", user.id);
+            async move {
+                app(&config())
+                    .await
+                    .oneshot(
+                        Request::builder()
+                            .method(http::Method::PUT)
+                            .uri(&uri)
+                            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                            .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap()
+            }
+        };
+        let mut body = json!({
+            "user_name": user.user_name,
+            "first_name": "Updated",
+            "last_name": "Person",
+            "email": "some@email.com",
+            "phone": null,
+            "password": null,
+            "active": true,
+            "organization_id": organization.id,
+        });
+
+        // The id can come from the path alone
+        let response = update(body.clone()).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response_body = response.into_body().collect().await.unwrap().to_bytes();
+        let response_body: Value = serde_json::from_slice(&response_body).unwrap();
+
+        assert_eq!(response_body["id"], json!(user.id));
+        assert_eq!(response_body["first_name"], json!("Updated"));
+
+        // A matching body id is accepted
+        body["id"] = json!(user.id);
+        let response = update(body.clone()).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // A different body id is rejected
+        body["id"] = json!(Uuid::new_v4().to_string());
+        let response = update(body).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn password_change_requires_tls() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let user_create = UserCreate {
+            user_name: Uuid::new_v4().to_string(),
+            first_name: "Imma".to_string(),
+            last_name: "Person".to_string(),
+            email: "some@email.com".to_string(),
+            phone: None,
+            password: "Somepassword1!".to_string(),
+            organization_id: organization.id.to_string(),
+        };
+        let user = create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+            .await
+            .unwrap();
+        let body = json!({
+            "user_name": user.user_name,
+            "first_name": "Imma",
+            "last_name": "Person",
+            "email": "some@email.com",
+            "phone": null,
+            "password": "Otherpassword1!",
+            "active": true,
+            "organization_id": organization.id,
+        });
+        let change_password = |require_tls: bool, forwarded_proto: &'static str| {
+            let uri = format!("/api/user/{}", user.id);
+            let body = body.clone();
+            async move {
+                let mut config = config();
+                config.require_tls_for_sensitive = require_tls;
+                config.trusted_proxies = vec!["10.0.0.0/8".parse().unwrap()];
+                let proxy: SocketAddr = "10.0.0.1:443".parse().unwrap();
+                app(&config)
+                    .await
+                    .oneshot(
+                        Request::builder()
+                            .method(http::Method::PUT)
+                            .uri(&uri)
+                            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                            .header("x-forwarded-proto", forwarded_proto)
+                            .extension(ConnectInfo(proxy))
+                            .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let response = change_password(true, "http").await;
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response_body = response.into_body().collect().await.unwrap().to_bytes();
+        let response_body: Value = serde_json::from_slice(&response_body).unwrap();
+
+        assert_eq!(
+            response_body["detail"],
+            json!("Changing a password must be sent over HTTPS")
+        );
+
+        let response = change_password(true, "https").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = change_password(false, "http").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn organization_facade_matches_services() {
+        let test_pool = test_pool().await;
+        let db_pool = test_pool.pool.clone();
+        let state = test_state(&config(), &db_pool).await;
+        let organizations = state.organizations();
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let created = organizations.create(&create_org).await.unwrap();
+        let from_facade = organizations.get(&created.id).await.unwrap().unwrap();
+        let from_service = get_organization_service(
+            &state.db_state.pool,
+            &state.valkey_state.pool,
+            &created.id,
+            false,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&from_facade).unwrap(),
+            serde_json::to_value(&from_service).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&from_facade).unwrap(),
+            serde_json::to_value(&created).unwrap()
+        );
+
+        let ids = [created.id.clone()];
+        let many_from_facade = organizations.get_many(&ids).await.unwrap();
+        let many_from_service =
+            get_organizations_by_id_service(&state.db_state.pool, &state.valkey_state.pool, &ids)
+                .await
+                .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&many_from_facade).unwrap(),
+            serde_json::to_value(&many_from_service).unwrap()
+        );
+        assert_eq!(many_from_facade.len(), 1);
+        assert_eq!(many_from_facade[0].id, created.id);
+
+        let facade_count = organizations.count().await.unwrap();
+        let service_count = count_organizations_service(&state.db_state.read_pool)
+            .await
+            .unwrap();
+
+        assert_eq!(facade_count, service_count);
+        assert_eq!(facade_count, 1);
+
+        let pagination = Pagination {
+            limit: Some(10),
+            offset: Some(0),
+        };
+        let sort = Sort::default();
+        let list_from_facade = organizations.list(&pagination, &sort).await.unwrap();
+        let list_from_service =
+            get_organizations_service(&state.db_state.read_pool, &pagination, &sort)
+                .await
+                .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&list_from_facade).unwrap(),
+            serde_json::to_value(&list_from_service).unwrap()
+        );
+
+        organizations.delete(&created.id, false).await.unwrap();
+
+        assert!(organizations.get(&created.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn cached_reads_use_primary() {
+        // An empty schema stands in for a replica that hasn't caught up yet
+        let replica = test_pool().await;
+        let mut state = AppState::create_state(&config()).await.unwrap();
+        state.db_state.read_pool = replica.pool.clone();
+        let organizations = state.organizations();
+        let created = organizations
+            .create(&OrganizationCreate {
+                name: Uuid::new_v4().to_string(),
+            })
+            .await
+            .unwrap();
+        delete_cached_value::<Organization>(&state.valkey_state.pool, created.id.as_str())
+            .await
+            .unwrap();
+
+        let found = organizations.get(&created.id).await.unwrap();
+        let cached =
+            get_cached_value::<Organization>(&state.valkey_state.pool, created.id.as_str())
+                .await
+                .unwrap();
+
+        assert_eq!(found.map(|o| o.id), Some(created.id.clone()));
+        assert_eq!(cached.map(|o| o.id), Some(created.id.clone()));
+
+        organizations.delete(&created.id, false).await.unwrap();
+    }
+
+    async fn amend_study_request(id: &str, note: &str) -> (StatusCode, Value) {
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri(&format!("/api/study/{id}/amend"))
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({ "note": note })).unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn amend_study() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let study_create = StudyCreate {
+            study_id: Uuid::new_v4().to_string(),
+            study_name: Some("Study Name".to_string()),
+            study_description: None,
+            organization_id: organization.id.to_string(),
+        };
+        let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+            .await
+            .unwrap();
+
+        assert_eq!(study.protocol_version, "1.0");
+
+        let (status, body) = amend_study_request(&study.id, "Added visit 3").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["protocol_version"], json!("2.0"));
+
+        let (status, body) = amend_study_request(&study.id, "Changed dosing").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["protocol_version"], json!("3.0"));
+
+        let cached = get_cached_value::<Study>(&valkey_pool, &study.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached.protocol_version, "3.0");
+
+        let history = sqlx::query!(
+            r#"
+                SELECT protocol_version, note
+                FROM study_amendments
+                WHERE study_id = $1
+                ORDER BY date_added
+            "#,
+            &study.id,
+        )
+        .fetch_all(&db_pool)
+        .await
+        .unwrap();
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].protocol_version, "2.0");
+        assert_eq!(history[0].note, "Added visit 3");
+        assert_eq!(history[1].protocol_version, "3.0");
+        assert_eq!(history[1].note, "Changed dosing");
+    }
+
+    #[tokio::test]
+    async fn amend_study_locked() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let study_create = StudyCreate {
+            study_id: Uuid::new_v4().to_string(),
+            study_name: None,
+            study_description: None,
+            organization_id: organization.id.to_string(),
+        };
+        let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+            .await
+            .unwrap();
+        set_study_lock_service(&db_pool, &valkey_pool, &study.id, true)
+            .await
+            .unwrap();
+
+        let (status, _) = amend_study_request(&study.id, "Added visit 3").await;
+
+        assert_eq!(status, StatusCode::LOCKED);
+
+        let (status, _) = amend_study_request(&Uuid::new_v4().to_string(), "note").await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn openapi_etag() {
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri("/api-doc/openapi.json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().contains_key(http::header::CACHE_CONTROL));
+
+        let etag = response.headers()[http::header::ETAG].clone();
+        assert_eq!(etag, openapi::openapi_etag());
+
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri("/api-doc/openapi.json")
+                    .header(http::header::IF_NONE_MATCH, etag.clone())
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[http::header::ETAG], etag);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(body.is_empty());
+    }
+
+    async fn create_organization_with(config: &Config, body: Value) -> (StatusCode, Value) {
+        let response = app(config)
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/api/organization")
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn strict_json_rejects_unknown_fields() {
+        let mut config = config();
+        config.strict_json = true;
+
+        let (status, body) = create_organization_with(
+            &config,
+            json!({ "name": Uuid::new_v4().to_string(), "nmae": "typo", "active": true }),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let detail = body["detail"].as_str().unwrap();
+        assert!(detail.contains("nmae"));
+        assert!(detail.contains("active"));
+
+        let (status, _) =
+            create_organization_with(&config, json!({ "name": Uuid::new_v4().to_string() })).await;
+
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn strict_json_off_ignores_unknown_fields() {
+        let mut config = config();
+        config.strict_json = false;
+
+        let (status, _) = create_organization_with(
+            &config,
+            json!({ "name": Uuid::new_v4().to_string(), "nmae": "typo" }),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    async fn get_list(uri: &str) -> (StatusCode, Value) {
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    /// Random hour in the twentieth century for backdating rows, so their range doesn't overlap
+    /// rows left by other tests or earlier runs
+    fn backdated_base() -> chrono::DateTime<chrono::Utc> {
+        let hours = (Uuid::new_v4().as_u128() % (100 * 365 * 24)) as i64;
+        chrono::DateTime::parse_from_rfc3339("1900-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+            + chrono::Duration::hours(hours)
+    }
+
+    fn created_range_uri(
+        path: &str,
+        after: chrono::DateTime<chrono::Utc>,
+        before: chrono::DateTime<chrono::Utc>,
+    ) -> String {
+        format!(
+            "{path}?created_after={}&created_before={}",
+            after.format("%Y-%m-%dT%H:%M:%SZ"),
+            before.format("%Y-%m-%dT%H:%M:%SZ")
+        )
+    }
+
+    #[tokio::test]
+    async fn studies_created_range() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let base = backdated_base();
+        let mut ids = Vec::new();
+        for minutes in [0, 60, 120] {
+            let study_create = StudyCreate {
+                study_id: Uuid::new_v4().to_string(),
+                study_name: None,
+                study_description: None,
+                organization_id: organization.id.to_string(),
+            };
+            let study = create_study_service(&db_pool, &valkey_pool, &study_create)
+                .await
+                .unwrap();
+            sqlx::query("UPDATE studies SET date_added = $2 WHERE id = $1")
+                .bind(&study.id)
+                .bind(base + chrono::Duration::minutes(minutes))
+                .execute(&db_pool)
+                .await
+                .unwrap();
+            ids.push(study.id);
+        }
+
+        // The lower bound is inclusive and the upper bound exclusive
+        let (status, body) = get_list(&created_range_uri(
+            "/api/study",
+            base + chrono::Duration::minutes(30),
+            base + chrono::Duration::minutes(120),
+        ))
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total"], json!(1));
+        assert_eq!(body["items"][0]["id"], json!(ids[1]));
+
+        let (status, body) = get_list(&created_range_uri(
+            "/api/study",
+            base,
+            base + chrono::Duration::minutes(121),
+        ))
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total"], json!(3));
+    }
+
+    #[tokio::test]
+    async fn users_created_range() {
+        let db_client = db_client();
+        let db_pool = db_client.create_pool(Some(1), None).await.unwrap();
+        let valkey_pool = valkey_pool().await;
+        let create_org = OrganizationCreate {
+            name: Uuid::new_v4().to_string(),
+        };
+        let organization = create_organization_service(&db_pool, &valkey_pool, &create_org)
+            .await
+            .unwrap();
+        let base = backdated_base();
+        let mut ids = Vec::new();
+        for minutes in [0, 60] {
+            let user_create = UserCreate {
+                user_name: Uuid::new_v4().to_string(),
+                first_name: "Arthur".to_string(),
+                last_name: "Dent".to_string(),
+                email: format!("{}@heartofgold.com", Uuid::new_v4()),
+                phone: None,
+                password: "password".to_string(),
+                organization_id: organization.id.to_string(),
+            };
+            let user = create_user_service(&db_pool, &valkey_pool, &hash_permits(), &user_create)
+                .await
+                .unwrap();
+            sqlx::query("UPDATE users SET date_added = $2 WHERE id = $1")
+                .bind(&user.id)
+                .bind(base + chrono::Duration::minutes(minutes))
+                .execute(&db_pool)
+                .await
+                .unwrap();
+            ids.push(user.id);
+        }
+
+        let (status, body) = get_list(&created_range_uri(
+            "/api/user",
+            base + chrono::Duration::minutes(30),
+            base + chrono::Duration::minutes(90),
+        ))
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total"], json!(1));
+        assert_eq!(body["items"][0]["id"], json!(ids[1]));
+    }
+
+    #[tokio::test]
+    async fn created_range_invalid() {
+        let (status, body) = get_list("/api/study?created_after=yesterday").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["detail"]
+            .as_str()
+            .unwrap()
+            .contains("created_after must be an RFC 3339 timestamp"));
+
+        let (status, _) = get_list(
+            "/api/user?created_after=2024-09-01T00:00:00Z&created_before=2024-08-01T00:00:00Z",
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn get_version() {
+        let response = app(&config())
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri("/api/version")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body["app_version"], json!(env!("CARGO_PKG_VERSION")));
+        for field in ["app_version", "git_sha", "build_time", "db_schema_version"] {
+            assert!(
+                !body[field].as_str().unwrap().is_empty(),
+                "{field} is empty"
+            );
+        }
+        assert!(body["db_schema_version"]
+            .as_str()
+            .unwrap()
+            .parse::<i64>()
+            .is_ok());
+    }
+
+    async fn health_response(config: &Config) -> axum::response::Response {
+        app(config)
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri("/api/health")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn security_headers_present() {
+        let mut config = config();
+        config.security_headers = true;
+        config.hsts_max_age_secs = 600;
+        let response = health_response(&config).await;
+        let headers = response.headers();
+
+        assert_eq!(headers[http::header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(headers[http::header::X_FRAME_OPTIONS], "DENY");
+        assert_eq!(
+            headers[http::header::STRICT_TRANSPORT_SECURITY],
+            "max-age=600; includeSubDomains"
+        );
+    }
+
+    #[tokio::test]
+    async fn security_headers_configurable() {
+        let mut config = config();
+        config.security_headers = true;
+        config.hsts_max_age_secs = 0;
+        let response = health_response(&config).await;
+
+        assert!(response
+            .headers()
+            .contains_key(http::header::X_CONTENT_TYPE_OPTIONS));
+        assert!(!response
+            .headers()
+            .contains_key(http::header::STRICT_TRANSPORT_SECURITY));
+
+        config.security_headers = false;
+        let response = health_response(&config).await;
+
+        assert!(!response
+            .headers()
+            .contains_key(http::header::X_CONTENT_TYPE_OPTIONS));
+        assert!(!response
+            .headers()
+            .contains_key(http::header::X_FRAME_OPTIONS));
+    }
+
+    async fn post_with(config: &Config, uri: &str, body: Value) -> (StatusCode, Value) {
+        let response = app(config)
+            .await
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri(uri)
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    /// Study id that matches `^[A-Z]{2,4}-\d{4}$` and is unlikely to collide with other runs
+    fn conforming_study_id() -> String {
+        format!("ONC-{:04}", Uuid::new_v4().as_u128() % 10000)
+    }
+
+    #[tokio::test]
+    async fn study_id_pattern() {
+        let mut config = config();
+        config.study_id_pattern = Some(regex::Regex::new(r"^[A-Z]{2,4}-\d{4}$").unwrap());
+        let (_, organization) =
+            create_organization_with(&config, json!({ "name": Uuid::new_v4().to_string() })).await;
+
+        let (status, body) = post_with(
+            &config,
+            "/api/study",
+            json!({
+                "study_id": Uuid::new_v4().to_string(),
+                "organization_id": organization["id"],
+            }),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["detail"]
+            .as_str()
+            .unwrap()
+            .contains(r"expected to match the pattern ^[A-Z]{2,4}-\d{4}$"));
+
+        let study_id = conforming_study_id();
+        let (status, body) = post_with(
+            &config,
+            "/api/study",
+            json!({ "study_id": study_id, "organization_id": organization["id"] }),
+        )
+        .await;
+
+        // A rerun can draw a study id that is already taken, which is still past the pattern check
+        if status == StatusCode::CREATED {
+            assert_eq!(body["study_id"], study_id);
+        } else {
+            assert!(body["detail"].as_str().unwrap().contains("already exists"));
+        }
+    }
+
+    #[tokio::test]
+    async fn user_name_pattern() {
+        let mut config = config();
+        config.user_name_pattern = Some(regex::Regex::new(r"^[a-z]+\.[a-z0-9-]+$").unwrap());
+        let (_, organization) =
+            create_organization_with(&config, json!({ "name": Uuid::new_v4().to_string() })).await;
+        let user = |user_name: &str| {
+            json!({
+                "user_name": user_name,
+                "first_name": "Arthur",
+                "last_name": "Dent",
+                "email": "arthur@heartofgold.com",
+                "password": "Somepassword1!",
+                "organization_id": organization["id"],
+            })
+        };
+
+        let (status, body) = post_with(&config, "/api/user", user("Arthur Dent")).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["detail"]
+            .as_str()
+            .unwrap()
+            .contains("Invalid user_name Arthur Dent"));
+
+        let user_name = format!("arthur.{}", Uuid::new_v4());
+        let (status, body) = post_with(&config, "/api/user", user(&user_name)).await;
+
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["user_name"], user_name);
+    }
+
+    #[tokio::test]
+    async fn study_id_pattern_unset() {
+        let mut config = config();
+        config.study_id_pattern = None;
+        let (_, organization) =
+            create_organization_with(&config, json!({ "name": Uuid::new_v4().to_string() })).await;
+
+        let (status, _) = post_with(
+            &config,
+            "/api/study",
+            json!({
+                "study_id": format!("any study id {}", Uuid::new_v4()),
+                "organization_id": organization["id"],
+            }),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn disabled_feature_not_found() {
+        let mut config = config();
+        config.features = vec!["organizations".to_string(), "studies".to_string()];
+
+        let (status, _) =
+            create_organization_with(&config, json!({ "name": Uuid::new_v4().to_string() })).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = get_with(&config, "/api/study/count").await;
+        assert_eq!(status, StatusCode::OK);
+
+        for uri in ["/api/user/count", "/api/webhook"] {
+            let (status, _) = get_with(&config, uri).await;
+            assert_eq!(status, StatusCode::NOT_FOUND, "{uri}");
+        }
+    }
+
+    async fn get_with(config: &Config, uri: &str) -> (StatusCode, Value) {
+        let response = app(config)
+            .await
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
     }
 }
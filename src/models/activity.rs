@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::models::timestamp;
+
+/// Kinds of change published to a study's live activity stream
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StudyActivityKind {
+    StudyUpdated,
+    StudyLocked,
+    StudyUnlocked,
+    StudyDeleted,
+    UserAdded,
+    UserRemoved,
+}
+
+impl StudyActivityKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::StudyUpdated => "study_updated",
+            Self::StudyLocked => "study_locked",
+            Self::StudyUnlocked => "study_unlocked",
+            Self::StudyDeleted => "study_deleted",
+            Self::UserAdded => "user_added",
+            Self::UserRemoved => "user_removed",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct StudyActivity {
+    /// Database id of the study the change happened in
+    pub study_id: String,
+    pub kind: StudyActivityKind,
+    #[serde(with = "timestamp")]
+    pub timestamp: DateTime<Utc>,
+    pub data: Value,
+}
+
+impl StudyActivity {
+    pub fn new(study_id: &str, kind: StudyActivityKind, data: Value) -> Self {
+        Self {
+            study_id: study_id.to_string(),
+            kind,
+            timestamp: Utc::now(),
+            data,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CacheStats {
+    /// Cache reads that found a value
+    pub hits: u64,
+
+    /// Cache reads that found nothing, including reads skipped while the cache is unavailable
+    pub misses: u64,
+
+    /// Fraction of reads that were hits, 0 when there have been no reads
+    pub ratio: f64,
+}
+
+impl CacheStats {
+    pub fn new(hits: u64, misses: u64) -> Self {
+        let reads = hits + misses;
+        let ratio = if reads == 0 {
+            0.0
+        } else {
+            hits as f64 / reads as f64
+        };
+
+        Self {
+            hits,
+            misses,
+            ratio,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+#[into_params(parameter_in = Query)]
+pub struct CacheStatsParams {
+    /// Start counting again from zero after returning the current stats
+    #[serde(default)]
+    pub reset: bool,
+}
@@ -0,0 +1,112 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+
+#[derive(Debug, Default, Deserialize, Serialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+#[into_params(parameter_in = Query)]
+pub struct CreatedRange {
+    /// Only include items added at or after this RFC 3339 timestamp
+    pub created_after: Option<String>,
+
+    /// Only include items added before this RFC 3339 timestamp
+    pub created_before: Option<String>,
+}
+
+impl CreatedRange {
+    pub fn validate(&self) -> Result<()> {
+        let after = parse_bound("created_after", self.created_after.as_deref())?;
+        let before = parse_bound("created_before", self.created_before.as_deref())?;
+
+        if let (Some(a), Some(b)) = (after, before) {
+            if a >= b {
+                bail!("created_after must be earlier than created_before");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lower bound, `None` when not set. Call `validate` first, an unparsable value is ignored
+    pub fn after(&self) -> Option<DateTime<Utc>> {
+        parse_bound("created_after", self.created_after.as_deref())
+            .ok()
+            .flatten()
+    }
+
+    /// Upper bound, `None` when not set. Call `validate` first, an unparsable value is ignored
+    pub fn before(&self) -> Option<DateTime<Utc>> {
+        parse_bound("created_before", self.created_before.as_deref())
+            .ok()
+            .flatten()
+    }
+}
+
+fn parse_bound(name: &str, value: Option<&str>) -> Result<Option<DateTime<Utc>>> {
+    match value {
+        None => Ok(None),
+        Some(v) => match DateTime::parse_from_rfc3339(v) {
+            Ok(d) => Ok(Some(d.with_timezone(&Utc))),
+            Err(_) => bail!(format!(
+                "{name} must be an RFC 3339 timestamp such as 2024-08-01T00:00:00Z, got {v}"
+            )),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(after: Option<&str>, before: Option<&str>) -> CreatedRange {
+        CreatedRange {
+            created_after: after.map(str::to_string),
+            created_before: before.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn created_range_default() {
+        let range = CreatedRange::default();
+
+        assert!(range.validate().is_ok());
+        assert!(range.after().is_none());
+        assert!(range.before().is_none());
+    }
+
+    #[test]
+    fn created_range_valid() {
+        let range = range(
+            Some("2024-08-01T00:00:00Z"),
+            Some("2024-09-01T00:00:00+02:00"),
+        );
+
+        assert!(range.validate().is_ok());
+        assert_eq!(
+            range.after().unwrap().to_rfc3339(),
+            "2024-08-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            range.before().unwrap().to_rfc3339(),
+            "2024-08-31T22:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn created_range_unparsable() {
+        let err = range(Some("last month"), None)
+            .validate()
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("created_after must be an RFC 3339 timestamp"));
+    }
+
+    #[test]
+    fn created_range_inverted() {
+        let range = range(Some("2024-09-01T00:00:00Z"), Some("2024-08-01T00:00:00Z"));
+
+        assert!(range.validate().is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::{
+    encode::IsNull,
+    error::BoxDynError,
+    postgres::{PgArgumentBuffer, PgTypeInfo, PgValueRef},
+    Decode, Encode, Postgres, Type,
+};
+
+use crate::utils::generate_db_id;
+
+/// Database id tagged with the entity it belongs to so, for example, a user id can't be passed
+/// where an organization id is expected. It is stored and serialized exactly like the plain
+/// string ids it replaces.
+pub struct Id<T> {
+    value: String,
+    entity: PhantomData<fn() -> T>,
+}
+
+impl<T> Id<T> {
+    pub fn new() -> Self {
+        Self::from(generate_db_id())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+}
+
+impl<T> Default for Id<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<String> for Id<T> {
+    fn from(value: String) -> Self {
+        Self {
+            value,
+            entity: PhantomData,
+        }
+    }
+}
+
+impl<T> From<&str> for Id<T> {
+    fn from(value: &str) -> Self {
+        Self::from(value.to_string())
+    }
+}
+
+// Implemented by hand because deriving would require the entity type to implement them too
+impl<T> Clone for Id<T> {
+    fn clone(&self) -> Self {
+        Self::from(self.value.clone())
+    }
+}
+
+impl<T> PartialEq for Id<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T> Eq for Id<T> {}
+
+impl<T> PartialEq<str> for Id<T> {
+    fn eq(&self, other: &str) -> bool {
+        self.value == other
+    }
+}
+
+impl<T> PartialEq<String> for Id<T> {
+    fn eq(&self, other: &String) -> bool {
+        &self.value == other
+    }
+}
+
+impl<T> Hash for Id<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Id<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.value, f)
+    }
+}
+
+impl<T> fmt::Display for Id<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+impl<T> Serialize for Id<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.value)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Id<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
+
+impl<T> Type<Postgres> for Id<T> {
+    fn type_info() -> PgTypeInfo {
+        <String as Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <String as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl<'q, T> Encode<'q, Postgres> for Id<T> {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        <&str as Encode<'q, Postgres>>::encode_by_ref(&self.value.as_str(), buf)
+    }
+}
+
+impl<'r, T> Decode<'r, Postgres> for Id<T> {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        <String as Decode<'r, Postgres>>::decode(value).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::models::organization::OrganizationId;
+
+    #[test]
+    fn serializes_as_string() {
+        let id = OrganizationId::from("abc".to_string());
+
+        assert_eq!(serde_json::to_value(&id).unwrap(), json!("abc"));
+        assert_eq!(
+            serde_json::to_string(&id).unwrap(),
+            serde_json::to_string("abc").unwrap()
+        );
+    }
+
+    #[test]
+    fn deserializes_from_string() {
+        let id: OrganizationId = serde_json::from_value(json!("abc")).unwrap();
+
+        assert_eq!(id.as_str(), "abc");
+    }
+
+    #[test]
+    fn new_ids_are_unique() {
+        assert_ne!(OrganizationId::new(), OrganizationId::new());
+    }
+}
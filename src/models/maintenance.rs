@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::strict_json::KnownFields;
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(rename_all = "camelCase")]
+pub struct MaintenanceMode {
+    /// Whether writes are blocked
+    pub enabled: bool,
+}
+
+impl KnownFields for MaintenanceMode {
+    const FIELDS: &'static [&'static str] = &["enabled"];
+}
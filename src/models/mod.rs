@@ -1,4 +1,18 @@
+pub mod activity;
+pub mod cache;
+pub mod created_range;
+pub mod id;
+pub mod maintenance;
 pub mod messages;
+pub mod nullable;
 pub mod organization;
+pub mod pagination;
+pub mod projection;
+pub mod response;
+pub mod search;
+pub mod sort;
 pub mod study;
+pub mod timestamp;
+pub mod timestamped;
 pub mod user;
+pub mod webhook;
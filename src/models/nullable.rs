@@ -0,0 +1,39 @@
+//! Serde helper for fields of a partial update that can be cleared.
+//!
+//! serde maps both an absent field and an explicit `null` to `None`, so a PATCH can't tell
+//! "leave unchanged" from "clear". Use on an `Option<Option<T>>` with
+//! `#[serde(default, deserialize_with = "nullable::deserialize")]`: an absent field is `None`,
+//! `null` is `Some(None)`, and a value is `Some(Some(value))`.
+
+use serde::{Deserialize, Deserializer};
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize)]
+    struct Patch {
+        #[serde(default, deserialize_with = "super::deserialize")]
+        value: Option<Option<String>>,
+    }
+
+    #[test]
+    fn absent_null_and_value() {
+        let absent: Patch = serde_json::from_value(json!({})).unwrap();
+        let null: Patch = serde_json::from_value(json!({ "value": null })).unwrap();
+        let value: Patch = serde_json::from_value(json!({ "value": "a" })).unwrap();
+
+        assert_eq!(absent.value, None);
+        assert_eq!(null.value, Some(None));
+        assert_eq!(value.value, Some(Some("a".to_string())));
+    }
+}
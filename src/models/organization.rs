@@ -1,15 +1,29 @@
+use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use serde_json::Value;
+use utoipa::{IntoParams, ToSchema};
 
-use crate::{services::cache_services::Cacheable, utils::generate_db_id};
+use crate::{
+    models::{
+        id::Id,
+        timestamp,
+        timestamped::new_timestamps,
+        user::{OrganizationAdminCreate, User},
+    },
+    services::cache_services::Cacheable,
+    strict_json::{unknown_keys, KnownFields},
+};
+
+pub type OrganizationId = Id<Organization>;
 
 #[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 #[schema(rename_all = "camelCase")]
 pub struct Organization {
     /// Uniue system identifier for the organization
-    pub id: String,
+    #[schema(value_type = String)]
+    pub id: OrganizationId,
 
     /// The name of of the organization
     pub name: String,
@@ -18,31 +32,58 @@ pub struct Organization {
     pub active: bool,
 
     /// Date the organization was added
+    #[serde(with = "timestamp")]
+    #[schema(value_type = String, format = DateTime)]
     pub date_added: DateTime<Utc>,
 
     /// Date the orginization was last modified
+    #[serde(with = "timestamp")]
+    #[schema(value_type = String, format = DateTime)]
     pub date_modified: DateTime<Utc>,
 }
 
-impl Organization {
-    pub fn new(name: String) -> Self {
-        Self {
-            id: generate_db_id(),
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct OrganizationInDb {
+    pub id: OrganizationId,
+    pub name: String,
+    pub active: bool,
+    #[serde(with = "timestamp")]
+    pub date_added: DateTime<Utc>,
+    #[serde(with = "timestamp")]
+    pub date_modified: DateTime<Utc>,
+}
+
+impl OrganizationInDb {
+    pub async fn prepare_create(name: String) -> Result<Self> {
+        let (date_added, date_modified) = new_timestamps();
+        Ok(Self {
+            id: OrganizationId::new(),
             name,
             active: true,
-            date_added: Utc::now(),
-            date_modified: Utc::now(),
+            date_added,
+            date_modified,
+        })
+    }
+}
+
+impl From<OrganizationInDb> for Organization {
+    fn from(organization: OrganizationInDb) -> Self {
+        Self {
+            id: organization.id,
+            name: organization.name,
+            active: organization.active,
+            date_added: organization.date_added,
+            date_modified: organization.date_modified,
         }
     }
 }
 
 impl Cacheable for Organization {
-    fn get_key(&self) -> &str {
-        &self.id
-    }
+    const CACHE_FIELD: &'static str = "organizations";
 
-    fn cache_field(&self) -> &str {
-        "organizations"
+    fn get_key(&self) -> &str {
+        self.id.as_str()
     }
 }
 
@@ -54,6 +95,10 @@ pub struct OrganizationCreate {
     pub name: String,
 }
 
+impl KnownFields for OrganizationCreate {
+    const FIELDS: &'static [&'static str] = &["name"];
+}
+
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 #[schema(rename_all = "camelCase")]
@@ -67,3 +112,99 @@ pub struct OrganizationUpdate {
     /// Is the organization activate
     pub active: bool,
 }
+
+impl KnownFields for OrganizationUpdate {
+    const FIELDS: &'static [&'static str] = &["id", "name", "active"];
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(rename_all = "camelCase")]
+pub struct OrganizationBootstrap {
+    /// The name of of the organization
+    pub name: String,
+
+    /// The organization's first admin user
+    pub admin: OrganizationAdminCreate,
+}
+
+impl KnownFields for OrganizationBootstrap {
+    const FIELDS: &'static [&'static str] = &["name", "admin"];
+
+    fn unknown_fields(value: &Value) -> Vec<String> {
+        let mut unknown = unknown_keys(value, Self::FIELDS, "");
+        if let Some(admin) = value.get("admin") {
+            unknown.extend(unknown_keys(
+                admin,
+                OrganizationAdminCreate::FIELDS,
+                "admin.",
+            ));
+        }
+        unknown
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(rename_all = "camelCase")]
+pub struct OrganizationBootstrapped {
+    pub organization: Organization,
+    pub admin: User,
+}
+
+/// Maximum number of ids accepted by a single batch lookup
+pub const MAX_ORGANIZATION_BATCH_SIZE: usize = 200;
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(rename_all = "camelCase")]
+pub struct OrganizationBatch {
+    /// Unique system identifiers of the organizations to fetch
+    #[schema(value_type = Vec<String>)]
+    pub ids: Vec<OrganizationId>,
+}
+
+impl KnownFields for OrganizationBatch {
+    const FIELDS: &'static [&'static str] = &["ids"];
+}
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+#[into_params(parameter_in = Query)]
+pub struct OrganizationDeleteParams {
+    /// Also delete the organization's studies and users
+    #[serde(default)]
+    pub cascade: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn prepare_create() {
+        let before = Utc::now();
+        let organization = OrganizationInDb::prepare_create("name".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(organization.name, "name");
+        assert!(organization.active);
+        assert!(!organization.id.as_str().is_empty());
+        assert!(organization.date_added >= before);
+        assert!(organization.date_modified >= before);
+        assert!(organization.date_added <= Utc::now());
+    }
+
+    #[tokio::test]
+    async fn prepare_create_unique_ids() {
+        let first = OrganizationInDb::prepare_create("name".to_string())
+            .await
+            .unwrap();
+        let second = OrganizationInDb::prepare_create("name".to_string())
+            .await
+            .unwrap();
+
+        assert_ne!(first.id, second.id);
+    }
+}
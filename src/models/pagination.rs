@@ -0,0 +1,110 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+
+use crate::config::Config;
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+#[into_params(parameter_in = Query)]
+pub struct Pagination {
+    /// Maximum number of items to return. Defaults to the configured default page size and is
+    /// capped at the configured max page size
+    pub limit: Option<i64>,
+
+    /// Number of items to skip
+    pub offset: Option<i64>,
+}
+
+impl Pagination {
+    /// Fills in defaults and caps the limit at the max page size. A limit below 1 or a
+    /// negative offset is an error rather than being silently corrected.
+    pub fn clamp(&mut self, config: &Config) -> Result<()> {
+        let max_page_size = i64::from(config.max_page_size);
+        let limit = self.limit.unwrap_or(i64::from(config.default_page_size));
+        let offset = self.offset.unwrap_or(0);
+
+        if limit < 1 {
+            bail!("limit must be at least 1");
+        }
+
+        if offset < 0 {
+            bail!("offset must not be negative");
+        }
+
+        self.limit = Some(limit.min(max_page_size));
+        self.offset = Some(offset);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dotenvy::dotenv;
+
+    fn config() -> Config {
+        dotenv().ok();
+        let mut config = Config::new();
+        config.default_page_size = 10;
+        config.max_page_size = 100;
+
+        config
+    }
+
+    #[test]
+    fn clamp_default() {
+        let mut pagination = Pagination {
+            limit: None,
+            offset: None,
+        };
+        pagination.clamp(&config()).unwrap();
+
+        assert_eq!(pagination.limit, Some(10));
+        assert_eq!(pagination.offset, Some(0));
+    }
+
+    #[test]
+    fn clamp_valid() {
+        let mut pagination = Pagination {
+            limit: Some(25),
+            offset: Some(50),
+        };
+        pagination.clamp(&config()).unwrap();
+
+        assert_eq!(pagination.limit, Some(25));
+        assert_eq!(pagination.offset, Some(50));
+    }
+
+    #[test]
+    fn clamp_above_max() {
+        let mut pagination = Pagination {
+            limit: Some(1000),
+            offset: None,
+        };
+        pagination.clamp(&config()).unwrap();
+
+        assert_eq!(pagination.limit, Some(100));
+    }
+
+    #[test]
+    fn clamp_below_min() {
+        let mut pagination = Pagination {
+            limit: Some(0),
+            offset: None,
+        };
+
+        assert!(pagination.clamp(&config()).is_err());
+    }
+
+    #[test]
+    fn clamp_negative_offset() {
+        let mut pagination = Pagination {
+            limit: None,
+            offset: Some(-1),
+        };
+
+        assert!(pagination.clamp(&config()).is_err());
+    }
+}
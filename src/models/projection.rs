@@ -0,0 +1,98 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use utoipa::IntoParams;
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+#[into_params(parameter_in = Query)]
+pub struct Projection {
+    /// Comma separated list of fields to include in the response, e.g. `id,user_name,email`.
+    /// All fields are returned when omitted
+    pub fields: Option<String>,
+}
+
+impl Projection {
+    /// Serializes `item` and, if fields were requested, keeps only those keys. Requesting a key
+    /// the item doesn't have is an error.
+    pub fn apply<T: Serialize>(&self, item: &T) -> Result<Value> {
+        let value = serde_json::to_value(item)?;
+
+        match &self.fields {
+            Some(fields) => project(value, fields),
+            None => Ok(value),
+        }
+    }
+}
+
+pub fn project(value: Value, fields: &str) -> Result<Value> {
+    let Value::Object(mut object) = value else {
+        bail!("Only objects can be projected");
+    };
+
+    let mut projected = Map::new();
+
+    for field in fields.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        match object.remove(field) {
+            Some(v) => {
+                projected.insert(field.to_string(), v);
+            }
+            None => {
+                if !projected.contains_key(field) {
+                    bail!(format!("Unknown field {field}"));
+                }
+            }
+        }
+    }
+
+    if projected.is_empty() {
+        bail!("At least one field must be requested");
+    }
+
+    Ok(Value::Object(projected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn project_subset() {
+        let value = json!({"id": "1", "user_name": "user", "email": "a@b.com", "active": true});
+        let projected = project(value, "id, email").unwrap();
+
+        assert_eq!(projected, json!({"id": "1", "email": "a@b.com"}));
+    }
+
+    #[test]
+    fn project_duplicate_field() {
+        let value = json!({"id": "1", "user_name": "user"});
+        let projected = project(value, "id,id").unwrap();
+
+        assert_eq!(projected, json!({"id": "1"}));
+    }
+
+    #[test]
+    fn project_unknown_field() {
+        let value = json!({"id": "1", "user_name": "user"});
+        let err = project(value, "id,password").unwrap_err().to_string();
+
+        assert_eq!(err, "Unknown field password");
+    }
+
+    #[test]
+    fn project_no_fields() {
+        let value = json!({"id": "1"});
+
+        assert!(project(value, " , ").is_err());
+    }
+
+    #[test]
+    fn apply_without_fields() {
+        let projection = Projection { fields: None };
+        let value = json!({"id": "1", "user_name": "user"});
+
+        assert_eq!(projection.apply(&value).unwrap(), value);
+    }
+}
@@ -0,0 +1,152 @@
+use axum::http::Uri;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::{organization::Organization, pagination::Pagination, study::Study, user::User};
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[aliases(
+    OrganizationList = ListResponse<Organization>,
+    StudyList = ListResponse<Study>,
+    UserList = ListResponse<User>,
+)]
+pub struct ListResponse<T> {
+    /// The requested page of items
+    pub items: Vec<T>,
+
+    /// Total number of items available across all pages
+    pub total: i64,
+
+    /// Maximum number of items in the page
+    pub limit: i64,
+
+    /// Number of items skipped before the page
+    pub offset: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Count {
+    /// Number of items the matching list endpoint would return across all pages
+    pub count: i64,
+}
+
+impl<T> ListResponse<T> {
+    pub fn new(items: Vec<T>, total: i64, pagination: &Pagination) -> Self {
+        Self {
+            items,
+            total,
+            limit: pagination.limit.unwrap_or_default(),
+            offset: pagination.offset.unwrap_or_default(),
+        }
+    }
+
+    /// RFC 8288 `Link` header value with first, prev, next and last page links for the request
+    /// `uri`. Query parameters other than `limit` and `offset` are kept as they are.
+    pub fn link_header(&self, uri: &Uri) -> String {
+        let limit = self.limit.max(1);
+        let last = if self.total > 0 {
+            (self.total - 1) / limit * limit
+        } else {
+            0
+        };
+        let mut links = vec![(0, "first")];
+
+        if self.offset > 0 {
+            links.push(((self.offset - limit).max(0), "prev"));
+        }
+
+        if self.offset + limit < self.total {
+            links.push((self.offset + limit, "next"));
+        }
+
+        links.push((last, "last"));
+
+        let query: Vec<&str> = uri
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|p| {
+                let key = p.split('=').next().unwrap_or_default();
+                !p.is_empty() && key != "limit" && key != "offset"
+            })
+            .collect();
+
+        links
+            .into_iter()
+            .map(|(offset, rel)| {
+                let mut params = query.clone();
+                let page = format!("limit={limit}&offset={offset}");
+                params.push(&page);
+                format!("<{}?{}>; rel=\"{rel}\"", uri.path(), params.join("&"))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn list_response_shape() {
+        let pagination = Pagination {
+            limit: Some(10),
+            offset: Some(20),
+        };
+        let response = ListResponse::new(vec!["a", "b"], 22, &pagination);
+
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            json!({"items": ["a", "b"], "total": 22, "limit": 10, "offset": 20})
+        );
+    }
+
+    fn page(total: i64, limit: i64, offset: i64) -> ListResponse<&'static str> {
+        ListResponse::new(
+            Vec::new(),
+            total,
+            &Pagination {
+                limit: Some(limit),
+                offset: Some(offset),
+            },
+        )
+    }
+
+    #[test]
+    fn link_header_middle_page() {
+        let uri: Uri = "/api/study?sort=name&limit=10&offset=10".parse().unwrap();
+
+        assert_eq!(
+            page(35, 10, 10).link_header(&uri),
+            "</api/study?sort=name&limit=10&offset=0>; rel=\"first\", \
+             </api/study?sort=name&limit=10&offset=0>; rel=\"prev\", \
+             </api/study?sort=name&limit=10&offset=20>; rel=\"next\", \
+             </api/study?sort=name&limit=10&offset=30>; rel=\"last\""
+        );
+    }
+
+    #[test]
+    fn link_header_last_page() {
+        let uri: Uri = "/api/study".parse().unwrap();
+        let link = page(35, 10, 30).link_header(&uri);
+
+        assert!(!link.contains("rel=\"next\""));
+        assert!(link.contains("</api/study?limit=10&offset=20>; rel=\"prev\""));
+        assert!(link.contains("</api/study?limit=10&offset=30>; rel=\"last\""));
+    }
+
+    #[test]
+    fn link_header_empty() {
+        let uri: Uri = "/api/study".parse().unwrap();
+
+        assert_eq!(
+            page(0, 10, 0).link_header(&uri),
+            "</api/study?limit=10&offset=0>; rel=\"first\", \
+             </api/study?limit=10&offset=0>; rel=\"last\""
+        );
+    }
+}
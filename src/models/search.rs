@@ -0,0 +1,145 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    config::Config,
+    models::{organization::Organization, study::Study, user::User},
+};
+
+/// Entity types a search can cover, named as in `FEATURES`
+pub const SEARCH_TYPES: [&str; 3] = ["organizations", "studies", "users"];
+
+/// Most results a search returns for each entity type
+pub const MAX_SEARCH_RESULTS: i64 = 20;
+
+#[derive(Debug, Default, Deserialize, Serialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Text to find, matched case-insensitively anywhere in names, study ids and emails
+    pub q: Option<String>,
+
+    /// Comma separated entity types to search, any of organizations, studies and users.
+    /// Defaults to every type whose feature is enabled
+    pub types: Option<String>,
+
+    /// Only return results that belong to this organization
+    pub organization_id: Option<String>,
+}
+
+impl SearchQuery {
+    /// The trimmed search text, which must not be blank
+    pub fn text(&self) -> Result<&str> {
+        match self.q.as_deref().map(str::trim) {
+            Some(q) if !q.is_empty() => Ok(q),
+            _ => bail!("q must not be blank"),
+        }
+    }
+
+    /// Entity types to search. Types whose feature is turned off can't be searched.
+    pub fn types(&self, config: &Config) -> Result<Vec<&'static str>> {
+        let Some(types) = &self.types else {
+            return Ok(SEARCH_TYPES
+                .into_iter()
+                .filter(|t| config.feature_enabled(t))
+                .collect());
+        };
+
+        let mut requested = Vec::new();
+        for t in types.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            match SEARCH_TYPES
+                .into_iter()
+                .find(|s| *s == t && config.feature_enabled(s))
+            {
+                Some(s) if !requested.contains(&s) => requested.push(s),
+                Some(_) => {}
+                None => bail!(format!(
+                    "types must only contain enabled types from {}, got {t}",
+                    SEARCH_TYPES.join(", ")
+                )),
+            }
+        }
+
+        Ok(requested)
+    }
+}
+
+/// ILIKE pattern matching `text` anywhere, with the wildcards in `text` matched literally
+pub fn contains_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    format!("%{escaped}%")
+}
+
+/// Matches for each searched entity type, types that weren't searched are empty
+#[derive(Debug, Default, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SearchResults {
+    pub organizations: Vec<Organization>,
+    pub studies: Vec<Study>,
+    pub users: Vec<User>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dotenvy::dotenv;
+
+    fn config() -> Config {
+        dotenv().ok();
+        let mut config = Config::new();
+        config.features = vec!["studies".to_string(), "users".to_string()];
+
+        config
+    }
+
+    fn query(q: Option<&str>, types: Option<&str>) -> SearchQuery {
+        SearchQuery {
+            q: q.map(str::to_string),
+            types: types.map(str::to_string),
+            organization_id: None,
+        }
+    }
+
+    #[test]
+    fn blank_text() {
+        assert!(query(None, None).text().is_err());
+        assert!(query(Some("  "), None).text().is_err());
+        assert_eq!(query(Some(" onc "), None).text().unwrap(), "onc");
+    }
+
+    #[test]
+    fn types_default_to_enabled_features() {
+        assert_eq!(
+            query(Some("onc"), None).types(&config()).unwrap(),
+            vec!["studies", "users"]
+        );
+    }
+
+    #[test]
+    fn types_requested() {
+        assert_eq!(
+            query(Some("onc"), Some("users, studies,users"))
+                .types(&config())
+                .unwrap(),
+            vec!["users", "studies"]
+        );
+        assert!(query(Some("onc"), Some("subjects"))
+            .types(&config())
+            .is_err());
+        // The organizations feature is turned off
+        assert!(query(Some("onc"), Some("organizations"))
+            .types(&config())
+            .is_err());
+    }
+
+    #[test]
+    fn pattern_escapes_wildcards() {
+        assert_eq!(contains_pattern("onc"), "%onc%");
+        assert_eq!(contains_pattern(r"50%_a\b"), r"%50\%\_a\\b%");
+    }
+}
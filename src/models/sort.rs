@@ -0,0 +1,98 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+
+/// Columns a list can be sorted by. The values are checked against this list before being
+/// passed to a query so arbitrary input never reaches the ORDER BY.
+const SORT_FIELDS: [&str; 2] = ["name", "date_added"];
+
+const SORT_DIRECTIONS: [&str; 2] = ["asc", "desc"];
+
+#[derive(Debug, Default, Deserialize, Serialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+#[into_params(parameter_in = Query)]
+pub struct Sort {
+    /// Field to sort by, either name or date_added. Defaults to date_added
+    pub sort: Option<String>,
+
+    /// Sort direction, either asc or desc. Defaults to asc
+    pub dir: Option<String>,
+}
+
+impl Sort {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(sort) = &self.sort {
+            if !SORT_FIELDS.contains(&sort.as_str()) {
+                bail!(format!(
+                    "sort must be one of {}, got {sort}",
+                    SORT_FIELDS.join(", ")
+                ));
+            }
+        }
+
+        if let Some(dir) = &self.dir {
+            if !SORT_DIRECTIONS.contains(&dir.as_str()) {
+                bail!(format!(
+                    "dir must be one of {}, got {dir}",
+                    SORT_DIRECTIONS.join(", ")
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn field(&self) -> &str {
+        self.sort.as_deref().unwrap_or("date_added")
+    }
+
+    pub fn direction(&self) -> &str {
+        self.dir.as_deref().unwrap_or("asc")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_default() {
+        let sort = Sort::default();
+
+        assert!(sort.validate().is_ok());
+        assert_eq!(sort.field(), "date_added");
+        assert_eq!(sort.direction(), "asc");
+    }
+
+    #[test]
+    fn sort_valid() {
+        let sort = Sort {
+            sort: Some("name".to_string()),
+            dir: Some("desc".to_string()),
+        };
+
+        assert!(sort.validate().is_ok());
+        assert_eq!(sort.field(), "name");
+        assert_eq!(sort.direction(), "desc");
+    }
+
+    #[test]
+    fn sort_invalid_field() {
+        let sort = Sort {
+            sort: Some("name; DROP TABLE users".to_string()),
+            dir: None,
+        };
+
+        assert!(sort.validate().is_err());
+    }
+
+    #[test]
+    fn sort_invalid_direction() {
+        let sort = Sort {
+            sort: None,
+            dir: Some("sideways".to_string()),
+        };
+
+        assert!(sort.validate().is_err());
+    }
+}
@@ -4,9 +4,23 @@ use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 use crate::{
-    models::organization::Organization, services::cache_services::Cacheable, utils::generate_db_id,
+    models::{nullable, organization::Organization, timestamp, timestamped::new_timestamps},
+    services::cache_services::Cacheable,
+    strict_json::KnownFields,
+    utils::generate_db_id,
 };
 
+pub const MAX_STUDY_ID_LENGTH: usize = 64;
+
+pub const MAX_STUDY_NAME_LENGTH: usize = 255;
+
+pub const MAX_STUDY_DESCRIPTION_LENGTH: usize = 2000;
+
+pub const MAX_AMENDMENT_NOTE_LENGTH: usize = 2000;
+
+/// Protocol version every study starts at
+pub const INITIAL_PROTOCOL_VERSION: &str = "1.0";
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub struct StudyInDb {
@@ -15,7 +29,11 @@ pub struct StudyInDb {
     pub study_name: Option<String>,
     pub study_description: Option<String>,
     pub organization_id: String,
+    pub locked: bool,
+    pub protocol_version: String,
+    #[serde(with = "timestamp")]
     pub date_added: DateTime<Utc>,
+    #[serde(with = "timestamp")]
     pub date_modified: DateTime<Utc>,
 }
 
@@ -26,19 +44,22 @@ impl StudyInDb {
         study_description: Option<String>,
         organization_id: String,
     ) -> Result<Self> {
+        let (date_added, date_modified) = new_timestamps();
         Ok(Self {
             id: generate_db_id(),
             study_id,
             study_name,
             study_description,
             organization_id,
-            date_added: Utc::now(),
-            date_modified: Utc::now(),
+            locked: false,
+            protocol_version: INITIAL_PROTOCOL_VERSION.to_string(),
+            date_added,
+            date_modified,
         })
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct Study {
     /// Uniue system identifier for the study
@@ -46,16 +67,36 @@ pub struct Study {
     pub study_id: String,
     pub study_name: Option<String>,
     pub study_description: Option<String>,
+
+    /// Locked studies can not be edited
+    pub locked: bool,
+
+    /// Version of the protocol, bumped each time the study is amended
+    pub protocol_version: String,
     pub organization: Organization,
 }
 
 impl Cacheable for Study {
+    const CACHE_FIELD: &'static str = "studies";
+
     fn get_key(&self) -> &str {
         &self.id
     }
+}
+
+/// Secondary cache entry mapping a study's protocol `study_id` to its database id
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct StudyIdIndex {
+    pub study_id: String,
+    pub id: String,
+}
+
+impl Cacheable for StudyIdIndex {
+    const CACHE_FIELD: &'static str = "study_ids";
 
-    fn cache_field(&self) -> &str {
-        "studies"
+    fn get_key(&self) -> &str {
+        &self.study_id
     }
 }
 
@@ -68,6 +109,15 @@ pub struct StudyCreate {
     pub organization_id: String,
 }
 
+impl KnownFields for StudyCreate {
+    const FIELDS: &'static [&'static str] = &[
+        "study_id",
+        "study_name",
+        "study_description",
+        "organization_id",
+    ];
+}
+
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct StudyUpdate {
@@ -78,3 +128,60 @@ pub struct StudyUpdate {
     pub study_description: Option<String>,
     pub organization_id: String,
 }
+
+impl KnownFields for StudyUpdate {
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "study_id",
+        "study_name",
+        "study_description",
+        "organization_id",
+    ];
+}
+
+/// Partial study update. Fields left out are unchanged, `null` clears the optional ones.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct StudyPatch {
+    pub study_id: Option<String>,
+
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    #[schema(value_type = Option<String>, nullable)]
+    pub study_name: Option<Option<String>>,
+
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    #[schema(value_type = Option<String>, nullable)]
+    pub study_description: Option<Option<String>>,
+}
+
+impl KnownFields for StudyPatch {
+    const FIELDS: &'static [&'static str] = &["study_id", "study_name", "study_description"];
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct StudyAmend {
+    /// What changed in the protocol
+    pub note: String,
+}
+
+impl KnownFields for StudyAmend {
+    const FIELDS: &'static [&'static str] = &["note"];
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct StudyAmendment {
+    /// Uniue system identifier for the amendment
+    pub id: String,
+    pub study_id: String,
+
+    /// Protocol version the amendment produced
+    pub protocol_version: String,
+    pub note: String,
+
+    /// Date the amendment was made
+    #[serde(with = "timestamp")]
+    #[schema(value_type = String, format = DateTime)]
+    pub date_added: DateTime<Utc>,
+}
@@ -0,0 +1,66 @@
+//! Serde helpers for timestamps.
+//!
+//! chrono's default serialization trims trailing zeros from the fractional seconds, so the
+//! length of the string changes from value to value. Use with `#[serde(with = "timestamp")]`
+//! to always emit RFC 3339 in UTC with microsecond precision and a `Z` suffix, e.g.
+//! `2024-08-15T12:00:00.000000Z`. Microseconds match the precision Postgres stores.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+pub fn serialize<S>(value: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&value.to_rfc3339_opts(SecondsFormat::Micros, true))
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    DateTime::parse_from_rfc3339(&value)
+        .map(|d| d.with_timezone(&Utc))
+        .map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    use crate::models::organization::Organization;
+
+    #[test]
+    fn serialize_organization_timestamps() {
+        let organization = Organization {
+            id: "id".to_string().into(),
+            name: "name".to_string(),
+            active: true,
+            date_added: Utc.with_ymd_and_hms(2024, 8, 15, 12, 0, 0).unwrap(),
+            date_modified: Utc.with_ymd_and_hms(2024, 8, 15, 12, 30, 5).unwrap()
+                + chrono::Duration::microseconds(120),
+        };
+
+        let value = serde_json::to_value(&organization).unwrap();
+
+        assert_eq!(value["date_added"], "2024-08-15T12:00:00.000000Z");
+        assert_eq!(value["date_modified"], "2024-08-15T12:30:05.000120Z");
+    }
+
+    #[test]
+    fn deserialize_offset_to_utc() {
+        let value = serde_json::json!({
+            "id": "id",
+            "name": "name",
+            "active": true,
+            "date_added": "2024-08-15T14:00:00+02:00",
+            "date_modified": "2024-08-15T12:00:00Z",
+        });
+
+        let organization: Organization = serde_json::from_value(value).unwrap();
+
+        assert_eq!(organization.date_added, organization.date_modified);
+    }
+}
@@ -0,0 +1,99 @@
+use chrono::{DateTime, Utc};
+
+use crate::models::{
+    organization::{Organization, OrganizationInDb},
+    study::StudyInDb,
+    user::UserInDb,
+};
+
+/// Models that record when they were added and last modified
+#[allow(dead_code)]
+pub trait Timestamped {
+    fn date_added(&self) -> DateTime<Utc>;
+
+    fn date_modified(&self) -> DateTime<Utc>;
+
+    fn set_date_modified(&mut self, date_modified: DateTime<Utc>);
+
+    /// Marks the model as modified now, `date_added` is left as is
+    fn touch(&mut self) {
+        self.set_date_modified(Utc::now());
+    }
+}
+
+/// `date_added` and `date_modified` for a model that is about to be created. Both are the same
+/// instant so a new row never looks like it was modified after it was added.
+pub fn new_timestamps() -> (DateTime<Utc>, DateTime<Utc>) {
+    let now = Utc::now();
+    (now, now)
+}
+
+/// Implements `Timestamped` for structs with `date_added` and `date_modified` fields
+macro_rules! impl_timestamped {
+    ($($model:ty),+ $(,)?) => {
+        $(
+            impl Timestamped for $model {
+                fn date_added(&self) -> DateTime<Utc> {
+                    self.date_added
+                }
+
+                fn date_modified(&self) -> DateTime<Utc> {
+                    self.date_modified
+                }
+
+                fn set_date_modified(&mut self, date_modified: DateTime<Utc>) {
+                    self.date_modified = date_modified;
+                }
+            }
+        )+
+    };
+}
+
+impl_timestamped!(Organization, OrganizationInDb, StudyInDb, UserInDb);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn new_timestamps_match() {
+        let (date_added, date_modified) = new_timestamps();
+
+        assert_eq!(date_added, date_modified);
+    }
+
+    #[tokio::test]
+    async fn touch_only_advances_date_modified() {
+        let mut organization = OrganizationInDb::prepare_create("Heart of Gold".to_string())
+            .await
+            .unwrap();
+        let date_added = organization.date_added();
+        let date_modified = organization.date_modified();
+        thread::sleep(Duration::from_millis(2));
+
+        organization.touch();
+
+        assert_eq!(organization.date_added(), date_added);
+        assert!(organization.date_modified() > date_modified);
+    }
+
+    #[tokio::test]
+    async fn touch_study() {
+        let mut study = StudyInDb::prepare_create(
+            "ONC-0001".to_string(),
+            None,
+            None,
+            "organization".to_string(),
+        )
+        .await
+        .unwrap();
+        let date_added = study.date_added;
+        thread::sleep(Duration::from_millis(2));
+
+        study.touch();
+
+        assert_eq!(study.date_added, date_added);
+        assert!(study.date_modified > date_added);
+    }
+}
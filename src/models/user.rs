@@ -1,23 +1,27 @@
+use std::fmt;
+
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
-    models::{organization::Organization, study::Study},
+    models::{organization::Organization, study::Study, timestamp, timestamped::new_timestamps},
     services::cache_services::Cacheable,
-    utils::{generate_db_id, hash_password},
+    strict_json::KnownFields,
+    utils::{generate_db_id, hash_password, PasswordHashPermits},
 };
 
+/// Mirrors the `accesslevel` Postgres enum, variant names must match its labels exactly
 #[derive(Debug, Deserialize, Serialize, sqlx::Type)]
-#[sqlx(rename_all = "snake_case")]
+#[sqlx(type_name = "accesslevel", rename_all = "snake_case")]
 pub enum AccessLevel {
     OrganizationAdmin,
     SystemAdmin,
     User,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub struct UserInDb {
     pub id: String,
@@ -25,36 +29,66 @@ pub struct UserInDb {
     pub first_name: String,
     pub last_name: String,
     pub email: String,
+    pub phone: Option<String>,
+    /// Never serialized so the hash can't end up in a response or log even if a `UserInDb` is
+    /// passed where a `User` was meant
+    #[serde(skip_serializing)]
     pub hashed_password: String,
     pub organization_id: String,
     pub active: bool,
     pub access_level: AccessLevel,
+    #[serde(with = "timestamp")]
     pub date_added: DateTime<Utc>,
+    #[serde(with = "timestamp")]
     pub date_modified: DateTime<Utc>,
 }
 
+/// Written out by hand so `hashed_password` is redacted when a `UserInDb` is logged
+impl fmt::Debug for UserInDb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserInDb")
+            .field("id", &self.id)
+            .field("user_name", &self.user_name)
+            .field("first_name", &self.first_name)
+            .field("last_name", &self.last_name)
+            .field("email", &self.email)
+            .field("phone", &self.phone)
+            .field("hashed_password", &"[redacted]")
+            .field("organization_id", &self.organization_id)
+            .field("active", &self.active)
+            .field("access_level", &self.access_level)
+            .field("date_added", &self.date_added)
+            .field("date_modified", &self.date_modified)
+            .finish()
+    }
+}
+
 impl UserInDb {
     pub async fn prepare_create(
         user_name: String,
         first_name: String,
         last_name: String,
         email: String,
+        phone: Option<String>,
         password: String,
         organization_id: String,
+        password_hash_permits: &PasswordHashPermits,
     ) -> Result<Self> {
-        let hashed_password = hash_password(&password).await?;
+        let hashed_password = hash_password(password_hash_permits, &password).await?;
+        let (date_added, date_modified) = new_timestamps();
         Ok(Self {
             id: generate_db_id(),
             user_name,
             first_name,
             last_name,
             email,
+            phone,
             hashed_password,
             organization_id,
             active: true,
             access_level: AccessLevel::User,
-            date_added: Utc::now(),
-            date_modified: Utc::now(),
+            date_added,
+            date_modified,
         })
     }
 }
@@ -68,19 +102,20 @@ pub struct User {
     pub first_name: String,
     pub last_name: String,
     pub email: String,
+
+    /// Contact phone number in E.164 format
+    pub phone: Option<String>,
     pub organization: Organization,
     pub studies: Option<Vec<Study>>,
     pub active: bool,
 }
 
 impl Cacheable for User {
+    const CACHE_FIELD: &'static str = "users";
+
     fn get_key(&self) -> &str {
         &self.id
     }
-
-    fn cache_field(&self) -> &str {
-        "users"
-    }
 }
 
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
@@ -90,10 +125,49 @@ pub struct UserCreate {
     pub first_name: String,
     pub last_name: String,
     pub email: String,
+
+    /// Contact phone number in E.164 format, e.g. +15555550123
+    pub phone: Option<String>,
     pub password: String,
     pub organization_id: String,
 }
 
+impl KnownFields for UserCreate {
+    const FIELDS: &'static [&'static str] = &[
+        "user_name",
+        "first_name",
+        "last_name",
+        "email",
+        "phone",
+        "password",
+        "organization_id",
+    ];
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct OrganizationAdminCreate {
+    pub user_name: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+
+    /// Contact phone number in E.164 format, e.g. +15555550123
+    pub phone: Option<String>,
+    pub password: String,
+}
+
+impl KnownFields for OrganizationAdminCreate {
+    const FIELDS: &'static [&'static str] = &[
+        "user_name",
+        "first_name",
+        "last_name",
+        "email",
+        "phone",
+        "password",
+    ];
+}
+
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct UserUpdate {
@@ -103,11 +177,71 @@ pub struct UserUpdate {
     pub first_name: String,
     pub last_name: String,
     pub email: String,
+
+    /// Contact phone number in E.164 format, e.g. +15555550123
+    pub phone: Option<String>,
     pub password: Option<String>,
     pub active: bool,
     pub organization_id: String,
 }
 
+impl KnownFields for UserUpdate {
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "user_name",
+        "first_name",
+        "last_name",
+        "email",
+        "phone",
+        "password",
+        "active",
+        "organization_id",
+    ];
+}
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+#[into_params(parameter_in = Query)]
+pub struct UserInclude {
+    /// Comma separated list of related data to include in the response. Currently only
+    /// `studies` is supported, they are omitted when not requested
+    pub include: Option<String>,
+}
+
+impl UserInclude {
+    pub fn studies(&self) -> bool {
+        self.include
+            .as_deref()
+            .map(|i| i.split(',').any(|v| v.trim() == "studies"))
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct UserStudiesRemoved {
+    /// Number of studies the user was removed from
+    pub removed: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct UserStudiesTransfer {
+    /// User who takes over the studies
+    pub target_user_id: String,
+}
+
+impl KnownFields for UserStudiesTransfer {
+    const FIELDS: &'static [&'static str] = &["target_user_id"];
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct UserStudiesTransferred {
+    /// Number of studies moved to the target user
+    pub transferred: usize,
+}
+
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct UserStudy {
@@ -117,3 +251,41 @@ pub struct UserStudy {
     /// Study's unique system identifier
     pub study_id: String,
 }
+
+impl KnownFields for UserStudy {
+    const FIELDS: &'static [&'static str] = &["user_id", "study_id"];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn user_in_db_omits_hashed_password() {
+        let user = UserInDb::prepare_create(
+            "user_name".to_string(),
+            "First".to_string(),
+            "Last".to_string(),
+            "user@example.com".to_string(),
+            None,
+            "password".to_string(),
+            generate_db_id(),
+            &PasswordHashPermits::default(),
+        )
+        .await
+        .unwrap();
+        assert!(!user.hashed_password.is_empty());
+
+        let serialized = serde_json::to_value(&user).unwrap();
+
+        assert!(serialized.get("hashed_password").is_none());
+        assert!(!serialized.to_string().contains(&user.hashed_password));
+        assert_eq!(serialized["user_name"], "user_name");
+
+        let debug = format!("{user:?}");
+
+        assert!(!debug.contains(&user.hashed_password));
+        assert!(debug.contains("hashed_password: \"[redacted]\""));
+        assert!(debug.contains("user_name"));
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{models::timestamp, strict_json::KnownFields, utils::generate_db_id};
+
+/// Changes that can be subscribed to
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    StudyCreated,
+    StudyUpdated,
+    StudyDeleted,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::StudyCreated => "study_created",
+            Self::StudyUpdated => "study_updated",
+            Self::StudyDeleted => "study_deleted",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct WebhookInDb {
+    pub id: String,
+    pub url: String,
+    /// Never serialized so the signing key can't end up in a response or log
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub event_types: Vec<String>,
+    #[serde(with = "timestamp")]
+    pub date_added: DateTime<Utc>,
+    #[serde(with = "timestamp")]
+    pub date_modified: DateTime<Utc>,
+}
+
+/// Written out by hand so `secret` is redacted when a `WebhookInDb` is logged
+impl fmt::Debug for WebhookInDb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookInDb")
+            .field("id", &self.id)
+            .field("url", &self.url)
+            .field("secret", &"[redacted]")
+            .field("event_types", &self.event_types)
+            .field("date_added", &self.date_added)
+            .field("date_modified", &self.date_modified)
+            .finish()
+    }
+}
+
+impl WebhookInDb {
+    pub fn prepare_create(url: String, secret: String, event_types: &[WebhookEvent]) -> Self {
+        Self {
+            id: generate_db_id(),
+            url,
+            secret,
+            event_types: event_types.iter().map(|e| e.as_str().to_string()).collect(),
+            date_added: Utc::now(),
+            date_modified: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Webhook {
+    /// Unique system identifier for the webhook
+    pub id: String,
+
+    /// Address events are POSTed to
+    pub url: String,
+
+    /// Events the webhook is subscribed to
+    pub event_types: Vec<String>,
+
+    #[serde(with = "timestamp")]
+    #[schema(value_type = String, format = DateTime)]
+    pub date_added: DateTime<Utc>,
+
+    #[serde(with = "timestamp")]
+    #[schema(value_type = String, format = DateTime)]
+    pub date_modified: DateTime<Utc>,
+}
+
+impl From<WebhookInDb> for Webhook {
+    fn from(webhook: WebhookInDb) -> Self {
+        Self {
+            id: webhook.id,
+            url: webhook.url,
+            event_types: webhook.event_types,
+            date_added: webhook.date_added,
+            date_modified: webhook.date_modified,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct WebhookCreate {
+    /// Address events are POSTed to, must be http or https
+    pub url: String,
+
+    /// Shared secret used to sign each payload with HMAC-SHA256
+    pub secret: String,
+
+    /// Events to subscribe to
+    pub event_types: Vec<WebhookEvent>,
+}
+
+impl KnownFields for WebhookCreate {
+    const FIELDS: &'static [&'static str] = &["url", "secret", "event_types"];
+}
+
+/// Body sent to subscribers
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct WebhookPayload<T> {
+    pub event: WebhookEvent,
+    #[serde(with = "timestamp")]
+    pub timestamp: DateTime<Utc>,
+    pub data: T,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhook_in_db_omits_secret() {
+        let webhook = WebhookInDb::prepare_create(
+            "https://example.com/hook".to_string(),
+            "signing-secret".to_string(),
+            &[WebhookEvent::StudyCreated],
+        );
+
+        let serialized = serde_json::to_value(&webhook).unwrap();
+
+        assert!(serialized.get("secret").is_none());
+        assert_eq!(serialized["url"], "https://example.com/hook");
+
+        let debug = format!("{webhook:?}");
+
+        assert!(!debug.contains("signing-secret"));
+        assert!(debug.contains("secret: \"[redacted]\""));
+    }
+}
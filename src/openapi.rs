@@ -1,45 +1,244 @@
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+
+use anyhow::{bail, Result};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
 use utoipa::OpenApi;
 
-use crate::{models, routes};
+use crate::{models, routes, state::AppState};
+
+/// Path the OpenAPI spec is served from by the Swagger UI router
+pub const OPENAPI_PATH: &str = "/api-doc/openapi.json";
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
+        routes::admin::get_cache_stats,
+        routes::admin::set_maintenance,
+        routes::config::get_config,
+        routes::organization::bootstrap_organization,
         routes::organization::create_organization,
         routes::organization::delete_organization,
         routes::organization::get_organization,
+        routes::organization::get_organization_count,
+        routes::organization::get_organization_users,
         routes::organization::get_organizations,
+        routes::organization::get_organizations_batch,
         routes::organization::update_organization,
+        routes::search::search,
+        routes::study::amend_study,
         routes::study::create_study,
         routes::study::delete_study,
         routes::study::get_studies,
         routes::study::get_study,
+        routes::study::get_study_by_study_id,
+        routes::study::get_study_count,
+        routes::study::lock_study,
+        routes::study::patch_study,
+        routes::study::study_events,
+        routes::study::unlock_study,
         routes::study::update_study,
+        routes::study::update_study_by_id,
+        routes::user::activate_user,
         routes::user::create_user,
+        routes::user::deactivate_user,
         routes::user::delete_user,
         routes::user::get_user,
+        routes::user::get_user_count,
+        routes::user::get_user_studies,
         routes::user::get_users,
         routes::user::update_user,
+        routes::user::update_user_by_id,
         routes::user::user_add_study,
+        routes::user::user_remove_all_studies,
         routes::user::user_remove_study,
+        routes::user::user_transfer_studies,
+        routes::version::get_version,
+        routes::webhook::create_webhook,
+        routes::webhook::delete_webhook,
     ),
     components(schemas(
+        routes::config::ClientConfig,
+        routes::config::Features,
+        routes::version::VersionInfo,
+        models::cache::CacheStats,
+        models::maintenance::MaintenanceMode,
         models::messages::GenericMessage,
         models::organization::Organization,
+        models::organization::OrganizationBatch,
+        models::organization::OrganizationBootstrap,
+        models::organization::OrganizationBootstrapped,
         models::organization::OrganizationCreate,
         models::organization::OrganizationUpdate,
+        models::response::Count,
+        models::response::OrganizationList,
+        models::response::StudyList,
+        models::response::UserList,
+        models::search::SearchResults,
         models::study::Study,
+        models::study::StudyAmend,
         models::study::StudyCreate,
+        models::study::StudyPatch,
         models::study::StudyUpdate,
+        models::user::OrganizationAdminCreate,
         models::user::User,
         models::user::UserCreate,
+        models::user::UserStudiesRemoved,
+        models::user::UserStudiesTransfer,
+        models::user::UserStudiesTransferred,
         models::user::UserStudy,
         models::user::UserUpdate,
+        models::webhook::Webhook,
+        models::webhook::WebhookCreate,
+        models::webhook::WebhookEvent,
     )),
     tags(
+        (name = "Admin", description = "Server administration"),
+        (name = "Config", description = "Client configuration"),
         (name = "Organizations", description = "Organization management"),
+        (name = "Search", description = "Search across entities"),
         (name = "Studies", description = "Study management"),
         (name = "Users", description = "User managmenet"),
+        (name = "Webhooks", description = "Event notifications"),
     ),
 )]
 pub struct ApiDoc;
+
+pub fn generate_openapi_json() -> Result<String> {
+    Ok(ApiDoc::openapi().to_pretty_json()?)
+}
+
+pub fn write_openapi(path: &Path) -> Result<()> {
+    let spec = generate_openapi_json()?;
+    fs::write(path, spec)?;
+
+    Ok(())
+}
+
+pub fn check_openapi(path: &Path) -> Result<()> {
+    let spec = generate_openapi_json()?;
+    let existing = match fs::read_to_string(path) {
+        Ok(e) => e,
+        Err(e) => bail!("Unable to read {}: {}", path.display(), e.to_string()),
+    };
+
+    if existing != spec {
+        bail!("{} is out of date, regenerate it", path.display());
+    }
+
+    Ok(())
+}
+
+/// Strong ETag for the served spec. The spec only changes between builds so it is hashed once.
+pub fn openapi_etag() -> &'static str {
+    static ETAG: OnceLock<String> = OnceLock::new();
+    ETAG.get_or_init(|| {
+        let spec = ApiDoc::openapi().to_json().unwrap_or_default();
+        format!("\"{}\"", hex::encode(Sha256::digest(spec.as_bytes())))
+    })
+}
+
+/// Whether an `If-None-Match` header matches `etag`, ignoring weak validator prefixes
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+/// Adds `Cache-Control` to the OpenAPI spec and Swagger UI assets, which are static per build.
+/// The spec also gets an `ETag` and a conditional request for it is answered with a `304`.
+pub async fn docs_cache_headers(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let cache_control = match state.config.docs_cache_max_age_secs {
+        0 => HeaderValue::from_static("no-cache"),
+        max_age => HeaderValue::from_str(&format!("public, max-age={max_age}"))
+            .expect("cache control header is always valid"),
+    };
+    let is_spec = request.uri().path() == OPENAPI_PATH;
+    let etag = HeaderValue::from_static(openapi_etag());
+
+    if is_spec && etag_matches(request.headers(), openapi_etag()) {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, etag), (header::CACHE_CONTROL, cache_control)],
+        )
+            .into_response();
+    }
+
+    let mut response = next.run(request).await;
+    if response.status().is_success() {
+        let headers = response.headers_mut();
+        headers.insert(header::CACHE_CONTROL, cache_control);
+        if is_spec {
+            headers.insert(header::ETAG, etag);
+        }
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use uuid::Uuid;
+
+    #[test]
+    fn generated_spec_is_valid_openapi() {
+        let spec = generate_openapi_json().unwrap();
+        let parsed: utoipa::openapi::OpenApi = serde_json::from_str(&spec).unwrap();
+
+        assert!(!parsed.paths.paths.is_empty());
+    }
+
+    #[test]
+    fn check_openapi_detects_stale_file() {
+        let path = env::temp_dir().join(format!("{}.json", Uuid::new_v4()));
+
+        write_openapi(&path).unwrap();
+        assert!(check_openapi(&path).is_ok());
+
+        fs::write(&path, "{}").unwrap();
+        assert!(check_openapi(&path).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn etag_is_stable() {
+        assert_eq!(openapi_etag(), openapi_etag());
+        assert!(openapi_etag().starts_with('"') && openapi_etag().ends_with('"'));
+    }
+
+    #[test]
+    fn etag_matching() {
+        let etag = openapi_etag();
+        let mut headers = HeaderMap::new();
+        assert!(!etag_matches(&headers, etag));
+
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_str(&format!("\"other\", W/{etag}")).unwrap(),
+        );
+        assert!(etag_matches(&headers, etag));
+
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"other\""));
+        assert!(!etag_matches(&headers, etag));
+    }
+}
@@ -0,0 +1,208 @@
+use anyhow::{bail, Result};
+use argon2::{
+    password_hash::{
+        rand_core::{OsRng, RngCore},
+        PasswordHash, PasswordHasher as _, PasswordVerifier, SaltString,
+    },
+    Argon2,
+};
+use pbkdf2::pbkdf2_hmac;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+/// Names `PASSWORD_HASH_ALGORITHM` accepts
+pub const PASSWORD_HASH_ALGORITHMS: [&str; 2] = ["argon2", "pbkdf2-sha256"];
+
+static ARGON2: Argon2Hasher = Argon2Hasher;
+
+static PBKDF2: Pbkdf2Hasher = Pbkdf2Hasher {
+    iterations: PBKDF2_ITERATIONS,
+};
+
+/// Iterations for new PBKDF2 hashes, OWASP's recommendation for PBKDF2-HMAC-SHA256
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// A way of hashing passwords. Each hash starts with a prefix naming the algorithm that made it
+/// so a stored hash is always verified with the right one, whichever is configured for new
+/// hashes. This lets a deployment switch algorithms while older hashes are still around.
+pub trait PasswordHasher: Send + Sync {
+    /// What `PASSWORD_HASH_ALGORITHM` calls this hasher
+    fn name(&self) -> &'static str;
+
+    /// Start of every hash this hasher makes
+    fn prefix(&self) -> &'static str;
+
+    fn hash(&self, password: &str) -> Result<String>;
+
+    fn verify(&self, password: &str, hashed_password: &str) -> Result<()>;
+}
+
+/// Hasher for new passwords when `PASSWORD_HASH_ALGORITHM` isn't set
+pub fn default_hasher() -> &'static dyn PasswordHasher {
+    &ARGON2
+}
+
+/// The hasher called `name`, `None` for an unknown name
+pub fn hasher_named(name: &str) -> Option<&'static dyn PasswordHasher> {
+    hashers().find(|h| h.name() == name)
+}
+
+/// The hasher that made `hashed_password`, going by its prefix
+pub fn hasher_for(hashed_password: &str) -> Result<&'static dyn PasswordHasher> {
+    match hashers().find(|h| hashed_password.starts_with(h.prefix())) {
+        Some(h) => Ok(h),
+        None => bail!("Unrecognized password hash algorithm"),
+    }
+}
+
+fn hashers() -> impl Iterator<Item = &'static dyn PasswordHasher> {
+    [
+        &ARGON2 as &'static dyn PasswordHasher,
+        &PBKDF2 as &'static dyn PasswordHasher,
+    ]
+    .into_iter()
+}
+
+/// Argon2id in its PHC string format, the default
+pub struct Argon2Hasher;
+
+impl PasswordHasher for Argon2Hasher {
+    fn name(&self) -> &'static str {
+        "argon2"
+    }
+
+    fn prefix(&self) -> &'static str {
+        "$argon2"
+    }
+
+    fn hash(&self, password: &str) -> Result<String> {
+        let salt = SaltString::generate(&mut OsRng);
+        let hashed_password = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)?
+            .to_string();
+
+        Ok(hashed_password)
+    }
+
+    fn verify(&self, password: &str, hashed_password: &str) -> Result<()> {
+        let parsed_hash = PasswordHash::new(hashed_password)?;
+        Argon2::default().verify_password(password.as_bytes(), &parsed_hash)?;
+
+        Ok(())
+    }
+}
+
+/// PBKDF2-HMAC-SHA256 for deployments whose policy calls for PBKDF2 rather than Argon2. The
+/// algorithm is on the FIPS approved list, but the RustCrypto implementation used here isn't a
+/// validated module. Hashes are stored as `$pbkdf2-sha256$i=<iterations>$<salt hex>$<hash hex>`.
+pub struct Pbkdf2Hasher {
+    iterations: u32,
+}
+
+impl PasswordHasher for Pbkdf2Hasher {
+    fn name(&self) -> &'static str {
+        "pbkdf2-sha256"
+    }
+
+    fn prefix(&self) -> &'static str {
+        "$pbkdf2-sha256$"
+    }
+
+    fn hash(&self, password: &str) -> Result<String> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let hash = pbkdf2_sha256(password.as_bytes(), &salt, self.iterations);
+
+        Ok(format!(
+            "{}i={}${}${}",
+            self.prefix(),
+            self.iterations,
+            hex::encode(salt),
+            hex::encode(hash)
+        ))
+    }
+
+    fn verify(&self, password: &str, hashed_password: &str) -> Result<()> {
+        let parts = hashed_password
+            .strip_prefix(self.prefix())
+            .map(|p| p.split('$').collect::<Vec<_>>());
+        let Some([iterations, salt, hash]) = parts.as_deref() else {
+            bail!("Invalid PBKDF2 password hash");
+        };
+        let iterations = match iterations.strip_prefix("i=").map(str::parse::<u32>) {
+            Some(Ok(i)) if i > 0 => i,
+            _ => bail!("Invalid PBKDF2 password hash"),
+        };
+        let salt = hex::decode(salt)?;
+        let expected = hex::decode(hash)?;
+        let actual = pbkdf2_sha256(password.as_bytes(), &salt, iterations);
+
+        // Constant time so the time taken doesn't show how much of the hash matched
+        if !bool::from(actual.as_slice().ct_eq(expected.as_slice())) {
+            bail!("Password does not match");
+        }
+
+        Ok(())
+    }
+}
+
+/// PBKDF2 with HMAC-SHA256 and a 32 byte output
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut output = [0u8; 32];
+    pbkdf2_hmac::<Sha256>(password, salt, iterations, &mut output);
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keeps the tests quick, verification reads the iteration count from the hash
+    const FAST_PBKDF2: Pbkdf2Hasher = Pbkdf2Hasher { iterations: 1000 };
+
+    #[test]
+    fn pbkdf2_known_answers() {
+        assert_eq!(
+            hex::encode(pbkdf2_sha256(b"password", b"salt", 1)),
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+        );
+        assert_eq!(
+            hex::encode(pbkdf2_sha256(b"password", b"salt", 4096)),
+            "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"
+        );
+    }
+
+    #[test]
+    fn hashes_verify_after_switching_algorithm() {
+        // Made while argon2 was configured, then verified with pbkdf2-sha256 as the default
+        let argon2_hash = ARGON2.hash("Somepassword1!").unwrap();
+        let pbkdf2_hash = FAST_PBKDF2.hash("Somepassword1!").unwrap();
+
+        for hashed_password in [&argon2_hash, &pbkdf2_hash] {
+            let hasher = hasher_for(hashed_password).unwrap();
+
+            assert!(hasher.verify("Somepassword1!", hashed_password).is_ok());
+            assert!(hasher.verify("Otherpassword1!", hashed_password).is_err());
+        }
+
+        assert_eq!(hasher_for(&argon2_hash).unwrap().name(), "argon2");
+        assert_eq!(hasher_for(&pbkdf2_hash).unwrap().name(), "pbkdf2-sha256");
+    }
+
+    #[test]
+    fn unrecognized_hash() {
+        assert!(hasher_for("$2b$12$bcrypt").is_err());
+        assert!(PBKDF2
+            .verify("Somepassword1!", "$pbkdf2-sha256$i=0$00$00")
+            .is_err());
+    }
+
+    #[test]
+    fn hasher_names() {
+        for name in PASSWORD_HASH_ALGORITHMS {
+            assert_eq!(hasher_named(name).unwrap().name(), name);
+        }
+        assert!(hasher_named("md5").is_none());
+    }
+}
@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, Request, State},
+    http::{header, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+
+use crate::{
+    admin_auth::require_admin,
+    config::Config,
+    models::{
+        cache::{CacheStats, CacheStatsParams},
+        maintenance::MaintenanceMode,
+        messages::GenericMessage,
+    },
+    services::{
+        cache_services::cache_stats,
+        maintenance_services::{get_maintenance_service, set_maintenance_service},
+    },
+    state::AppState,
+    strict_json::StrictJson,
+};
+
+/// Seconds clients are asked to wait before retrying a write blocked by maintenance mode
+const MAINTENANCE_RETRY_AFTER_SECS: u32 = 300;
+
+pub fn admin_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<AppState>> {
+    let prefix = format!("{}/admin", config.api_prefix);
+    Router::new()
+        .route(&format!("{prefix}/maintenance"), post(set_maintenance))
+        .with_state(state.clone())
+        .route(&format!("{prefix}/cache/stats"), get(get_cache_stats))
+        .with_state(state.clone())
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin))
+}
+
+/// Get cache hit and miss counts for this instance
+#[utoipa::path(
+    get,
+    path = (format!("{}/admin/cache/stats", Config::new().api_prefix)),
+    params(CacheStatsParams),
+    tag = "Admin",
+    responses(
+        (status = 200, description = "Cache hit and miss counts", body = CacheStats),
+        (status = 401, description = "Missing or invalid admin API key", body = GenericMessage),
+        (status = 403, description = "Admin endpoints are disabled, or HTTPS is required", body = GenericMessage),
+    )
+)]
+pub async fn get_cache_stats(Query(params): Query<CacheStatsParams>) -> Response {
+    tracing::debug!("Getting cache stats");
+    if params.reset {
+        tracing::info!("Resetting cache stats");
+    }
+
+    (StatusCode::OK, Json(cache_stats(params.reset))).into_response()
+}
+
+/// Turn maintenance mode on or off for every instance. The response is the resulting state, which
+/// stays on while `MAINTENANCE_MODE` is set whatever the toggle says.
+#[utoipa::path(
+    post,
+    path = (format!("{}/admin/maintenance", Config::new().api_prefix)),
+    request_body = MaintenanceMode,
+    tag = "Admin",
+    responses(
+        (status = 200, description = "Maintenance mode updated", body = MaintenanceMode),
+        (status = 401, description = "Missing or invalid admin API key", body = GenericMessage),
+        (status = 403, description = "Admin endpoints are disabled, or HTTPS is required", body = GenericMessage),
+        (status = 500, description = "Maintenance mode could not be updated", body = GenericMessage)
+    )
+)]
+pub async fn set_maintenance(
+    State(state): State<Arc<AppState>>,
+    StrictJson(maintenance): StrictJson<MaintenanceMode>,
+) -> Response {
+    tracing::info!("Setting maintenance mode to {}", maintenance.enabled);
+    let valkey_pool = &state.valkey_state.pool;
+
+    match set_maintenance_service(valkey_pool, maintenance.enabled).await {
+        Ok(_) => {
+            let effective = MaintenanceMode {
+                enabled: state.config.maintenance_mode || maintenance.enabled,
+            };
+            (StatusCode::OK, Json(effective)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Error setting maintenance mode: {}", e.to_string());
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(GenericMessage {
+                    detail: "An error occurred while setting maintenance mode".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// POST routes, under the API prefix, that only read and so are let through during maintenance
+const READ_ONLY_POSTS: [&str; 1] = ["/organization/batch"];
+
+/// Rejects writes with a 503 while maintenance mode is on, either from `MAINTENANCE_MODE` or the
+/// runtime toggle. Reads, including the `READ_ONLY_POSTS`, and the toggle itself are always let
+/// through.
+pub async fn maintenance_guard(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_write = matches!(
+        *request.method(),
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    );
+    let path = request
+        .uri()
+        .path()
+        .strip_prefix(state.config.api_prefix.as_str());
+    let is_exempt = path.is_some_and(|p| p == "/admin/maintenance" || READ_ONLY_POSTS.contains(&p));
+
+    if !is_write || is_exempt {
+        return next.run(request).await;
+    }
+
+    let enabled = state.config.maintenance_mode
+        || get_maintenance_service(&state.valkey_state.pool)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!("Error reading maintenance mode: {}", e.to_string());
+                false
+            });
+
+    if !enabled {
+        return next.run(request).await;
+    }
+
+    tracing::debug!(
+        "Blocking {} {} during maintenance",
+        request.method(),
+        request.uri()
+    );
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(
+            header::RETRY_AFTER,
+            MAINTENANCE_RETRY_AFTER_SECS.to_string(),
+        )],
+        Json(GenericMessage {
+            detail: "The server is in maintenance mode, try again later".to_string(),
+        }),
+    )
+        .into_response()
+}
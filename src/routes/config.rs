@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{config::Config, services::cache_services::cache_available, state::AppState};
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Features {
+    /// Outgoing email is turned on
+    pub email: bool,
+
+    /// The cache is in use. False while valkey is being bypassed after connection failures
+    pub cache: bool,
+
+    /// Metrics collection is turned on
+    pub metrics: bool,
+}
+
+/// Public, non-sensitive settings for clients. Never add secrets to this.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ClientConfig {
+    pub features: Features,
+
+    /// The running server's version
+    pub version: String,
+
+    /// Banner for clients to show users at login
+    pub message: Option<String>,
+}
+
+pub fn config_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<AppState>> {
+    let prefix = format!("{}/config", config.api_prefix);
+    Router::new()
+        .route(&prefix, get(get_config))
+        .with_state(state.clone())
+}
+
+/// Get the features enabled on this server
+#[utoipa::path(
+    get,
+    path = (format!("{}/config", Config::new().api_prefix)),
+    tag = "Config",
+    responses(
+        (status = 200, description = "Enabled features and server version", body = ClientConfig),
+    )
+)]
+pub async fn get_config(State(state): State<Arc<AppState>>) -> Response {
+    Json(ClientConfig {
+        features: Features {
+            email: state.config.email_enabled,
+            cache: cache_available(),
+            metrics: state.config.metrics_enabled,
+        },
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        message: state.config.banner_message.clone(),
+    })
+    .into_response()
+}
@@ -0,0 +1,54 @@
+use axum::{
+    http::{header, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::{config::Config, models::messages::GenericMessage};
+
+/// Handler for paths that don't match any route
+pub async fn not_found(uri: Uri) -> Response {
+    tracing::debug!("No route for {uri}");
+    (
+        StatusCode::NOT_FOUND,
+        Json(GenericMessage {
+            detail: format!("No route found for {}", uri.path()),
+        }),
+    )
+        .into_response()
+}
+
+/// Response for deleting something that doesn't exist. With `IDEMPOTENT_DELETES` a repeat delete
+/// succeeds like the first one did, otherwise it is a 404 with `detail`.
+pub fn missing_on_delete(config: &Config, detail: String) -> Response {
+    if config.idempotent_deletes {
+        tracing::debug!("Nothing to delete, treating as already deleted: {detail}");
+        return StatusCode::NO_CONTENT.into_response();
+    }
+
+    (StatusCode::NOT_FOUND, Json(GenericMessage { detail })).into_response()
+}
+
+/// Replaces axum's empty 405 body with a GenericMessage, keeping the Allow header it sets
+pub async fn method_not_allowed(response: Response) -> Response {
+    if response.status() != StatusCode::METHOD_NOT_ALLOWED {
+        return response;
+    }
+
+    let allow = response.headers().get(header::ALLOW).cloned();
+    let detail = match allow.as_ref().and_then(|a| a.to_str().ok()) {
+        Some(a) => format!("Method not allowed, allowed methods: {a}"),
+        None => "Method not allowed".to_string(),
+    };
+    let mut response = (
+        StatusCode::METHOD_NOT_ALLOWED,
+        Json(GenericMessage { detail }),
+    )
+        .into_response();
+
+    if let Some(a) = allow {
+        response.headers_mut().insert(header::ALLOW, a);
+    }
+
+    response
+}
@@ -1,22 +1,44 @@
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 
 use axum::{
     extract::State,
+    http::StatusCode,
     response::{IntoResponse, Response},
     routing::get,
     Json, Router,
 };
+use bb8::Pool;
+use bb8_redis::RedisConnectionManager;
 use serde::{Deserialize, Serialize};
+use sqlx::{migrate::Migrator, postgres::PgPool};
 
-use crate::{config::Config, state::AppState};
+use crate::{config::Config, services::cache_services::cache_available, state::AppState};
 
-#[derive(Debug, Deserialize, Serialize)]
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Set once migrations are seen to be up to date so the health check stops querying for them.
+/// A migration reverted from outside while the server is running isn't noticed until a restart.
+static MIGRATIONS_UP_TO_DATE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 enum HealthStatus {
     Healthy,
+    /// Reachable but not being used, e.g. the cache while its circuit breaker is open
+    Degraded,
     Unhealthy,
 }
 
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum MigrationStatus {
+    UpToDate,
+    Pending,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 struct Health {
@@ -25,16 +47,77 @@ struct Health {
     valkey: HealthStatus,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+struct Readiness {
+    server: HealthStatus,
+    db: HealthStatus,
+    valkey: HealthStatus,
+    migrations: MigrationStatus,
+}
+
 pub fn health_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<AppState>> {
     let prefix = format!("{}/health", config.api_prefix);
-    Router::new().route(&prefix, get(health)).with_state(state)
+    Router::new()
+        .route(&prefix, get(health))
+        .route(&format!("{prefix}/ready"), get(ready))
+        .with_state(state.clone())
 }
 
 pub async fn health(State(state): State<Arc<AppState>>) -> Response {
+    let db_status = db_health(&state.db_state.pool).await;
+    let valkey_status = valkey_health(&state.valkey_state.pool).await;
+
+    Json(Health {
+        server: HealthStatus::Healthy,
+        db: db_status,
+        valkey: valkey_status,
+    })
+    .into_response()
+}
+
+pub async fn ready(State(state): State<Arc<AppState>>) -> Response {
+    let db_status = if state.db_state.healthy.load(Ordering::Relaxed) {
+        HealthStatus::Healthy
+    } else {
+        HealthStatus::Unhealthy
+    };
+    let valkey_status = if cache_available() {
+        valkey_health(&state.valkey_state.pool).await
+    } else {
+        HealthStatus::Degraded
+    };
+    let migration_status = if MIGRATIONS_UP_TO_DATE.load(Ordering::Relaxed) {
+        MigrationStatus::UpToDate
+    } else if db_status == HealthStatus::Unhealthy {
+        MigrationStatus::Pending
+    } else {
+        check_migrations(&state.db_state.pool).await
+    };
+
+    let status_code =
+        if db_status == HealthStatus::Healthy && migration_status == MigrationStatus::UpToDate {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+
+    (
+        status_code,
+        Json(Readiness {
+            server: HealthStatus::Healthy,
+            db: db_status,
+            valkey: valkey_status,
+            migrations: migration_status,
+        }),
+    )
+        .into_response()
+}
+
+async fn db_health(db_pool: &PgPool) -> HealthStatus {
     tracing::debug!("Checking db health");
-    let db_pool = state.db_state.pool.clone();
 
-    let db_status = match sqlx::query!("SELECT 1 as result").fetch_one(&db_pool).await {
+    match sqlx::query!("SELECT 1 as result").fetch_one(db_pool).await {
         Ok(_) => {
             tracing::debug!("db is healthy");
             HealthStatus::Healthy
@@ -43,12 +126,13 @@ pub async fn health(State(state): State<Arc<AppState>>) -> Response {
             tracing::debug!("db is unhealthy");
             HealthStatus::Unhealthy
         }
-    };
+    }
+}
 
+async fn valkey_health(valkey_pool: &Pool<RedisConnectionManager>) -> HealthStatus {
     tracing::debug!("Checking valkey health");
-    let valkey_status: HealthStatus;
 
-    match state.valkey_state.pool.get().await {
+    match valkey_pool.get().await {
         Ok(mut conn) => {
             let result: String = redis::cmd("PING")
                 .query_async(&mut *conn)
@@ -56,22 +140,73 @@ pub async fn health(State(state): State<Arc<AppState>>) -> Response {
                 .unwrap_or("unhealthy".to_string());
             if result == "PONG" {
                 tracing::debug!("valkey is healthy");
-                valkey_status = HealthStatus::Healthy;
+                HealthStatus::Healthy
             } else {
                 tracing::debug!("valkey is unhealthy");
-                valkey_status = HealthStatus::Unhealthy;
+                HealthStatus::Unhealthy
             }
         }
         Err(_) => {
             tracing::debug!("valkey is unhealthy");
-            valkey_status = HealthStatus::Unhealthy;
+            HealthStatus::Unhealthy
         }
     }
+}
 
-    Json(Health {
-        server: HealthStatus::Healthy,
-        db: db_status,
-        valkey: valkey_status,
-    })
-    .into_response()
+async fn check_migrations(db_pool: &PgPool) -> MigrationStatus {
+    tracing::debug!("Checking migration status");
+    let status = match applied_migrations(db_pool).await {
+        Ok(applied) => migration_status(&MIGRATOR, &applied),
+        Err(e) => {
+            tracing::error!("Error checking migration status: {}", e.to_string());
+            MigrationStatus::Pending
+        }
+    };
+
+    if status == MigrationStatus::UpToDate {
+        MIGRATIONS_UP_TO_DATE.store(true, Ordering::Relaxed);
+    }
+
+    status
+}
+
+async fn applied_migrations(db_pool: &PgPool) -> Result<Vec<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success = true")
+        .fetch_all(db_pool)
+        .await
+}
+
+fn migration_status(migrator: &Migrator, applied: &[i64]) -> MigrationStatus {
+    let pending = migrator
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .any(|m| !applied.contains(&m.version));
+
+    if pending {
+        tracing::debug!("Database has pending migrations");
+        MigrationStatus::Pending
+    } else {
+        tracing::debug!("Database migrations are up to date");
+        MigrationStatus::UpToDate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migration_status_up_to_date() {
+        let applied: Vec<i64> = MIGRATOR.iter().map(|m| m.version).collect();
+
+        assert_eq!(
+            migration_status(&MIGRATOR, &applied),
+            MigrationStatus::UpToDate
+        );
+    }
+
+    #[test]
+    fn migration_status_pending() {
+        assert_eq!(migration_status(&MIGRATOR, &[]), MigrationStatus::Pending);
+    }
 }
@@ -1,4 +1,10 @@
+pub mod admin;
+pub mod config;
+pub mod fallback;
 pub mod health;
 pub mod organization;
+pub mod search;
 pub mod study;
 pub mod user;
+pub mod version;
+pub mod webhook;
@@ -1,24 +1,33 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header, StatusCode, Uri},
     response::{IntoResponse, Response},
     routing::{delete, get, post, put},
     Json, Router,
 };
 
 use crate::{
+    client_ip::ClientIp,
     config::Config,
+    db::is_statement_timeout,
     models::{
         messages::GenericMessage,
-        organization::{OrganizationCreate, OrganizationUpdate},
-    },
-    services::organization_services::{
-        create_organization_service, delete_organization_service, get_organization_service,
-        get_organizations_service, update_organization_service,
+        organization::{
+            OrganizationBatch, OrganizationBootstrap, OrganizationCreate, OrganizationDeleteParams,
+            OrganizationId, OrganizationUpdate, MAX_ORGANIZATION_BATCH_SIZE,
+        },
+        pagination::Pagination,
+        response::Count,
+        sort::Sort,
     },
+    routes::fallback::missing_on_delete,
+    services::user_services::get_organization_users_service,
     state::AppState,
+    strict_json::StrictJson,
+    tls::{require_tls_for_sensitive, OverTls},
+    utils::validate_pattern,
 };
 
 pub fn organization_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<AppState>> {
@@ -26,18 +35,100 @@ pub fn organization_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<
     Router::new()
         .route(&prefix, post(create_organization))
         .with_state(state.clone())
+        .route(&format!("{prefix}/batch"), post(get_organizations_batch))
+        .with_state(state.clone())
+        .route(&format!("{prefix}/bootstrap"), post(bootstrap_organization))
+        .with_state(state.clone())
         .route(&format!("{prefix}/:id"), delete(delete_organization))
         .with_state(state.clone())
         .route(&format!("{prefix}/:id"), get(get_organization))
         .with_state(state.clone())
+        .route(&format!("{prefix}/:id/users"), get(get_organization_users))
+        .with_state(state.clone())
         .route(&prefix, get(get_organizations))
         .with_state(state.clone())
+        .route(&format!("{prefix}/count"), get(get_organization_count))
+        .with_state(state.clone())
         // TODO: I want to make this a patch but need to figure out how to diferentiate between
         // default None and user set None in serde.
         .route(&prefix, put(update_organization))
         .with_state(state.clone())
 }
 
+/// Add a new organization together with its first admin user
+#[utoipa::path(
+    post,
+    path = (format!("{}/organization/bootstrap", Config::new().api_prefix)),
+    request_body = OrganizationBootstrap,
+    tag = "Organizations",
+    responses(
+        (status = 201, description = "Organization and admin added successfully", body = OrganizationBootstrapped),
+        (status = 400, description = "Organization or user already exists", body = GenericMessage),
+        (status = 403, description = "HTTPS is required to send a password", body = GenericMessage),
+    )
+)]
+pub async fn bootstrap_organization(
+    State(state): State<Arc<AppState>>,
+    client_ip: ClientIp,
+    over_tls: OverTls,
+    StrictJson(bootstrap): StrictJson<OrganizationBootstrap>,
+) -> Response {
+    tracing::debug!("Bootstrapping new organization for {client_ip}");
+    if let Err(e) = require_tls_for_sensitive(&state.config, over_tls, "Setting a password") {
+        tracing::debug!("Rejecting bootstrap from {client_ip}: {}", e.to_string());
+        return (
+            StatusCode::FORBIDDEN,
+            Json(GenericMessage {
+                detail: e.to_string(),
+            }),
+        )
+            .into_response();
+    }
+    if let Err(e) = validate_pattern(
+        "user_name",
+        state.config.user_name_pattern.as_ref(),
+        &bootstrap.admin.user_name,
+    ) {
+        tracing::debug!("Rejecting user_name: {}", e.to_string());
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(GenericMessage {
+                detail: e.to_string(),
+            }),
+        )
+            .into_response();
+    }
+    match state.organizations().bootstrap(&bootstrap).await {
+        Ok(b) => {
+            tracing::debug!("Successfully bootstrapped organization");
+            (StatusCode::CREATED, Json(b)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Error bootstrapping organization: {}", e.to_string());
+
+            if e.to_string().contains("already exists")
+                || e.to_string().contains("Invalid phone number")
+            {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "An error occurred while bootstrapping organization".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
 /// Add a new organization
 #[utoipa::path(
     post,
@@ -51,13 +142,10 @@ pub fn organization_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<
 )]
 pub async fn create_organization(
     State(state): State<Arc<AppState>>,
-    Json(new_organization): Json<OrganizationCreate>,
+    StrictJson(new_organization): StrictJson<OrganizationCreate>,
 ) -> Response {
     tracing::debug!("Creating new organization");
-    let db_pool = state.db_state.pool.clone();
-    let valkey_pool = &state.valkey_state.pool;
-
-    match create_organization_service(&db_pool, valkey_pool, &new_organization).await {
+    match state.organizations().create(&new_organization).await {
         Ok(o) => {
             tracing::debug!("Organization successfully created");
             (StatusCode::OK, Json(o)).into_response()
@@ -65,14 +153,11 @@ pub async fn create_organization(
         Err(e) => {
             tracing::error!("Error creating organization: {}", e.to_string());
 
-            if e.to_string().contains("violates unique constraint") {
+            if e.to_string().contains("already exists") {
                 (
                     StatusCode::BAD_REQUEST,
                     Json(GenericMessage {
-                        detail: format!(
-                            "An organization with the name {} already exists",
-                            &new_organization.name
-                        ),
+                        detail: e.to_string(),
                     }),
                 )
                     .into_response()
@@ -94,23 +179,23 @@ pub async fn create_organization(
     delete,
     path = (format!("{}/organization/{{id}}", Config::new().api_prefix)),
     params(
-        ("id" = String, Path, description = "Organization database id")
+        ("id" = String, Path, description = "Organization database id"),
+        OrganizationDeleteParams,
     ),
     tag = "Organizations",
     responses(
         (status = 204, description = "Organization successfully deleted"),
         (status = 404, description = "Organization not found", body = GenericMessage),
+        (status = 409, description = "Organization has dependent studies or users", body = GenericMessage),
     )
 )]
 pub async fn delete_organization(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<String>,
+    Path(id): Path<OrganizationId>,
+    Query(params): Query<OrganizationDeleteParams>,
 ) -> Response {
     tracing::debug!("Deleting organization {id}");
-    let db_pool = state.db_state.pool.clone();
-    let valkey_pool = &state.valkey_state.pool;
-
-    match delete_organization_service(&db_pool, valkey_pool, &id).await {
+    match state.organizations().delete(&id, params.cascade).await {
         Ok(o) => {
             tracing::debug!("Successfully deleted organization {id}");
             (StatusCode::NO_CONTENT, Json(o)).into_response()
@@ -119,8 +204,10 @@ pub async fn delete_organization(
             tracing::error!("Error deleting organization {id}: {}", e.to_string());
 
             if e.to_string().contains("No organization with the id") {
+                missing_on_delete(&state.config, e.to_string())
+            } else if e.to_string().contains("has dependent studies or users") {
                 (
-                    StatusCode::NOT_FOUND,
+                    StatusCode::CONFLICT,
                     Json(GenericMessage {
                         detail: e.to_string(),
                     }),
@@ -154,13 +241,10 @@ pub async fn delete_organization(
 )]
 pub async fn get_organization(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<String>,
+    Path(id): Path<OrganizationId>,
 ) -> Response {
     tracing::debug!("Getting organization {id}");
-    let db_pool = state.db_state.pool.clone();
-    let valkey_pool = &state.valkey_state.pool;
-
-    match get_organization_service(&db_pool, valkey_pool, &id, false).await {
+    match state.organizations().get(&id).await {
         Ok(organization) => {
             if let Some(o) = organization {
                 tracing::debug!("Successfully retrieved organization {id}");
@@ -189,24 +273,204 @@ pub async fn get_organization(
     }
 }
 
+/// Get the users of an organization
+#[utoipa::path(
+    get,
+    path = (format!("{}/organization/{{id}}/users", Config::new().api_prefix)),
+    params(
+        ("id" = String, Path, description = "Organization database id"),
+        Pagination,
+    ),
+    tag = "Organizations",
+    responses(
+        (status = 200, description = "The organization's users", body = UserList),
+        (status = 400, description = "Invalid pagination", body = GenericMessage),
+        (status = 404, description = "Organization not found", body = GenericMessage),
+    )
+)]
+pub async fn get_organization_users(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<OrganizationId>,
+    Query(mut pagination): Query<Pagination>,
+    uri: Uri,
+) -> Response {
+    tracing::debug!("Getting users for organization {id}");
+    if let Err(e) = pagination.clamp(&state.config) {
+        tracing::debug!("Invalid pagination: {}", e.to_string());
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(GenericMessage {
+                detail: e.to_string(),
+            }),
+        )
+            .into_response();
+    }
+    let db_pool = state.db_state.read_pool.clone();
+
+    match get_organization_users_service(&db_pool, &id, &pagination).await {
+        Ok(u) => {
+            tracing::debug!("Successfully retrieved users for organization {id}");
+            let link = u.link_header(&uri);
+            (StatusCode::OK, [(header::LINK, link)], Json(u)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Error retrieving organization users: {}", e.to_string());
+
+            if e.to_string().contains("No organization with the id") {
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "Error retrieving organization users".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// Count all organizations
+#[utoipa::path(
+    get,
+    path = (format!("{}/organization/count", Config::new().api_prefix)),
+    tag = "Organizations",
+    responses(
+        (status = 200, description = "Number of organizations", body = Count),
+        (status = 503, description = "Query timed out", body = GenericMessage),
+    )
+)]
+pub async fn get_organization_count(State(state): State<Arc<AppState>>) -> Response {
+    tracing::debug!("Counting organizations");
+    match state.organizations().count().await {
+        Ok(count) => (StatusCode::OK, Json(Count { count })).into_response(),
+        Err(e) => {
+            tracing::error!("Error counting organizations: {}", e.to_string());
+
+            if is_statement_timeout(&e) {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(GenericMessage {
+                        detail: "Timed out counting organizations".to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "Error counting organizations".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
 /// Get all organizations
 #[utoipa::path(
     get,
     path = (format!("{}/organization", Config::new().api_prefix)),
+    params(Pagination, Sort),
     tag = "Organizations",
-    responses((status = 200, description = "Organization information", body = [Organization])),
+    responses(
+        (status = 200, description = "Organization information", body = OrganizationList),
+        (status = 400, description = "Invalid pagination or sort", body = GenericMessage),
+        (status = 503, description = "Query timed out", body = GenericMessage),
+    ),
 )]
-pub async fn get_organizations(State(state): State<Arc<AppState>>) -> Response {
+pub async fn get_organizations(
+    State(state): State<Arc<AppState>>,
+    Query(mut pagination): Query<Pagination>,
+    Query(sort): Query<Sort>,
+    uri: Uri,
+) -> Response {
     tracing::debug!("Getting all organizations");
-    let db_pool = state.db_state.pool.clone();
-
-    match get_organizations_service(&db_pool).await {
+    if let Err(e) = pagination
+        .clamp(&state.config)
+        .and_then(|_| sort.validate())
+    {
+        tracing::debug!("Invalid pagination or sort: {}", e.to_string());
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(GenericMessage {
+                detail: e.to_string(),
+            }),
+        )
+            .into_response();
+    }
+    match state.organizations().list(&pagination, &sort).await {
         Ok(o) => {
             tracing::debug!("Successfully retrieved all organizaiton");
-            (StatusCode::OK, Json(o)).into_response()
+            let link = o.link_header(&uri);
+            (StatusCode::OK, [(header::LINK, link)], Json(o)).into_response()
         }
         Err(e) => {
             tracing::error!("Error retrieving all organizations: {}", e.to_string());
+
+            if is_statement_timeout(&e) {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(GenericMessage {
+                        detail: "Timed out retrieving organizations".to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "Error retrieving organizations".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// Get multiple organizations by database id
+#[utoipa::path(
+    post,
+    path = (format!("{}/organization/batch", Config::new().api_prefix)),
+    request_body = OrganizationBatch,
+    tag = "Organizations",
+    responses(
+        (status = 200, description = "Organizations found for the requested ids", body = [Organization]),
+        (status = 400, description = "Too many ids requested", body = GenericMessage),
+    )
+)]
+pub async fn get_organizations_batch(
+    State(state): State<Arc<AppState>>,
+    StrictJson(batch): StrictJson<OrganizationBatch>,
+) -> Response {
+    tracing::debug!("Getting {} organizations by id", batch.ids.len());
+    if batch.ids.len() > MAX_ORGANIZATION_BATCH_SIZE {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(GenericMessage {
+                detail: format!(
+                    "At most {MAX_ORGANIZATION_BATCH_SIZE} ids can be requested at once"
+                ),
+            }),
+        )
+            .into_response();
+    }
+    match state.organizations().get_many(&batch.ids).await {
+        Ok(o) => {
+            tracing::debug!("Successfully retrieved {} organizations", o.len());
+            (StatusCode::OK, Json(o)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Error retrieving organizations: {}", e.to_string());
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(GenericMessage {
@@ -224,17 +488,17 @@ pub async fn get_organizations(State(state): State<Arc<AppState>>) -> Response {
     path = (format!("{}/organization", Config::new().api_prefix)),
     request_body = OrganizationUpdate,
     tag = "Organizations",
-    responses((status = 200, description = "Organization added successfully", body = Organization)),
+    responses(
+        (status = 200, description = "Organization added successfully", body = Organization),
+        (status = 400, description = "Organization already exists", body = GenericMessage)
+    ),
 )]
 pub async fn update_organization(
     State(state): State<Arc<AppState>>,
-    Json(update_organization): Json<OrganizationUpdate>,
+    StrictJson(update_organization): StrictJson<OrganizationUpdate>,
 ) -> Response {
     tracing::debug!("Updating organization");
-    let db_pool = state.db_state.pool.clone();
-    let valkey_pool = &state.valkey_state.pool;
-
-    match update_organization_service(&db_pool, valkey_pool, &update_organization).await {
+    match state.organizations().update(&update_organization).await {
         Ok(o) => {
             tracing::debug!("Successfully updated organization");
             (StatusCode::OK, Json(o)).into_response()
@@ -242,7 +506,15 @@ pub async fn update_organization(
         Err(e) => {
             tracing::error!("Error updating organization: {}", e.to_string());
 
-            if e.to_string().contains("no rows returned") {
+            if e.to_string().contains("already exists") {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else if e.to_string().contains("no rows returned") {
                 (
                     StatusCode::BAD_REQUEST,
                     Json(GenericMessage {
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+
+use crate::{
+    config::Config,
+    models::{messages::GenericMessage, search::SearchQuery},
+    services::search_services::search_service,
+    state::AppState,
+};
+
+pub fn search_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<AppState>> {
+    let prefix = format!("{}/search", config.api_prefix);
+    Router::new()
+        .route(&prefix, get(search))
+        .with_state(state.clone())
+}
+
+/// Search organizations, studies and users at once
+#[utoipa::path(
+    get,
+    path = (format!("{}/search", Config::new().api_prefix)),
+    params(SearchQuery),
+    tag = "Search",
+    responses(
+        (status = 200, description = "Matches for each searched type", body = SearchResults),
+        (status = 400, description = "Blank search text or unknown types", body = GenericMessage),
+    )
+)]
+pub async fn search(
+    State(state): State<Arc<AppState>>,
+    Query(search_query): Query<SearchQuery>,
+) -> Response {
+    let search_params = search_query
+        .text()
+        .and_then(|text| Ok((text, search_query.types(&state.config)?)));
+    let (text, types) = match search_params {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::debug!("Invalid search: {}", e.to_string());
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(GenericMessage {
+                    detail: e.to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+    tracing::debug!("Searching {} for {text}", types.join(", "));
+    let db_pool = state.db_state.read_pool.clone();
+
+    match search_service(
+        &db_pool,
+        text,
+        &types,
+        search_query.organization_id.as_deref(),
+    )
+    .await
+    {
+        Ok(results) => {
+            tracing::debug!("Search for {text} succeeded");
+            (StatusCode::OK, Json(results)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Error searching for {text}: {}", e.to_string());
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(GenericMessage {
+                    detail: "Error searching".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
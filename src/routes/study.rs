@@ -1,22 +1,39 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::{IntoResponse, Response},
-    routing::{delete, get, post, put},
+    extract::{Path, Query, State},
+    http::{header, StatusCode, Uri},
+    response::{
+        sse::{KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{delete, get, patch, post, put},
     Json, Router,
 };
+use serde_json::json;
 
 use crate::{
     config::Config,
+    db::is_statement_timeout,
+    models::activity::{StudyActivity, StudyActivityKind},
+    models::created_range::CreatedRange,
     models::messages::GenericMessage,
-    models::study::{StudyCreate, StudyUpdate},
+    models::pagination::Pagination,
+    models::response::Count,
+    models::sort::Sort,
+    models::study::{StudyAmend, StudyCreate, StudyPatch, StudyUpdate},
+    models::webhook::WebhookEvent,
+    routes::fallback::missing_on_delete,
+    services::activity_services::study_activity_stream,
     services::study_services::{
-        create_study_service, delete_study_service, get_studies_service, get_study_service,
-        update_study_service,
+        amend_study_service, count_studies_service, create_study_service, delete_study_service,
+        get_studies_service, get_study_by_study_id_service, get_study_service, patch_study_service,
+        set_study_lock_service, update_study_service,
     },
+    services::webhook_services::dispatch_event,
     state::AppState,
+    strict_json::{JsonFields, StrictJson},
+    utils::{validate_pattern, with_path_id},
 };
 
 pub fn study_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<AppState>> {
@@ -30,10 +47,29 @@ pub fn study_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<AppStat
         .with_state(state.clone())
         .route(&prefix, get(get_studies))
         .with_state(state.clone())
+        .route(&format!("{prefix}/count"), get(get_study_count))
+        .with_state(state.clone())
+        .route(
+            &format!("{prefix}/by-study-id/:study_id"),
+            get(get_study_by_study_id),
+        )
+        .with_state(state.clone())
         // TODO: I want to make this a patch but need to figure out how to diferentiate between
         // default None and study set None in serde.
         .route(&prefix, put(update_study))
         .with_state(state.clone())
+        .route(&format!("{prefix}/:id"), put(update_study_by_id))
+        .with_state(state.clone())
+        .route(&format!("{prefix}/:id"), patch(patch_study))
+        .with_state(state.clone())
+        .route(&format!("{prefix}/:id/events"), get(study_events))
+        .with_state(state.clone())
+        .route(&format!("{prefix}/:id/lock"), post(lock_study))
+        .with_state(state.clone())
+        .route(&format!("{prefix}/:id/unlock"), post(unlock_study))
+        .with_state(state.clone())
+        .route(&format!("{prefix}/:id/amend"), post(amend_study))
+        .with_state(state.clone())
 }
 
 /// Create a new study
@@ -49,32 +85,57 @@ pub fn study_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<AppStat
 )]
 pub async fn create_study(
     State(state): State<Arc<AppState>>,
-    Json(new_study): Json<StudyCreate>,
+    StrictJson(new_study): StrictJson<StudyCreate>,
 ) -> Response {
     tracing::debug!("Creating study");
+    if let Err(e) = validate_pattern(
+        "study_id",
+        state.config.study_id_pattern.as_ref(),
+        &new_study.study_id,
+    ) {
+        tracing::debug!("Rejecting study_id: {}", e.to_string());
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(GenericMessage {
+                detail: e.to_string(),
+            }),
+        )
+            .into_response();
+    }
     let db_pool = state.db_state.pool.clone();
     let valkey_pool = &state.valkey_state.pool;
 
     match create_study_service(&db_pool, valkey_pool, &new_study).await {
         Ok(study) => {
             tracing::debug!("Successfully created study");
+            dispatch_event(
+                db_pool.clone(),
+                state.webhook_client.clone(),
+                WebhookEvent::StudyCreated,
+                &study,
+            );
             (StatusCode::CREATED, Json(study)).into_response()
         }
         Err(e) => {
             tracing::error!("Error creating study: {}", e.to_string());
 
-            if e.to_string().contains("violates unique constraint") {
+            if e.to_string().contains("already exists") {
                 (
                     StatusCode::BAD_REQUEST,
                     Json(GenericMessage {
-                        detail: format!(
-                            "An study with the study id {} already exists",
-                            &new_study.study_id
-                        ),
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else if e.to_string().contains("Invalid study") {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
                     }),
                 )
                     .into_response()
-            } else if e.to_string().contains("No organization found") {
+            } else if e.to_string().contains("No organization") {
                 (
                     StatusCode::BAD_REQUEST,
                     Json(GenericMessage {
@@ -116,19 +177,24 @@ pub async fn delete_study(State(state): State<Arc<AppState>>, Path(id): Path<Str
     match delete_study_service(&db_pool, valkey_pool, &id).await {
         Ok(o) => {
             tracing::debug!("Successfully deleted study {id}");
+            dispatch_event(
+                db_pool.clone(),
+                state.webhook_client.clone(),
+                WebhookEvent::StudyDeleted,
+                &json!({ "id": id }),
+            );
+            state.activity.publish(StudyActivity::new(
+                &id,
+                StudyActivityKind::StudyDeleted,
+                json!({ "id": id }),
+            ));
             (StatusCode::NO_CONTENT, Json(o)).into_response()
         }
         Err(e) => {
             tracing::error!("Error deleting study: {}", e.to_string());
 
             if e.to_string().contains("No study with the id") {
-                (
-                    StatusCode::NOT_FOUND,
-                    Json(GenericMessage {
-                        detail: e.to_string(),
-                    }),
-                )
-                    .into_response()
+                missing_on_delete(&state.config, e.to_string())
             } else {
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
@@ -186,31 +252,207 @@ pub async fn get_study(State(state): State<Arc<AppState>>, Path(id): Path<String
     }
 }
 
+/// Get a study by its protocol study id
+#[utoipa::path(
+    get,
+    path = (format!("{}/study/by-study-id/{{study_id}}", Config::new().api_prefix)),
+    params(
+        ("study_id" = String, Path, description = "Protocol study id")
+    ),
+    tag = "Studies",
+    responses(
+        (status = 200, description = "Study information", body = Study),
+        (status = 404, description = "Study not found", body = GenericMessage)
+    )
+)]
+pub async fn get_study_by_study_id(
+    State(state): State<Arc<AppState>>,
+    Path(study_id): Path<String>,
+) -> Response {
+    tracing::debug!("Getting study with study id {study_id}");
+    let db_pool = state.db_state.pool.clone();
+    let valkey_pool = &state.valkey_state.pool;
+
+    match get_study_by_study_id_service(&db_pool, valkey_pool, &study_id).await {
+        Ok(study) => {
+            if let Some(s) = study {
+                tracing::debug!("Successfully retrieved study with study id {study_id}");
+                (StatusCode::OK, Json(s)).into_response()
+            } else {
+                tracing::error!("Study with study id {study_id} not found");
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(GenericMessage {
+                        detail: format!("No study with study id {study_id} found"),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+        Err(e) => {
+            tracing::error!(
+                "Error retrieving study with study id {study_id}: {}",
+                e.to_string()
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(GenericMessage {
+                    detail: "Error getting study".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Count all studies
+#[utoipa::path(
+    get,
+    path = (format!("{}/study/count", Config::new().api_prefix)),
+    tag = "Studies",
+    responses(
+        (status = 200, description = "Number of studies", body = Count),
+        (status = 503, description = "Query timed out", body = GenericMessage),
+    )
+)]
+pub async fn get_study_count(State(state): State<Arc<AppState>>) -> Response {
+    tracing::debug!("Counting studies");
+    let db_pool = state.db_state.read_pool.clone();
+
+    match count_studies_service(&db_pool, &CreatedRange::default()).await {
+        Ok(count) => (StatusCode::OK, Json(Count { count })).into_response(),
+        Err(e) => {
+            tracing::error!("Error counting studies: {}", e.to_string());
+
+            if is_statement_timeout(&e) {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(GenericMessage {
+                        detail: "Timed out counting studies".to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "Error counting studies".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
 /// Get all study
 #[utoipa::path(
     get,
     path = (format!("{}/study", Config::new().api_prefix)),
+    params(Pagination, Sort, CreatedRange),
     tag = "Studies",
     responses(
-        (status = 200, description = "All studies information", body = [Study]),
+        (status = 200, description = "All studies information", body = StudyList),
+        (status = 400, description = "Invalid pagination, sort or created range", body = GenericMessage),
+        (status = 503, description = "Query timed out", body = GenericMessage),
     )
 )]
-pub async fn get_studies(State(state): State<Arc<AppState>>) -> Response {
+pub async fn get_studies(
+    State(state): State<Arc<AppState>>,
+    Query(mut pagination): Query<Pagination>,
+    Query(sort): Query<Sort>,
+    Query(created): Query<CreatedRange>,
+    uri: Uri,
+) -> Response {
     tracing::debug!("Getting all studies");
+    if let Err(e) = pagination
+        .clamp(&state.config)
+        .and_then(|_| sort.validate())
+        .and_then(|_| created.validate())
+    {
+        tracing::debug!(
+            "Invalid pagination, sort or created range: {}",
+            e.to_string()
+        );
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(GenericMessage {
+                detail: e.to_string(),
+            }),
+        )
+            .into_response();
+    }
     let db_pool = state.db_state.pool.clone();
     let valkey_pool = &state.valkey_state.pool;
 
-    match get_studies_service(&db_pool, valkey_pool).await {
+    match get_studies_service(&db_pool, valkey_pool, &pagination, &sort, &created).await {
         Ok(u) => {
             tracing::debug!("Successfully retrieved all studies");
-            (StatusCode::OK, Json(u)).into_response()
+            let link = u.link_header(&uri);
+            (StatusCode::OK, [(header::LINK, link)], Json(u)).into_response()
         }
         Err(e) => {
             tracing::error!("Error retrieving all studies: {}", e.to_string());
+
+            if is_statement_timeout(&e) {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(GenericMessage {
+                        detail: "Timed out retrieving studies".to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "Error retrieving studies".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// Stream live activity for a study as server-sent events
+#[utoipa::path(
+    get,
+    path = (format!("{}/study/{{id}}/events", Config::new().api_prefix)),
+    params(
+        ("id" = String, Path, description = "Study database id")
+    ),
+    tag = "Studies",
+    responses(
+        (status = 200, description = "Stream of study activity", content_type = "text/event-stream", body = String),
+        (status = 404, description = "Study not found", body = GenericMessage),
+    )
+)]
+pub async fn study_events(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Response {
+    tracing::debug!("Subscribing to study {id} activity");
+    let db_pool = state.db_state.pool.clone();
+    let valkey_pool = &state.valkey_state.pool;
+
+    // Subscribe before the lookup so nothing published in between is missed
+    let receiver = state.activity.subscribe();
+
+    match get_study_service(&db_pool, valkey_pool, &id, false).await {
+        Ok(Some(_)) => Sse::new(study_activity_stream(receiver, id))
+            .keep_alive(KeepAlive::new().interval(Duration::from_secs(15)))
+            .into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(GenericMessage {
+                detail: format!("No study with the id {id} found"),
+            }),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Error retrieving study: {}", e.to_string());
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(GenericMessage {
-                    detail: "Error retrieving studies".to_string(),
+                    detail: "Error retrieving study".to_string(),
                 }),
             )
                 .into_response()
@@ -218,7 +460,84 @@ pub async fn get_studies(State(state): State<Arc<AppState>>) -> Response {
     }
 }
 
-/// Update a study by database id
+/// Lock a study so its data can no longer be edited
+#[utoipa::path(
+    post,
+    path = (format!("{}/study/{{id}}/lock", Config::new().api_prefix)),
+    params(
+        ("id" = String, Path, description = "Study database id")
+    ),
+    tag = "Studies",
+    responses(
+        (status = 200, description = "Study successfully locked", body = Study),
+        (status = 404, description = "Study not found", body = GenericMessage),
+    )
+)]
+pub async fn lock_study(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Response {
+    tracing::debug!("Locking study {id}");
+    set_study_lock(&state, &id, true).await
+}
+
+/// Unlock a study so its data can be edited again
+#[utoipa::path(
+    post,
+    path = (format!("{}/study/{{id}}/unlock", Config::new().api_prefix)),
+    params(
+        ("id" = String, Path, description = "Study database id")
+    ),
+    tag = "Studies",
+    responses(
+        (status = 200, description = "Study successfully unlocked", body = Study),
+        (status = 404, description = "Study not found", body = GenericMessage),
+    )
+)]
+pub async fn unlock_study(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Response {
+    tracing::debug!("Unlocking study {id}");
+    set_study_lock(&state, &id, false).await
+}
+
+async fn set_study_lock(state: &AppState, id: &str, locked: bool) -> Response {
+    let db_pool = state.db_state.pool.clone();
+    let valkey_pool = &state.valkey_state.pool;
+
+    match set_study_lock_service(&db_pool, valkey_pool, id, locked).await {
+        Ok(study) => {
+            tracing::debug!("Successfully set study {id} lock to {locked}");
+            let kind = if locked {
+                StudyActivityKind::StudyLocked
+            } else {
+                StudyActivityKind::StudyUnlocked
+            };
+            state
+                .activity
+                .publish(StudyActivity::new(id, kind, json!(study)));
+            (StatusCode::OK, Json(study)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Error setting study lock: {}", e.to_string());
+
+            if e.to_string().contains("No study with the id") {
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "Error setting study lock".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// Update a study by database id. Deprecated in favour of `PUT /study/{id}`
 #[utoipa::path(
     put,
     path = (format!("{}/study", Config::new().api_prefix)),
@@ -226,35 +545,108 @@ pub async fn get_studies(State(state): State<Arc<AppState>>) -> Response {
     tag = "Studies",
     responses((status = 200, description = "Study added successfully", body = Organization)),
     responses((status = 400, body = GenericMessage)),
+    responses((status = 423, description = "Study is locked", body = GenericMessage)),
 )]
 pub async fn update_study(
     State(state): State<Arc<AppState>>,
-    Json(study_update): Json<StudyUpdate>,
+    StrictJson(study_update): StrictJson<StudyUpdate>,
+) -> Response {
+    update_study_response(&state, &study_update).await
+}
+
+/// Update a study, taking the database id from the path
+#[utoipa::path(
+    put,
+    path = (format!("{}/study/{{id}}", Config::new().api_prefix)),
+    params(
+        ("id" = String, Path, description = "Study database id")
+    ),
+    request_body = StudyUpdate,
+    tag = "Studies",
+    responses(
+        (status = 200, description = "Study updated successfully", body = Study),
+        (status = 400, description = "Invalid study or body id does not match the path", body = GenericMessage),
+        (status = 423, description = "Study is locked", body = GenericMessage),
+    )
+)]
+pub async fn update_study_by_id(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    StrictJson(JsonFields(body, _)): StrictJson<JsonFields<StudyUpdate>>,
 ) -> Response {
+    let study_update =
+        match with_path_id(body, &id).and_then(|b| Ok(serde_json::from_value::<StudyUpdate>(b)?)) {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::debug!("Invalid update for study {id}: {}", e.to_string());
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response();
+            }
+        };
+
+    update_study_response(&state, &study_update).await
+}
+
+async fn update_study_response(state: &AppState, study_update: &StudyUpdate) -> Response {
     tracing::debug!("Updating study");
+    if let Err(e) = validate_pattern(
+        "study_id",
+        state.config.study_id_pattern.as_ref(),
+        &study_update.study_id,
+    ) {
+        tracing::debug!("Rejecting study_id: {}", e.to_string());
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(GenericMessage {
+                detail: e.to_string(),
+            }),
+        )
+            .into_response();
+    }
     let db_pool = state.db_state.pool.clone();
     let valkey_pool = &state.valkey_state.pool;
 
-    match update_study_service(&db_pool, valkey_pool, &study_update).await {
+    match update_study_service(&db_pool, valkey_pool, study_update).await {
         Ok(o) => {
             tracing::debug!("Successfully updated study");
+            dispatch_event(
+                db_pool.clone(),
+                state.webhook_client.clone(),
+                WebhookEvent::StudyUpdated,
+                &o,
+            );
+            state.activity.publish(StudyActivity::new(
+                &o.id,
+                StudyActivityKind::StudyUpdated,
+                json!(o),
+            ));
             (StatusCode::OK, Json(o)).into_response()
         }
         Err(e) => {
             tracing::error!("Error updating study: {}", e.to_string());
 
-            if e.to_string().contains("violates unique constraint") {
+            if e.to_string().contains("already exists") {
                 (
                     StatusCode::BAD_REQUEST,
                     Json(GenericMessage {
-                        detail: format!(
-                            "An study with the study id {} already exists",
-                            &study_update.study_id
-                        ),
+                        detail: e.to_string(),
                     }),
                 )
                     .into_response()
-            } else if e.to_string().contains("No organization found") {
+            } else if e.to_string().contains("Invalid study") {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else if e.to_string().contains("No organization") {
                 (
                     StatusCode::BAD_REQUEST,
                     Json(GenericMessage {
@@ -273,6 +665,14 @@ pub async fn update_study(
                     }),
                 )
                     .into_response()
+            } else if e.to_string().contains("is locked") {
+                (
+                    StatusCode::LOCKED,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
             } else {
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
@@ -285,3 +685,190 @@ pub async fn update_study(
         }
     }
 }
+
+/// Partially update a study by database id
+#[utoipa::path(
+    patch,
+    path = (format!("{}/study/{{id}}", Config::new().api_prefix)),
+    params(
+        ("id" = String, Path, description = "Study database id")
+    ),
+    request_body = StudyPatch,
+    tag = "Studies",
+    responses(
+        (status = 200, description = "Study updated successfully", body = Study),
+        (status = 400, description = "Invalid study", body = GenericMessage),
+        (status = 404, description = "Study not found", body = GenericMessage),
+        (status = 423, description = "Study is locked", body = GenericMessage),
+    )
+)]
+pub async fn patch_study(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    StrictJson(study_patch): StrictJson<StudyPatch>,
+) -> Response {
+    tracing::debug!("Patching study {id}");
+    if let Some(study_id) = &study_patch.study_id {
+        if let Err(e) =
+            validate_pattern("study_id", state.config.study_id_pattern.as_ref(), study_id)
+        {
+            tracing::debug!("Rejecting study_id: {}", e.to_string());
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(GenericMessage {
+                    detail: e.to_string(),
+                }),
+            )
+                .into_response();
+        }
+    }
+    let db_pool = state.db_state.pool.clone();
+    let valkey_pool = &state.valkey_state.pool;
+
+    match patch_study_service(&db_pool, valkey_pool, &id, &study_patch).await {
+        Ok(s) => {
+            tracing::debug!("Successfully patched study {id}");
+            dispatch_event(
+                db_pool.clone(),
+                state.webhook_client.clone(),
+                WebhookEvent::StudyUpdated,
+                &s,
+            );
+            state.activity.publish(StudyActivity::new(
+                &s.id,
+                StudyActivityKind::StudyUpdated,
+                json!(s),
+            ));
+            (StatusCode::OK, Json(s)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Error patching study {id}: {}", e.to_string());
+
+            if e.to_string().contains("already exists") {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else if e.to_string().contains("Invalid study") {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else if e.to_string().contains("No study with the id") {
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else if e.to_string().contains("is locked") {
+                (
+                    StatusCode::LOCKED,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "Error updating study".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// Amend a study's protocol, bumping its protocol version and recording the note
+#[utoipa::path(
+    post,
+    path = (format!("{}/study/{{id}}/amend", Config::new().api_prefix)),
+    params(
+        ("id" = String, Path, description = "Study database id")
+    ),
+    request_body = StudyAmend,
+    tag = "Studies",
+    responses(
+        (status = 200, description = "Study amended successfully", body = Study),
+        (status = 400, description = "Invalid amendment", body = GenericMessage),
+        (status = 404, description = "Study not found", body = GenericMessage),
+        (status = 423, description = "Study is locked", body = GenericMessage),
+    )
+)]
+pub async fn amend_study(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    StrictJson(study_amend): StrictJson<StudyAmend>,
+) -> Response {
+    tracing::debug!("Amending study {id}");
+    let db_pool = state.db_state.pool.clone();
+    let valkey_pool = &state.valkey_state.pool;
+
+    match amend_study_service(&db_pool, valkey_pool, &id, &study_amend).await {
+        Ok(s) => {
+            tracing::debug!(
+                "Successfully amended study {id} to protocol version {}",
+                s.protocol_version
+            );
+            dispatch_event(
+                db_pool.clone(),
+                state.webhook_client.clone(),
+                WebhookEvent::StudyUpdated,
+                &s,
+            );
+            state.activity.publish(StudyActivity::new(
+                &s.id,
+                StudyActivityKind::StudyUpdated,
+                json!(s),
+            ));
+            (StatusCode::OK, Json(s)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Error amending study {id}: {}", e.to_string());
+
+            if e.to_string().contains("Invalid amendment") {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else if e.to_string().contains("No study with the id") {
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else if e.to_string().contains("is locked") {
+                (
+                    StatusCode::LOCKED,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "Error amending study".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
@@ -1,22 +1,40 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header, StatusCode, Uri},
     response::{IntoResponse, Response},
     routing::{delete, get, post, put},
     Json, Router,
 };
+use serde_json::json;
 
 use crate::{
+    client_ip::ClientIp,
     config::Config,
+    db::is_statement_timeout,
+    models::activity::{StudyActivity, StudyActivityKind},
+    models::created_range::CreatedRange,
     models::messages::GenericMessage,
-    models::user::{UserCreate, UserStudy, UserUpdate},
+    models::pagination::Pagination,
+    models::projection::Projection,
+    models::response::Count,
+    models::sort::Sort,
+    models::user::{
+        UserCreate, UserInclude, UserStudiesRemoved, UserStudiesTransfer, UserStudiesTransferred,
+        UserStudy, UserUpdate,
+    },
+    routes::fallback::missing_on_delete,
     services::user_services::{
-        add_user_to_study_service, create_user_service, delete_user_service, get_user_service,
-        get_users_service, remove_user_from_study_service, update_user_service,
+        add_user_to_study_service, count_users_service, create_user_service, delete_user_service,
+        get_user_service, get_user_studies_page_service, get_users_service,
+        remove_user_from_all_studies_service, remove_user_from_study_service,
+        set_user_active_service, transfer_user_studies_service, update_user_service,
     },
     state::AppState,
+    strict_json::{JsonFields, StrictJson},
+    tls::{require_tls_for_sensitive, OverTls},
+    utils::{validate_pattern, with_path_id},
 };
 
 pub fn user_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<AppState>> {
@@ -30,10 +48,30 @@ pub fn user_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<AppState
         .with_state(state.clone())
         .route(&prefix, get(get_users))
         .with_state(state.clone())
+        .route(&format!("{prefix}/count"), get(get_user_count))
+        .with_state(state.clone())
         // TODO: I want to make this a patch but need to figure out how to diferentiate between
         // default None and user set None in serde.
         .route(&prefix, put(update_user))
         .with_state(state.clone())
+        .route(&format!("{prefix}/:id"), put(update_user_by_id))
+        .with_state(state.clone())
+        .route(&format!("{prefix}/:id/studies"), get(get_user_studies))
+        .with_state(state.clone())
+        .route(
+            &format!("{prefix}/:id/studies"),
+            delete(user_remove_all_studies),
+        )
+        .with_state(state.clone())
+        .route(
+            &format!("{prefix}/:id/transfer-studies"),
+            post(user_transfer_studies),
+        )
+        .with_state(state.clone())
+        .route(&format!("{prefix}/:id/activate"), post(activate_user))
+        .with_state(state.clone())
+        .route(&format!("{prefix}/:id/deactivate"), post(deactivate_user))
+        .with_state(state.clone())
         .route(&format!("{prefix}/study"), post(user_add_study))
         .with_state(state.clone())
         .route(
@@ -56,7 +94,7 @@ pub fn user_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<AppState
 )]
 pub async fn user_add_study(
     State(state): State<Arc<AppState>>,
-    Json(user_study): Json<UserStudy>,
+    StrictJson(user_study): StrictJson<UserStudy>,
 ) -> Response {
     tracing::debug!(
         "Adding user {} to study {}",
@@ -80,12 +118,19 @@ pub async fn user_add_study(
                 &user_study.user_id,
                 &user_study.study_id
             );
+            state.activity.publish(StudyActivity::new(
+                &user_study.study_id,
+                StudyActivityKind::UserAdded,
+                json!({ "user_id": user_study.user_id }),
+            ));
             (StatusCode::OK, Json(user)).into_response()
         }
         Err(e) => {
             tracing::error!("Error adding user to study: {}", e.to_string());
 
-            if e.to_string().contains("violates unique constraint") {
+            if e.to_string().contains("has already been added")
+                || e.to_string().contains("violates unique constraint")
+            {
                 (
                     StatusCode::BAD_REQUEST,
                     Json(GenericMessage {
@@ -136,18 +181,55 @@ pub async fn user_add_study(
     tag = "Users",
     responses(
         (status = 201, description = "User added successfully", body = User),
-        (status = 400, body = GenericMessage)
+        (status = 400, body = GenericMessage),
+        (status = 403, description = "HTTPS is required to send a password", body = GenericMessage),
     )
 )]
 pub async fn create_user(
     State(state): State<Arc<AppState>>,
-    Json(new_user): Json<UserCreate>,
+    client_ip: ClientIp,
+    over_tls: OverTls,
+    StrictJson(new_user): StrictJson<UserCreate>,
 ) -> Response {
-    tracing::debug!("Creating new user");
+    tracing::debug!("Creating new user for {client_ip}");
+    if let Err(e) = require_tls_for_sensitive(&state.config, over_tls, "Setting a password") {
+        tracing::debug!(
+            "Rejecting user creation from {client_ip}: {}",
+            e.to_string()
+        );
+        return (
+            StatusCode::FORBIDDEN,
+            Json(GenericMessage {
+                detail: e.to_string(),
+            }),
+        )
+            .into_response();
+    }
+    if let Err(e) = validate_pattern(
+        "user_name",
+        state.config.user_name_pattern.as_ref(),
+        &new_user.user_name,
+    ) {
+        tracing::debug!("Rejecting user_name: {}", e.to_string());
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(GenericMessage {
+                detail: e.to_string(),
+            }),
+        )
+            .into_response();
+    }
     let db_pool = state.db_state.pool.clone();
     let valkey_pool = &state.valkey_state.pool;
 
-    match create_user_service(&db_pool, valkey_pool, &new_user).await {
+    match create_user_service(
+        &db_pool,
+        valkey_pool,
+        &state.password_hash_permits,
+        &new_user,
+    )
+    .await
+    {
         Ok(user) => {
             tracing::debug!("User successfully created");
             (StatusCode::CREATED, Json(user)).into_response()
@@ -166,7 +248,15 @@ pub async fn create_user(
                     }),
                 )
                     .into_response()
-            } else if e.to_string().contains("No organization found") {
+            } else if e.to_string().contains("Invalid phone number") {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else if e.to_string().contains("No organization") {
                 (
                     StatusCode::BAD_REQUEST,
                     Json(GenericMessage {
@@ -214,13 +304,7 @@ pub async fn delete_user(State(state): State<Arc<AppState>>, Path(id): Path<Stri
             tracing::error!("Error deleting user: {}", e.to_string());
 
             if e.to_string().contains("No user with the id") {
-                (
-                    StatusCode::NOT_FOUND,
-                    Json(GenericMessage {
-                        detail: e.to_string(),
-                    }),
-                )
-                    .into_response()
+                missing_on_delete(&state.config, e.to_string())
             } else {
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
@@ -238,22 +322,41 @@ pub async fn delete_user(State(state): State<Arc<AppState>>, Path(id): Path<Stri
 #[utoipa::path(
     get,
     path = (format!("{}/user/{{id}}", Config::new().api_prefix)),
+    params(UserInclude, Projection),
     tag = "Users",
     responses(
         (status = 200, description = "User information", body = User),
+        (status = 400, description = "Unknown field requested", body = GenericMessage),
         (status = 404, description = "User not found", body = GenericMessage)
     )
 )]
-pub async fn get_user(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Response {
+pub async fn get_user(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(include): Query<UserInclude>,
+    Query(projection): Query<Projection>,
+) -> Response {
     tracing::debug!("Getting user {id}");
     let db_pool = state.db_state.pool.clone();
     let valkey_pool = &state.valkey_state.pool;
 
     match get_user_service(&db_pool, valkey_pool, &id, false).await {
         Ok(user) => {
-            if let Some(u) = user {
+            if let Some(mut u) = user {
                 tracing::debug!("User {id} successfully retrieved");
-                (StatusCode::OK, Json(u)).into_response()
+                if !include.studies() {
+                    u.studies = None;
+                }
+                match projection.apply(&u) {
+                    Ok(v) => (StatusCode::OK, Json(v)).into_response(),
+                    Err(e) => (
+                        StatusCode::BAD_REQUEST,
+                        Json(GenericMessage {
+                            detail: e.to_string(),
+                        }),
+                    )
+                        .into_response(),
+                }
             } else {
                 tracing::debug!("User {id} not found");
                 (
@@ -278,34 +381,248 @@ pub async fn get_user(State(state): State<Arc<AppState>>, Path(id): Path<String>
     }
 }
 
+/// Get the studies a user belongs to
+#[utoipa::path(
+    get,
+    path = (format!("{}/user/{{id}}/studies", Config::new().api_prefix)),
+    params(
+        ("id" = String, Path, description = "User database id"),
+        Pagination,
+    ),
+    tag = "Users",
+    responses(
+        (status = 200, description = "The user's studies", body = StudyList),
+        (status = 400, description = "Invalid pagination", body = GenericMessage),
+        (status = 404, description = "User not found", body = GenericMessage),
+    )
+)]
+pub async fn get_user_studies(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(mut pagination): Query<Pagination>,
+    uri: Uri,
+) -> Response {
+    tracing::debug!("Getting studies for user {id}");
+    if let Err(e) = pagination.clamp(&state.config) {
+        tracing::debug!("Invalid pagination: {}", e.to_string());
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(GenericMessage {
+                detail: e.to_string(),
+            }),
+        )
+            .into_response();
+    }
+    let db_pool = state.db_state.pool.clone();
+    let valkey_pool = &state.valkey_state.pool;
+
+    match get_user_studies_page_service(&db_pool, valkey_pool, &id, &pagination).await {
+        Ok(s) => {
+            tracing::debug!("Successfully retrieved studies for user {id}");
+            let link = s.link_header(&uri);
+            (StatusCode::OK, [(header::LINK, link)], Json(s)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Error retrieving user studies: {}", e.to_string());
+
+            if e.to_string().contains("No user with the id") {
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "Error retrieving user studies".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// Count all users
+#[utoipa::path(
+    get,
+    path = (format!("{}/user/count", Config::new().api_prefix)),
+    tag = "Users",
+    responses(
+        (status = 200, description = "Number of users", body = Count),
+        (status = 503, description = "Query timed out", body = GenericMessage),
+    )
+)]
+pub async fn get_user_count(State(state): State<Arc<AppState>>) -> Response {
+    tracing::debug!("Counting users");
+    let db_pool = state.db_state.read_pool.clone();
+
+    match count_users_service(&db_pool, &CreatedRange::default()).await {
+        Ok(count) => (StatusCode::OK, Json(Count { count })).into_response(),
+        Err(e) => {
+            tracing::error!("Error counting users: {}", e.to_string());
+
+            if is_statement_timeout(&e) {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(GenericMessage {
+                        detail: "Timed out counting users".to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "Error counting users".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
 /// Get all users
 #[utoipa::path(
     get,
     path = (format!("{}/user", Config::new().api_prefix)),
+    params(Pagination, Sort, CreatedRange),
     tag = "Users",
     responses(
-        (status = 200, description = "All users information", body = [User]),
+        (status = 200, description = "All users information", body = UserList),
+        (status = 400, description = "Invalid pagination, sort or created range", body = GenericMessage),
+        (status = 503, description = "Query timed out", body = GenericMessage),
     )
 )]
-pub async fn get_users(State(state): State<Arc<AppState>>) -> Response {
+pub async fn get_users(
+    State(state): State<Arc<AppState>>,
+    Query(mut pagination): Query<Pagination>,
+    Query(sort): Query<Sort>,
+    Query(created): Query<CreatedRange>,
+    uri: Uri,
+) -> Response {
     tracing::debug!("Getting all users");
-    let db_pool = state.db_state.pool.clone();
-    let valkey_pool = &state.valkey_state.pool;
+    if let Err(e) = pagination
+        .clamp(&state.config)
+        .and_then(|_| sort.validate())
+        .and_then(|_| created.validate())
+    {
+        tracing::debug!(
+            "Invalid pagination, sort or created range: {}",
+            e.to_string()
+        );
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(GenericMessage {
+                detail: e.to_string(),
+            }),
+        )
+            .into_response();
+    }
+    let db_pool = state.db_state.read_pool.clone();
 
-    match get_users_service(&db_pool, valkey_pool).await {
+    match get_users_service(&db_pool, &pagination, &sort, &created).await {
         Ok(u) => {
             tracing::debug!("Successfully retrieved all users");
-            (StatusCode::OK, Json(u)).into_response()
+            let link = u.link_header(&uri);
+            (StatusCode::OK, [(header::LINK, link)], Json(u)).into_response()
         }
         Err(e) => {
             tracing::error!("Error retrieving all users: {}", e.to_string());
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(GenericMessage {
-                    detail: "Error retrieving users".to_string(),
-                }),
-            )
-                .into_response()
+
+            if is_statement_timeout(&e) {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(GenericMessage {
+                        detail: "Timed out retrieving users".to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "Error retrieving users".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// Activate a user
+#[utoipa::path(
+    post,
+    path = (format!("{}/user/{{id}}/activate", Config::new().api_prefix)),
+    params(
+        ("id" = String, Path, description = "User database id")
+    ),
+    tag = "Users",
+    responses(
+        (status = 200, description = "User successfully activated", body = User),
+        (status = 404, description = "User not found", body = GenericMessage),
+    )
+)]
+pub async fn activate_user(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Response {
+    tracing::debug!("Activating user {id}");
+    set_user_active(&state, &id, true).await
+}
+
+/// Deactivate a user
+#[utoipa::path(
+    post,
+    path = (format!("{}/user/{{id}}/deactivate", Config::new().api_prefix)),
+    params(
+        ("id" = String, Path, description = "User database id")
+    ),
+    tag = "Users",
+    responses(
+        (status = 200, description = "User successfully deactivated", body = User),
+        (status = 404, description = "User not found", body = GenericMessage),
+    )
+)]
+pub async fn deactivate_user(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Response {
+    tracing::debug!("Deactivating user {id}");
+    set_user_active(&state, &id, false).await
+}
+
+async fn set_user_active(state: &AppState, id: &str, active: bool) -> Response {
+    let db_pool = state.db_state.pool.clone();
+    let valkey_pool = &state.valkey_state.pool;
+
+    match set_user_active_service(&db_pool, valkey_pool, id, active).await {
+        Ok(user) => {
+            tracing::debug!("Successfully set user {id} active to {active}");
+            (StatusCode::OK, Json(user)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Error setting user active: {}", e.to_string());
+
+            if e.to_string().contains("No user with the id") {
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "Error setting user active".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
         }
     }
 }
@@ -335,6 +652,11 @@ pub async fn user_remove_study(
     match remove_user_from_study_service(&db_pool, valkey_pool, &user_id, &study_id).await {
         Ok(o) => {
             tracing::debug!("Successfully removed user {user_id} from study {study_id}");
+            state.activity.publish(StudyActivity::new(
+                &study_id,
+                StudyActivityKind::UserRemoved,
+                json!({ "user_id": user_id }),
+            ));
             (StatusCode::NO_CONTENT, Json(o)).into_response()
         }
         Err(e) => {
@@ -361,7 +683,156 @@ pub async fn user_remove_study(
     }
 }
 
-/// Update a user by database id
+/// Move every study a user is part of to another user in the same organization
+#[utoipa::path(
+    post,
+    path = (format!("{}/user/{{id}}/transfer-studies", Config::new().api_prefix)),
+    params(
+        ("id" = String, Path, description = "Database id of the user to move studies from"),
+    ),
+    request_body = UserStudiesTransfer,
+    tag = "Users",
+    responses(
+        (status = 200, description = "Studies moved to the target user", body = UserStudiesTransferred),
+        (status = 400, description = "Users are not in the organization of every study", body = GenericMessage),
+        (status = 404, description = "User not found", body = GenericMessage),
+    )
+)]
+pub async fn user_transfer_studies(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    StrictJson(transfer): StrictJson<UserStudiesTransfer>,
+) -> Response {
+    let target_id = &transfer.target_user_id;
+    tracing::debug!("Transferring studies from user {id} to user {target_id}");
+    let db_pool = state.db_state.pool.clone();
+    let valkey_pool = &state.valkey_state.pool;
+
+    match transfer_user_studies_service(&db_pool, valkey_pool, &id, target_id).await {
+        Ok(study_ids) => {
+            tracing::debug!(
+                "Successfully transferred {} studies from user {id} to user {target_id}",
+                study_ids.len()
+            );
+            for study_id in study_ids.iter() {
+                state.activity.publish(StudyActivity::new(
+                    study_id,
+                    StudyActivityKind::UserRemoved,
+                    json!({ "user_id": id }),
+                ));
+                state.activity.publish(StudyActivity::new(
+                    study_id,
+                    StudyActivityKind::UserAdded,
+                    json!({ "user_id": target_id }),
+                ));
+            }
+            (
+                StatusCode::OK,
+                Json(UserStudiesTransferred {
+                    transferred: study_ids.len(),
+                }),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            tracing::error!("Error transferring user studies: {}", e.to_string());
+
+            if e.to_string().contains("No user with the id") {
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else if e.to_string().contains("Invalid transfer") {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "Error transferring user studies".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// Remove a user from every study they are part of
+#[utoipa::path(
+    delete,
+    path = (format!("{}/user/{{id}}/studies", Config::new().api_prefix)),
+    params(
+        ("id" = String, Path, description = "User database id"),
+    ),
+    tag = "Users",
+    responses(
+        (status = 200, description = "User removed from all studies", body = UserStudiesRemoved),
+        (status = 404, description = "User not found", body = GenericMessage),
+    )
+)]
+pub async fn user_remove_all_studies(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Response {
+    tracing::debug!("Removing user {id} from all studies");
+    let db_pool = state.db_state.pool.clone();
+    let valkey_pool = &state.valkey_state.pool;
+
+    match remove_user_from_all_studies_service(&db_pool, valkey_pool, &id).await {
+        Ok(study_ids) => {
+            tracing::debug!(
+                "Successfully removed user {id} from {} studies",
+                study_ids.len()
+            );
+            for study_id in study_ids.iter() {
+                state.activity.publish(StudyActivity::new(
+                    study_id,
+                    StudyActivityKind::UserRemoved,
+                    json!({ "user_id": id }),
+                ));
+            }
+            (
+                StatusCode::OK,
+                Json(UserStudiesRemoved {
+                    removed: study_ids.len(),
+                }),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            tracing::error!("Error removing user from all studies: {}", e.to_string());
+
+            if e.to_string().contains("No user with the id") {
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "Error removing user from studies".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// Update a user by database id. Deprecated in favour of `PUT /user/{id}`
 #[utoipa::path(
     put,
     path = (format!("{}/user", Config::new().api_prefix)),
@@ -369,16 +840,98 @@ pub async fn user_remove_study(
     tag = "Users",
     responses((status = 200, description = "User added successfully", body = Organization)),
     responses((status = 400, body = GenericMessage)),
+    responses((status = 403, description = "HTTPS is required to change a password", body = GenericMessage)),
 )]
 pub async fn update_user(
     State(state): State<Arc<AppState>>,
-    Json(user_update): Json<UserUpdate>,
+    over_tls: OverTls,
+    StrictJson(user_update): StrictJson<UserUpdate>,
+) -> Response {
+    update_user_response(&state, over_tls, &user_update).await
+}
+
+/// Update a user, taking the database id from the path
+#[utoipa::path(
+    put,
+    path = (format!("{}/user/{{id}}", Config::new().api_prefix)),
+    params(
+        ("id" = String, Path, description = "User database id")
+    ),
+    request_body = UserUpdate,
+    tag = "Users",
+    responses(
+        (status = 200, description = "User updated successfully", body = User),
+        (status = 400, description = "Invalid user or body id does not match the path", body = GenericMessage),
+        (status = 403, description = "HTTPS is required to change a password", body = GenericMessage),
+    )
+)]
+pub async fn update_user_by_id(
+    State(state): State<Arc<AppState>>,
+    over_tls: OverTls,
+    Path(id): Path<String>,
+    StrictJson(JsonFields(body, _)): StrictJson<JsonFields<UserUpdate>>,
+) -> Response {
+    let user_update =
+        match with_path_id(body, &id).and_then(|b| Ok(serde_json::from_value::<UserUpdate>(b)?)) {
+            Ok(u) => u,
+            Err(e) => {
+                tracing::debug!("Invalid update for user {id}: {}", e.to_string());
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response();
+            }
+        };
+
+    update_user_response(&state, over_tls, &user_update).await
+}
+
+async fn update_user_response(
+    state: &AppState,
+    over_tls: OverTls,
+    user_update: &UserUpdate,
 ) -> Response {
     tracing::debug!("Updating user");
+    if user_update.password.is_some() {
+        if let Err(e) = require_tls_for_sensitive(&state.config, over_tls, "Changing a password") {
+            tracing::debug!("Rejecting password change: {}", e.to_string());
+            return (
+                StatusCode::FORBIDDEN,
+                Json(GenericMessage {
+                    detail: e.to_string(),
+                }),
+            )
+                .into_response();
+        }
+    }
+    if let Err(e) = validate_pattern(
+        "user_name",
+        state.config.user_name_pattern.as_ref(),
+        &user_update.user_name,
+    ) {
+        tracing::debug!("Rejecting user_name: {}", e.to_string());
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(GenericMessage {
+                detail: e.to_string(),
+            }),
+        )
+            .into_response();
+    }
     let db_pool = state.db_state.pool.clone();
     let valkey_pool = &state.valkey_state.pool;
 
-    match update_user_service(&db_pool, valkey_pool, &user_update).await {
+    match update_user_service(
+        &db_pool,
+        valkey_pool,
+        &state.password_hash_permits,
+        user_update,
+    )
+    .await
+    {
         Ok(o) => {
             tracing::debug!("Succesfully updated user");
             (StatusCode::OK, Json(o)).into_response()
@@ -397,7 +950,15 @@ pub async fn update_user(
                     }),
                 )
                     .into_response()
-            } else if e.to_string().contains("No organization found") {
+            } else if e.to_string().contains("Invalid phone number") {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else if e.to_string().contains("No organization") {
                 (
                     StatusCode::BAD_REQUEST,
                     Json(GenericMessage {
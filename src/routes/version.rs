@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use utoipa::ToSchema;
+
+use crate::{config::Config, models::messages::GenericMessage, state::AppState};
+
+/// What is deployed, for support and debugging
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct VersionInfo {
+    /// The running server's version
+    pub app_version: String,
+
+    /// Commit the server was built from, `unknown` when built outside a git checkout
+    pub git_sha: String,
+
+    /// When the server was built, as RFC 3339
+    pub build_time: String,
+
+    /// Version of the latest migration applied to the database
+    pub db_schema_version: String,
+}
+
+pub fn version_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<AppState>> {
+    let prefix = format!("{}/version", config.api_prefix);
+    Router::new()
+        .route(&prefix, get(get_version))
+        .with_state(state.clone())
+}
+
+/// Get the server build and database schema versions
+#[utoipa::path(
+    get,
+    path = (format!("{}/version", Config::new().api_prefix)),
+    tag = "Config",
+    responses(
+        (status = 200, description = "Server and database schema versions", body = VersionInfo),
+        (status = 500, description = "Schema version could not be read", body = GenericMessage),
+    )
+)]
+pub async fn get_version(State(state): State<Arc<AppState>>) -> Response {
+    tracing::debug!("Getting version");
+
+    match db_schema_version(&state.db_state.read_pool).await {
+        Ok(db_schema_version) => (
+            StatusCode::OK,
+            Json(VersionInfo {
+                app_version: env!("CARGO_PKG_VERSION").to_string(),
+                git_sha: env!("OPEN_EDC_GIT_SHA").to_string(),
+                build_time: env!("OPEN_EDC_BUILD_TIME").to_string(),
+                db_schema_version,
+            }),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Error reading schema version: {}", e.to_string());
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(GenericMessage {
+                    detail: "Error reading database schema version".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Latest successfully applied migration, `none` before any have run
+async fn db_schema_version(db_pool: &PgPool) -> Result<String, sqlx::Error> {
+    let version: Option<i64> =
+        sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success = true")
+            .fetch_one(db_pool)
+            .await?;
+
+    Ok(version.map_or_else(|| "none".to_string(), |v| v.to_string()))
+}
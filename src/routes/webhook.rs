@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{delete, post},
+    Json, Router,
+};
+
+use crate::{
+    admin_auth::require_admin,
+    config::Config,
+    models::messages::GenericMessage,
+    models::webhook::WebhookCreate,
+    routes::fallback::missing_on_delete,
+    services::webhook_services::{create_webhook_service, delete_webhook_service},
+    state::AppState,
+    strict_json::StrictJson,
+};
+
+pub fn webhook_routes(state: Arc<AppState>, config: &Config) -> Router<Arc<AppState>> {
+    let prefix = format!("{}/webhook", config.api_prefix);
+    Router::new()
+        .route(&prefix, post(create_webhook))
+        .with_state(state.clone())
+        .route(&format!("{prefix}/:id"), delete(delete_webhook))
+        .with_state(state.clone())
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin))
+}
+
+/// Register a webhook
+#[utoipa::path(
+    post,
+    path = (format!("{}/webhook", Config::new().api_prefix)),
+    request_body = WebhookCreate,
+    tag = "Webhooks",
+    responses(
+        (status = 201, description = "Webhook registered successfully", body = Webhook),
+        (status = 400, description = "Invalid webhook", body = GenericMessage),
+        (status = 401, description = "Missing or invalid admin API key", body = GenericMessage),
+        (status = 403, description = "ADMIN_API_KEY is not set, or HTTPS is required", body = GenericMessage),
+    )
+)]
+pub async fn create_webhook(
+    State(state): State<Arc<AppState>>,
+    StrictJson(new_webhook): StrictJson<WebhookCreate>,
+) -> Response {
+    tracing::debug!("Creating webhook");
+    let db_pool = state.db_state.pool.clone();
+
+    match create_webhook_service(&db_pool, &new_webhook).await {
+        Ok(webhook) => {
+            tracing::debug!("Successfully created webhook");
+            (StatusCode::CREATED, Json(webhook)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Error creating webhook: {}", e.to_string());
+
+            if e.to_string().contains("Invalid webhook") {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(GenericMessage {
+                        detail: e.to_string(),
+                    }),
+                )
+                    .into_response()
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "An error occurred while creating webhook".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// Remove a webhook
+#[utoipa::path(
+    delete,
+    path = (format!("{}/webhook/{{id}}", Config::new().api_prefix)),
+    params(
+        ("id" = String, Path, description = "Webhook database id")
+    ),
+    tag = "Webhooks",
+    responses(
+        (status = 204, description = "Webhook successfully deleted"),
+        (status = 401, description = "Missing or invalid admin API key", body = GenericMessage),
+        (status = 403, description = "ADMIN_API_KEY is not set, or HTTPS is required", body = GenericMessage),
+        (status = 404, description = "Webhook not found", body = GenericMessage),
+    )
+)]
+pub async fn delete_webhook(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Response {
+    tracing::debug!("Deleting webhook {id}");
+    let db_pool = state.db_state.pool.clone();
+
+    match delete_webhook_service(&db_pool, &id).await {
+        Ok(w) => {
+            tracing::debug!("Successfully deleted webhook {id}");
+            (StatusCode::NO_CONTENT, Json(w)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Error deleting webhook: {}", e.to_string());
+
+            if e.to_string().contains("No webhook with the id") {
+                missing_on_delete(&state.config, e.to_string())
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(GenericMessage {
+                        detail: "Error deleting webhook".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
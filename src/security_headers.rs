@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::state::AppState;
+
+/// Adds `X-Content-Type-Options`, `X-Frame-Options` and, unless `HSTS_MAX_AGE_SECS` is 0,
+/// `Strict-Transport-Security` to every response. Headers a handler set itself are left alone.
+/// Turned off entirely with `SECURITY_HEADERS=false`, e.g. for local development over HTTP.
+pub async fn security_headers(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    if !state.config.security_headers {
+        return response;
+    }
+
+    let headers = response.headers_mut();
+    headers
+        .entry(header::X_CONTENT_TYPE_OPTIONS)
+        .or_insert(HeaderValue::from_static("nosniff"));
+    headers
+        .entry(header::X_FRAME_OPTIONS)
+        .or_insert(HeaderValue::from_static("DENY"));
+
+    if state.config.hsts_max_age_secs > 0 {
+        let hsts = HeaderValue::from_str(&format!(
+            "max-age={}; includeSubDomains",
+            state.config.hsts_max_age_secs
+        ))
+        .expect("HSTS header is always valid");
+        headers
+            .entry(header::STRICT_TRANSPORT_SECURITY)
+            .or_insert(hsts);
+    }
+
+    response
+}
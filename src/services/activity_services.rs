@@ -0,0 +1,108 @@
+use std::convert::Infallible;
+
+use axum::response::sse::Event;
+use futures_util::{stream, Stream};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::models::activity::StudyActivity;
+
+/// Number of unread events a slow subscriber can fall behind by before it starts missing them
+const ACTIVITY_CHANNEL_CAPACITY: usize = 256;
+
+/// In process fan out of study activity to live subscribers. Publishing never blocks and is a
+/// no-op when nobody is listening.
+#[derive(Clone)]
+pub struct ActivityBus {
+    sender: broadcast::Sender<StudyActivity>,
+}
+
+impl Default for ActivityBus {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(ACTIVITY_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+}
+
+impl ActivityBus {
+    pub fn publish(&self, activity: StudyActivity) {
+        // An error only means there are no subscribers right now
+        let _ = self.sender.send(activity);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StudyActivity> {
+        self.sender.subscribe()
+    }
+}
+
+/// Turns a subscription into SSE events for a single study. The stream ends when the bus is
+/// dropped and is dropped itself when the client disconnects, which unsubscribes it.
+pub fn study_activity_stream(
+    receiver: broadcast::Receiver<StudyActivity>,
+    study_id: String,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    stream::unfold(receiver, move |mut receiver| {
+        let study_id = study_id.clone();
+        async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(activity) if activity.study_id == study_id => {
+                        let event = Event::default()
+                            .event(activity.kind.as_str())
+                            .json_data(&activity)
+                            .unwrap_or_else(|_| Event::default().comment("unserializable event"));
+                        return Some((Ok(event), receiver));
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::warn!("Activity subscriber for {study_id} missed {missed} events");
+                        let event = Event::default().comment(format!("missed {missed} events"));
+                        return Some((Ok(event), receiver));
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use serde_json::json;
+
+    use crate::models::activity::StudyActivityKind;
+
+    #[tokio::test]
+    async fn stream_filters_by_study() {
+        let bus = ActivityBus::default();
+        let stream = study_activity_stream(bus.subscribe(), "study-1".to_string());
+        tokio::pin!(stream);
+
+        bus.publish(StudyActivity::new(
+            "study-2",
+            StudyActivityKind::StudyLocked,
+            json!({}),
+        ));
+        bus.publish(StudyActivity::new(
+            "study-1",
+            StudyActivityKind::StudyUnlocked,
+            json!({}),
+        ));
+        drop(bus);
+
+        assert!(stream.next().await.is_some());
+        assert!(stream.next().await.is_none());
+    }
+
+    #[test]
+    fn publish_without_subscribers() {
+        let bus = ActivityBus::default();
+
+        bus.publish(StudyActivity::new(
+            "study-1",
+            StudyActivityKind::StudyLocked,
+            json!({}),
+        ));
+    }
+}
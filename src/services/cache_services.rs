@@ -1,61 +1,1171 @@
+use std::{
+    collections::BTreeSet,
+    future::Future,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
 use anyhow::Result;
-use bb8::Pool;
+use bb8::{Pool, PooledConnection};
 use bb8_redis::RedisConnectionManager;
-use serde::{de::DeserializeOwned, Serialize};
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::models::cache::CacheStats;
+
+/// Consecutive connection failures before the cache is bypassed
+const FAILURE_THRESHOLD: u32 = 5;
+
+/// How long the cache is bypassed before another connection is attempted
+const COOLDOWN: Duration = Duration::from_secs(30);
+
+static BREAKER: CircuitBreaker = CircuitBreaker::new(FAILURE_THRESHOLD, COOLDOWN);
+
+static COUNTERS: CacheCounters = CacheCounters::new();
+
+static POLICY: OnceLock<CachePolicy> = OnceLock::new();
+
+/// `field:key` of the values being refreshed in the background, so a burst of stale reads only
+/// starts one refresh per value
+static REFRESHING: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// `(field, key)` of cached values that changed while valkey couldn't be reached. They are
+/// deleted as soon as a connection succeeds again so the old values aren't served.
+static PENDING_INVALIDATIONS: Mutex<BTreeSet<(String, String)>> = Mutex::new(BTreeSet::new());
 
 pub trait Cacheable {
+    /// The valkey hash the values of this type are stored under
+    const CACHE_FIELD: &'static str;
+
     fn get_key(&self) -> &str;
-    fn cache_field(&self) -> &str;
+
+    fn cache_field(&self) -> &str {
+        Self::CACHE_FIELD
+    }
+}
+
+/// Stale-while-revalidate windows for cached values. For `fresh` after a value is cached it is
+/// served as is. For `stale` after that it is still served, but the read starts a refresh from
+/// the database in the background. Older values are treated as a miss. A `fresh` of zero turns
+/// this off and cached values are served until they are deleted.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CachePolicy {
+    pub fresh: Duration,
+    pub stale: Duration,
+}
+
+#[derive(Debug, PartialEq)]
+enum Freshness {
+    Fresh,
+    Stale,
+    Expired,
 }
 
+impl CachePolicy {
+    fn freshness(&self, age: Duration) -> Freshness {
+        if self.fresh.is_zero() || age < self.fresh {
+            Freshness::Fresh
+        } else if age < self.fresh + self.stale {
+            Freshness::Stale
+        } else {
+            Freshness::Expired
+        }
+    }
+}
+
+/// Sets the policy every cache read follows. Only the first call has an effect so it should be
+/// made at startup, until then values never go stale.
+pub fn set_cache_policy(policy: CachePolicy) {
+    if POLICY.set(policy).is_err() {
+        tracing::warn!("Cache policy already set, ignoring {policy:?}");
+    }
+}
+
+fn cache_policy() -> CachePolicy {
+    POLICY.get().copied().unwrap_or_default()
+}
+
+/// What is stored in valkey, the value along with when it was cached
+#[derive(Deserialize, Serialize)]
+struct CacheEntry<T> {
+    cached_at: DateTime<Utc>,
+    value: T,
+}
+
+/// Stops trying to reach valkey after repeated connection failures so requests don't pay for a
+/// connection timeout on every cache call. Once the cooldown passes the next call is let through
+/// as a probe; a success closes the breaker and a failure opens it for another cooldown.
+struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    failures: AtomicU32,
+    open_until: Mutex<Option<Instant>>,
+}
+
+impl CircuitBreaker {
+    const fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            failures: AtomicU32::new(0),
+            open_until: Mutex::new(None),
+        }
+    }
+
+    fn is_open(&self, now: Instant) -> bool {
+        match *self.open_until.lock().unwrap() {
+            Some(until) => now < until,
+            None => false,
+        }
+    }
+
+    fn record_success(&self) {
+        self.failures.store(0, Ordering::Relaxed);
+        let mut open_until = self.open_until.lock().unwrap();
+        if open_until.take().is_some() {
+            tracing::info!("Valkey connection recovered, cache re-enabled");
+        }
+    }
+
+    fn record_failure(&self, now: Instant) {
+        let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= self.threshold {
+            tracing::error!(
+                "Valkey unavailable after {failures} attempts, bypassing cache for {:?}",
+                self.cooldown
+            );
+            *self.open_until.lock().unwrap() = Some(now + self.cooldown);
+        }
+    }
+}
+
+/// Hit and miss counts for cache reads since startup or the last reset
+struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CacheCounters {
+    const fn new() -> Self {
+        Self {
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn stats(&self, reset: bool) -> CacheStats {
+        if reset {
+            CacheStats::new(
+                self.hits.swap(0, Ordering::Relaxed),
+                self.misses.swap(0, Ordering::Relaxed),
+            )
+        } else {
+            CacheStats::new(
+                self.hits.load(Ordering::Relaxed),
+                self.misses.load(Ordering::Relaxed),
+            )
+        }
+    }
+}
+
+/// Hit and miss counts for this instance, starting again from zero when `reset` is true
+pub fn cache_stats(reset: bool) -> CacheStats {
+    COUNTERS.stats(reset)
+}
+
+/// Returns false while the cache is being bypassed after repeated connection failures
+pub fn cache_available() -> bool {
+    !BREAKER.is_open(Instant::now())
+}
+
+pub(crate) async fn connection(
+    pool: &Pool<RedisConnectionManager>,
+) -> Option<PooledConnection<'_, RedisConnectionManager>> {
+    if BREAKER.is_open(Instant::now()) {
+        return None;
+    }
+
+    match pool.get().await {
+        Ok(mut conn) => {
+            BREAKER.record_success();
+            flush_pending_invalidations(&mut conn).await;
+            Some(conn)
+        }
+        Err(e) => {
+            tracing::error!("Error connecting to valkey: {}", e.to_string());
+            BREAKER.record_failure(Instant::now());
+            None
+        }
+    }
+}
+
+/// Remembers that the cached `key` in `field` is out of date but couldn't be removed
+fn defer_invalidation(field: &str, key: &str) {
+    PENDING_INVALIDATIONS
+        .lock()
+        .unwrap()
+        .insert((field.to_string(), key.to_string()));
+}
+
+async fn flush_pending_invalidations(conn: &mut PooledConnection<'_, RedisConnectionManager>) {
+    let pending: Vec<(String, String)> = PENDING_INVALIDATIONS
+        .lock()
+        .unwrap()
+        .iter()
+        .cloned()
+        .collect();
+
+    for (field, key) in pending {
+        let deleted: Result<(), _> = redis::cmd("HDEL")
+            .arg(&field)
+            .arg(&key)
+            .query_async(&mut **conn)
+            .await;
+        match deleted {
+            Ok(_) => {
+                tracing::debug!("Invalidated {key} in {field} after the cache came back");
+                PENDING_INVALIDATIONS.lock().unwrap().remove(&(field, key));
+            }
+            Err(e) => {
+                tracing::error!("Error invalidating {key} in {field}: {}", e.to_string());
+                return;
+            }
+        }
+    }
+}
+
+/// Caches `cache_value`. It's called once the change it reflects has been committed, so when
+/// valkey can't be reached the write is skipped and the old value is removed once it's back
+/// rather than failing the request.
 pub async fn add_cached_value<T: Cacheable + Serialize>(
     pool: &Pool<RedisConnectionManager>,
     cache_value: &T,
 ) -> Result<()> {
-    let study_json = serde_json::to_string(cache_value)?;
-    let mut conn = pool.get().await?;
-    redis::cmd("HSET")
-        .arg(cache_value.cache_field())
-        .arg(cache_value.get_key())
+    add_cached_entry(pool, cache_value, Utc::now()).await
+}
+
+async fn add_cached_entry<T: Cacheable + Serialize>(
+    pool: &Pool<RedisConnectionManager>,
+    cache_value: &T,
+    cached_at: DateTime<Utc>,
+) -> Result<()> {
+    let study_json = serde_json::to_string(&CacheEntry {
+        cached_at,
+        value: cache_value,
+    })?;
+    let field = cache_value.cache_field();
+    let key = cache_value.get_key();
+    let Some(mut conn) = connection(pool).await else {
+        tracing::warn!("Cache unavailable, {key} in {field} will be invalidated once it's back");
+        defer_invalidation(field, key);
+        return Ok(());
+    };
+    let cached: Result<(), _> = redis::cmd("HSET")
+        .arg(field)
+        .arg(key)
         .arg(study_json)
         .query_async(&mut *conn)
-        .await?;
+        .await;
+    if let Err(e) = cached {
+        tracing::error!("Error caching {key} in {field}: {}", e.to_string());
+        defer_invalidation(field, key);
+    }
 
     Ok(())
 }
 
-pub async fn delete_cached_value(
+/// Removes a cached value. Like `add_cached_value` it runs after the change is committed, so a
+/// delete valkey can't take is retried once it's back instead of failing the request.
+pub async fn delete_cached_value<T: Cacheable>(
     pool: &Pool<RedisConnectionManager>,
-    cache_field: &str,
     field_id: &str,
 ) -> Result<()> {
-    let mut conn = pool.get().await?;
-    redis::cmd("DEL")
-        .arg(cache_field)
+    let Some(mut conn) = connection(pool).await else {
+        tracing::warn!(
+            "Cache unavailable, {field_id} in {} will be invalidated once it's back",
+            T::CACHE_FIELD
+        );
+        defer_invalidation(T::CACHE_FIELD, field_id);
+        return Ok(());
+    };
+    let deleted: Result<(), _> = redis::cmd("HDEL")
+        .arg(T::CACHE_FIELD)
         .arg(field_id)
         .query_async(&mut *conn)
-        .await?;
+        .await;
+    if let Err(e) = deleted {
+        tracing::error!(
+            "Error deleting {field_id} from {}: {}",
+            T::CACHE_FIELD,
+            e.to_string()
+        );
+        defer_invalidation(T::CACHE_FIELD, field_id);
+    }
 
     Ok(())
 }
 
-pub async fn get_cached_value<T: DeserializeOwned>(
+/// Removes several cached values of one type with a single HDEL. Like `delete_cached_value` a
+/// delete valkey can't take is retried once it's back.
+pub async fn delete_cached_values<T: Cacheable>(
+    pool: &Pool<RedisConnectionManager>,
+    field_ids: &[String],
+) -> Result<()> {
+    if field_ids.is_empty() {
+        return Ok(());
+    }
+
+    let Some(mut conn) = connection(pool).await else {
+        tracing::warn!(
+            "Cache unavailable, {} values in {} will be invalidated once it's back",
+            field_ids.len(),
+            T::CACHE_FIELD
+        );
+        field_ids
+            .iter()
+            .for_each(|id| defer_invalidation(T::CACHE_FIELD, id));
+        return Ok(());
+    };
+    let deleted: Result<(), _> = redis::cmd("HDEL")
+        .arg(T::CACHE_FIELD)
+        .arg(field_ids)
+        .query_async(&mut *conn)
+        .await;
+    if let Err(e) = deleted {
+        tracing::error!(
+            "Error deleting {} values from {}: {}",
+            field_ids.len(),
+            T::CACHE_FIELD,
+            e.to_string()
+        );
+        field_ids
+            .iter()
+            .for_each(|id| defer_invalidation(T::CACHE_FIELD, id));
+    }
+
+    Ok(())
+}
+
+pub async fn get_cached_value<T: Cacheable + DeserializeOwned>(
     pool: &Pool<RedisConnectionManager>,
-    cache_field: &str,
     field_id: &str,
 ) -> Result<Option<T>> {
-    let mut conn = pool.get().await?;
-    let cached_study_str: Option<String> = redis::cmd("HGET")
-        .arg(cache_field)
+    let cached = get_cached_entry::<T>(pool, field_id, cache_policy()).await?;
+
+    Ok(cached.map(|(value, _, _)| value))
+}
+
+/// Like `get_cached_value`, but a stale value also starts `refresh` in the background to load
+/// the current value, which then replaces the cached one. The stale value is returned without
+/// waiting for it.
+pub async fn get_cached_value_or_refresh<T, F, Fut>(
+    pool: &Pool<RedisConnectionManager>,
+    field_id: &str,
+    refresh: F,
+) -> Result<Option<T>>
+where
+    T: Cacheable + Serialize + DeserializeOwned + Send + 'static,
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<Option<T>>> + Send + 'static,
+{
+    get_or_refresh_with_policy(pool, field_id, cache_policy(), refresh).await
+}
+
+async fn get_or_refresh_with_policy<T, F, Fut>(
+    pool: &Pool<RedisConnectionManager>,
+    field_id: &str,
+    policy: CachePolicy,
+    refresh: F,
+) -> Result<Option<T>>
+where
+    T: Cacheable + Serialize + DeserializeOwned + Send + 'static,
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<Option<T>>> + Send + 'static,
+{
+    match get_cached_entry::<T>(pool, field_id, policy).await? {
+        Some((value, Freshness::Stale, stale)) => {
+            spawn_refresh(pool.clone(), field_id.to_string(), stale, refresh);
+            Ok(Some(value))
+        }
+        cached => Ok(cached.map(|(value, _, _)| value)),
+    }
+}
+
+/// Refreshes the cached `field_id` in the background. `stale` is the entry that was read, the
+/// refreshed value only replaces it if nothing else has since, see `replace_cached_entry`.
+fn spawn_refresh<T, F, Fut>(
+    pool: Pool<RedisConnectionManager>,
+    field_id: String,
+    stale: String,
+    refresh: F,
+) where
+    T: Cacheable + Serialize + Send + 'static,
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<Option<T>>> + Send + 'static,
+{
+    let refresh_key = format!("{}:{field_id}", T::CACHE_FIELD);
+    if !REFRESHING.lock().unwrap().insert(refresh_key.clone()) {
+        tracing::debug!("Already refreshing {refresh_key}");
+        return;
+    }
+
+    tokio::spawn(async move {
+        tracing::debug!("Refreshing stale cached value {refresh_key}");
+        let refreshed = match refresh().await {
+            Ok(value) => replace_cached_entry(&pool, &field_id, &stale, value.as_ref()).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = refreshed {
+            tracing::error!(
+                "Error refreshing cached value {refresh_key}: {}",
+                e.to_string()
+            );
+        }
+        REFRESHING.lock().unwrap().remove(&refresh_key);
+    });
+}
+
+/// Script behind `replace_cached_entry`, the check and the write run as one command so no other
+/// write can land between them. An empty replacement removes the entry.
+const REPLACE_IF_UNCHANGED: &str = r#"
+if redis.call('HGET', KEYS[1], ARGV[1]) ~= ARGV[2] then
+    return 0
+end
+if ARGV[3] == '' then
+    redis.call('HDEL', KEYS[1], ARGV[1])
+else
+    redis.call('HSET', KEYS[1], ARGV[1], ARGV[3])
+end
+return 1
+"#;
+
+/// Replaces the cached `field_id` with `value`, or removes it when `value` is `None`, only if
+/// the entry is still `expected`. An update that cached a newer value after the refresh read the
+/// database is kept rather than overwritten by the older snapshot.
+async fn replace_cached_entry<T: Cacheable + Serialize>(
+    pool: &Pool<RedisConnectionManager>,
+    field_id: &str,
+    expected: &str,
+    value: Option<&T>,
+) -> Result<()> {
+    let replacement = match value {
+        Some(value) => serde_json::to_string(&CacheEntry {
+            cached_at: Utc::now(),
+            value,
+        })?,
+        None => String::new(),
+    };
+    let Some(mut conn) = connection(pool).await else {
+        tracing::debug!("Cache unavailable, skipping refresh of {field_id}");
+        return Ok(());
+    };
+    let replaced: i64 = redis::cmd("EVAL")
+        .arg(REPLACE_IF_UNCHANGED)
+        .arg(1)
+        .arg(T::CACHE_FIELD)
         .arg(field_id)
+        .arg(expected)
+        .arg(replacement)
         .query_async(&mut *conn)
         .await?;
+    if replaced == 0 {
+        tracing::debug!(
+            "Cached value {field_id} in {} changed while refreshing, keeping it",
+            T::CACHE_FIELD
+        );
+    }
 
-    match cached_study_str {
-        Some(c) => {
-            let cached_value: T = serde_json::from_str(&c)?;
-            Ok(Some(cached_value))
+    Ok(())
+}
+
+/// Reads a cached value and how fresh it is under `policy`, along with the entry as stored so a
+/// refresh can tell whether it has since been replaced. Expired and unreadable values are removed
+/// and reported as a miss, as is a failed read, which also counts towards tripping the breaker.
+async fn get_cached_entry<T: Cacheable + DeserializeOwned>(
+    pool: &Pool<RedisConnectionManager>,
+    field_id: &str,
+    policy: CachePolicy,
+) -> Result<Option<(T, Freshness, String)>> {
+    let Some(mut conn) = connection(pool).await else {
+        tracing::debug!("Cache unavailable, treating as a miss");
+        COUNTERS.record(false);
+        return Ok(None);
+    };
+    let cached_study_str: Option<String> = match redis::cmd("HGET")
+        .arg(T::CACHE_FIELD)
+        .arg(field_id)
+        .query_async(&mut *conn)
+        .await
+    {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::error!(
+                "Error reading {field_id} from {}: {}",
+                T::CACHE_FIELD,
+                e.to_string()
+            );
+            BREAKER.record_failure(Instant::now());
+            COUNTERS.record(false);
+            return Ok(None);
         }
-        None => Ok(None),
+    };
+
+    let Some(c) = cached_study_str else {
+        COUNTERS.record(false);
+        return Ok(None);
+    };
+
+    if let Some((value, freshness)) = decode_entry::<T>(field_id, &c, policy) {
+        COUNTERS.record(true);
+        return Ok(Some((value, freshness, c)));
+    }
+
+    COUNTERS.record(false);
+    let deleted: Result<(), _> = redis::cmd("HDEL")
+        .arg(T::CACHE_FIELD)
+        .arg(field_id)
+        .query_async(&mut *conn)
+        .await;
+    if let Err(e) = deleted {
+        tracing::error!(
+            "Error deleting discarded cached value {field_id}: {}",
+            e.to_string()
+        );
+    }
+
+    Ok(None)
+}
+
+/// Reads several cached values with a single HMGET, in the order of `field_ids`. Like
+/// `get_cached_value` expired and unreadable values are removed and come back as `None`, and a
+/// failed read is a miss for all of them.
+pub async fn get_cached_values<T: Cacheable + DeserializeOwned>(
+    pool: &Pool<RedisConnectionManager>,
+    field_ids: &[&str],
+) -> Result<Vec<Option<T>>> {
+    if field_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let Some(mut conn) = connection(pool).await else {
+        tracing::debug!("Cache unavailable, treating as a miss");
+        field_ids.iter().for_each(|_| COUNTERS.record(false));
+        return Ok(field_ids.iter().map(|_| None).collect());
+    };
+    let cached: Vec<Option<String>> = match redis::cmd("HMGET")
+        .arg(T::CACHE_FIELD)
+        .arg(field_ids)
+        .query_async(&mut *conn)
+        .await
+    {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::error!("Error reading from {}: {}", T::CACHE_FIELD, e.to_string());
+            BREAKER.record_failure(Instant::now());
+            field_ids.iter().for_each(|_| COUNTERS.record(false));
+            return Ok(field_ids.iter().map(|_| None).collect());
+        }
+    };
+
+    let policy = cache_policy();
+    let mut discarded: Vec<&str> = Vec::new();
+    let values = field_ids
+        .iter()
+        .zip(cached)
+        .map(|(field_id, c)| {
+            let value = c.and_then(|c| {
+                let cached = decode_entry::<T>(field_id, &c, policy);
+                if cached.is_none() {
+                    discarded.push(field_id);
+                }
+                cached
+            });
+            COUNTERS.record(value.is_some());
+            value.map(|(value, _)| value)
+        })
+        .collect();
+
+    if !discarded.is_empty() {
+        let deleted: Result<(), _> = redis::cmd("HDEL")
+            .arg(T::CACHE_FIELD)
+            .arg(&discarded)
+            .query_async(&mut *conn)
+            .await;
+        if let Err(e) = deleted {
+            tracing::error!(
+                "Error deleting discarded cached values from {}: {}",
+                T::CACHE_FIELD,
+                e.to_string()
+            );
+        }
+    }
+
+    Ok(values)
+}
+
+/// Decodes a cached entry and how fresh it is under `policy`. Expired and unreadable entries
+/// come back as `None` for the caller to remove.
+fn decode_entry<T: Cacheable + DeserializeOwned>(
+    field_id: &str,
+    cached: &str,
+    policy: CachePolicy,
+) -> Option<(T, Freshness)> {
+    match serde_json::from_str::<CacheEntry<T>>(cached) {
+        Ok(entry) => {
+            let age = (Utc::now() - entry.cached_at).to_std().unwrap_or_default();
+            match policy.freshness(age) {
+                Freshness::Expired => {
+                    tracing::debug!("Cached value {field_id} in {} expired", T::CACHE_FIELD);
+                    None
+                }
+                freshness => Some((entry.value, freshness)),
+            }
+        }
+        Err(e) => {
+            // A value written by an older deploy or otherwise mangled is treated as a miss so the
+            // caller falls back to the database instead of failing the request. It's removed so
+            // the next write replaces it rather than every read tripping over it.
+            tracing::warn!(
+                "Discarding unreadable cached value {field_id} in {}: {}",
+                T::CACHE_FIELD,
+                e.to_string()
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{atomic::AtomicBool, Arc};
+    use uuid::Uuid;
+
+    use crate::models::{
+        organization::{Organization, OrganizationInDb},
+        study::Study,
+        user::User,
+    };
+
+    #[test]
+    fn breaker_trips_after_threshold() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(30));
+        let now = Instant::now();
+
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+        assert!(!breaker.is_open(now));
+
+        breaker.record_failure(now);
+        assert!(breaker.is_open(now));
+    }
+
+    #[test]
+    fn breaker_allows_probe_after_cooldown() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(30));
+        let now = Instant::now();
+
+        breaker.record_failure(now);
+        assert!(breaker.is_open(now + Duration::from_secs(29)));
+        assert!(!breaker.is_open(now + Duration::from_secs(30)));
+
+        // A failed probe opens the breaker for another cooldown
+        let probe = now + Duration::from_secs(30);
+        breaker.record_failure(probe);
+        assert!(breaker.is_open(probe + Duration::from_secs(29)));
+    }
+
+    #[test]
+    fn breaker_recovers_on_success() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(30));
+        let now = Instant::now();
+
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+        assert!(breaker.is_open(now));
+
+        breaker.record_success();
+        assert!(!breaker.is_open(now));
+
+        // The failure count starts over after a success
+        breaker.record_failure(now);
+        assert!(!breaker.is_open(now));
+    }
+
+    #[test]
+    fn counters_reset() {
+        let counters = CacheCounters::new();
+        counters.record(true);
+        counters.record(true);
+        counters.record(true);
+        counters.record(false);
+
+        let stats = counters.stats(true);
+        assert_eq!(stats.hits, 3);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.ratio, 0.75);
+
+        let stats = counters.stats(false);
+        assert_eq!(stats.hits, 0);
+        assert_eq!(stats.misses, 0);
+        assert_eq!(stats.ratio, 0.0);
+    }
+
+    #[tokio::test]
+    async fn reads_are_counted() {
+        let pool = valkey_pool().await;
+        let organization = organization().await;
+
+        // Other tests read the cache concurrently so only a lower bound can be checked
+        let before = cache_stats(false);
+        get_cached_value::<Organization>(&pool, organization.id.as_str())
+            .await
+            .unwrap();
+        let after = cache_stats(false);
+        assert!(after.misses > before.misses);
+
+        add_cached_value(&pool, &organization).await.unwrap();
+        let before = cache_stats(false);
+        get_cached_value::<Organization>(&pool, organization.id.as_str())
+            .await
+            .unwrap();
+        let after = cache_stats(false);
+        assert!(after.hits > before.hits);
+
+        delete_cached_value::<Organization>(&pool, organization.id.as_str())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn deferred_invalidation_flushed_on_connect() {
+        let pool = valkey_pool().await;
+        let organization = organization().await;
+        add_cached_value(&pool, &organization).await.unwrap();
+
+        // As if the organization changed while valkey was unreachable
+        defer_invalidation(Organization::CACHE_FIELD, organization.id.as_str());
+        let cached = get_cached_value::<Organization>(&pool, organization.id.as_str())
+            .await
+            .unwrap();
+
+        assert!(cached.is_none());
+        assert!(!PENDING_INVALIDATIONS.lock().unwrap().contains(&(
+            Organization::CACHE_FIELD.to_string(),
+            organization.id.to_string()
+        )));
+    }
+
+    #[tokio::test]
+    async fn corrupt_value_is_a_miss() {
+        let pool = valkey_pool().await;
+        let organization = organization().await;
+        let mut conn = pool.get().await.unwrap();
+        let _: () = redis::cmd("HSET")
+            .arg(Organization::CACHE_FIELD)
+            .arg(organization.id.as_str())
+            .arg("{not json")
+            .query_async(&mut *conn)
+            .await
+            .unwrap();
+
+        let cached = get_cached_value::<Organization>(&pool, organization.id.as_str())
+            .await
+            .unwrap();
+        assert!(cached.is_none());
+
+        // The bad value is removed rather than left for the next read
+        let raw: Option<String> = redis::cmd("HGET")
+            .arg(Organization::CACHE_FIELD)
+            .arg(organization.id.as_str())
+            .query_async(&mut *conn)
+            .await
+            .unwrap();
+        assert!(raw.is_none());
+    }
+
+    #[tokio::test]
+    async fn values_read_together() {
+        let pool = valkey_pool().await;
+        let cached = organization().await;
+        let corrupt = organization().await;
+        let missing = organization().await;
+        add_cached_value(&pool, &cached).await.unwrap();
+        let mut conn = pool.get().await.unwrap();
+        let _: () = redis::cmd("HSET")
+            .arg(Organization::CACHE_FIELD)
+            .arg(corrupt.id.as_str())
+            .arg("{not json")
+            .query_async(&mut *conn)
+            .await
+            .unwrap();
+
+        let values = get_cached_values::<Organization>(
+            &pool,
+            &[cached.id.as_str(), corrupt.id.as_str(), missing.id.as_str()],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(values.len(), 3);
+        assert_eq!(values[0].as_ref().map(|o| &o.id), Some(&cached.id));
+        assert!(values[1].is_none());
+        assert!(values[2].is_none());
+
+        let raw: Option<String> = redis::cmd("HGET")
+            .arg(Organization::CACHE_FIELD)
+            .arg(corrupt.id.as_str())
+            .query_async(&mut *conn)
+            .await
+            .unwrap();
+        assert!(raw.is_none());
+
+        delete_cached_value::<Organization>(&pool, cached.id.as_str())
+            .await
+            .unwrap();
+    }
+
+    /// Stored under a plain string key so reading it as a hash fails
+    struct Mistyped;
+
+    impl Cacheable for Mistyped {
+        const CACHE_FIELD: &'static str = "cache-services-test-string";
+
+        fn get_key(&self) -> &str {
+            "mistyped"
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Mistyped {
+        fn deserialize<D: serde::Deserializer<'de>>(_: D) -> Result<Self, D::Error> {
+            Ok(Mistyped)
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_read_is_a_miss() {
+        let pool = valkey_pool().await;
+        let mut conn = pool.get().await.unwrap();
+        let _: () = redis::cmd("SET")
+            .arg(Mistyped::CACHE_FIELD)
+            .arg("not a hash")
+            .query_async(&mut *conn)
+            .await
+            .unwrap();
+
+        let before = cache_stats(false);
+        let cached = get_cached_value::<Mistyped>(&pool, "mistyped")
+            .await
+            .unwrap();
+        let values = get_cached_values::<Mistyped>(&pool, &["mistyped", "other"])
+            .await
+            .unwrap();
+        let after = cache_stats(false);
+
+        assert!(cached.is_none());
+        assert!(values.iter().all(Option::is_none));
+        assert!(after.misses >= before.misses + 3);
+    }
+
+    #[tokio::test]
+    async fn values_deleted_together() {
+        let pool = valkey_pool().await;
+        let first = organization().await;
+        let second = organization().await;
+        add_cached_value(&pool, &first).await.unwrap();
+        add_cached_value(&pool, &second).await.unwrap();
+
+        delete_cached_values::<Organization>(&pool, &[first.id.to_string(), second.id.to_string()])
+            .await
+            .unwrap();
+
+        let values =
+            get_cached_values::<Organization>(&pool, &[first.id.as_str(), second.id.as_str()])
+                .await
+                .unwrap();
+        assert!(values.iter().all(Option::is_none));
+    }
+
+    const SWR_POLICY: CachePolicy = CachePolicy {
+        fresh: Duration::from_secs(10),
+        stale: Duration::from_secs(60),
+    };
+
+    #[test]
+    fn policy_freshness() {
+        assert_eq!(
+            SWR_POLICY.freshness(Duration::from_secs(9)),
+            Freshness::Fresh
+        );
+        assert_eq!(
+            SWR_POLICY.freshness(Duration::from_secs(10)),
+            Freshness::Stale
+        );
+        assert_eq!(
+            SWR_POLICY.freshness(Duration::from_secs(69)),
+            Freshness::Stale
+        );
+        assert_eq!(
+            SWR_POLICY.freshness(Duration::from_secs(70)),
+            Freshness::Expired
+        );
+    }
+
+    #[test]
+    fn policy_disabled() {
+        let policy = CachePolicy::default();
+
+        assert_eq!(
+            policy.freshness(Duration::from_secs(u32::MAX.into())),
+            Freshness::Fresh
+        );
+    }
+
+    /// Caches `organization` as if it were added `age` ago
+    async fn add_aged(pool: &Pool<RedisConnectionManager>, organization: &Organization, age: i64) {
+        add_cached_entry(
+            pool,
+            organization,
+            Utc::now() - chrono::Duration::seconds(age),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn stale_value_served_and_refreshed() {
+        let pool = valkey_pool().await;
+        let organization = organization().await;
+        add_aged(&pool, &organization, 15).await;
+
+        let mut refreshed = organization.clone();
+        refreshed.name = Uuid::new_v4().to_string();
+        let refreshed_name = refreshed.name.clone();
+        let cached = get_or_refresh_with_policy(
+            &pool,
+            organization.id.as_str(),
+            SWR_POLICY,
+            move || async move { Ok(Some(refreshed)) },
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        // The stale value is returned straight away
+        assert_eq!(cached.name, organization.name);
+
+        let mut name = None;
+        for _ in 0..50 {
+            let (current, freshness, _) =
+                get_cached_entry::<Organization>(&pool, organization.id.as_str(), SWR_POLICY)
+                    .await
+                    .unwrap()
+                    .unwrap();
+            if freshness == Freshness::Fresh {
+                name = Some(current.name);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(name, Some(refreshed_name));
+
+        delete_cached_value::<Organization>(&pool, organization.id.as_str())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn refresh_keeps_newer_value() {
+        let pool = valkey_pool().await;
+        let organization = organization().await;
+        add_aged(&pool, &organization, 15).await;
+
+        // The refresh reads the database, then an update commits and caches its value before
+        // the refresh gets to write the snapshot it read
+        let snapshot = organization.clone();
+        let mut updated = organization.clone();
+        updated.name = Uuid::new_v4().to_string();
+        let updated_name = updated.name.clone();
+        let update_pool = pool.clone();
+        get_or_refresh_with_policy(
+            &pool,
+            organization.id.as_str(),
+            SWR_POLICY,
+            move || async move {
+                add_cached_value(&update_pool, &updated).await.unwrap();
+                Ok(Some(snapshot))
+            },
+        )
+        .await
+        .unwrap();
+
+        let refresh_key = format!("{}:{}", Organization::CACHE_FIELD, organization.id);
+        for _ in 0..50 {
+            if !REFRESHING.lock().unwrap().contains(&refresh_key) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let cached = get_cached_value::<Organization>(&pool, organization.id.as_str())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(cached.name, updated_name);
+
+        delete_cached_value::<Organization>(&pool, organization.id.as_str())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn fresh_value_not_refreshed() {
+        let pool = valkey_pool().await;
+        let organization = organization().await;
+        add_aged(&pool, &organization, 5).await;
+
+        let refreshed = Arc::new(AtomicBool::new(false));
+        let called = refreshed.clone();
+        let cached = get_or_refresh_with_policy(
+            &pool,
+            organization.id.as_str(),
+            SWR_POLICY,
+            move || async move {
+                called.store(true, Ordering::SeqCst);
+                Ok(None::<Organization>)
+            },
+        )
+        .await
+        .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(cached.unwrap().name, organization.name);
+        assert!(!refreshed.load(Ordering::SeqCst));
+
+        delete_cached_value::<Organization>(&pool, organization.id.as_str())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn expired_value_is_a_miss() {
+        let pool = valkey_pool().await;
+        let organization = organization().await;
+        add_aged(&pool, &organization, 70).await;
+
+        let cached = get_cached_entry::<Organization>(&pool, organization.id.as_str(), SWR_POLICY)
+            .await
+            .unwrap();
+        assert!(cached.is_none());
+
+        // Once expired the value is gone whatever the policy
+        let cached = get_cached_entry::<Organization>(
+            &pool,
+            organization.id.as_str(),
+            CachePolicy::default(),
+        )
+        .await
+        .unwrap();
+        assert!(cached.is_none());
+    }
+
+    async fn organization() -> Organization {
+        OrganizationInDb::prepare_create(Uuid::new_v4().to_string())
+            .await
+            .unwrap()
+            .into()
+    }
+
+    async fn valkey_pool() -> Pool<RedisConnectionManager> {
+        let manager = RedisConnectionManager::new("redis://:valkeypassword@127.0.0.1:6379")
+            .expect("Error creating valkey manager");
+
+        Pool::builder()
+            .build(manager)
+            .await
+            .expect("Error creating valkey pool")
+    }
+
+    #[tokio::test]
+    async fn organization_cache_round_trip() {
+        let pool = valkey_pool().await;
+        let organization = organization().await;
+
+        add_cached_value(&pool, &organization).await.unwrap();
+        let cached = get_cached_value::<Organization>(&pool, organization.id.as_str())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached.name, organization.name);
+
+        delete_cached_value::<Organization>(&pool, organization.id.as_str())
+            .await
+            .unwrap();
+        let cached = get_cached_value::<Organization>(&pool, organization.id.as_str())
+            .await
+            .unwrap();
+        assert!(cached.is_none());
+    }
+
+    #[tokio::test]
+    async fn study_cache_round_trip() {
+        let pool = valkey_pool().await;
+        let study = Study {
+            id: Uuid::new_v4().to_string(),
+            study_id: Uuid::new_v4().to_string(),
+            study_name: Some("Study Name".to_string()),
+            study_description: None,
+            locked: false,
+            protocol_version: "1.0".to_string(),
+            organization: organization().await,
+        };
+
+        add_cached_value(&pool, &study).await.unwrap();
+        let cached = get_cached_value::<Study>(&pool, &study.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached.study_id, study.study_id);
+
+        delete_cached_value::<Study>(&pool, &study.id)
+            .await
+            .unwrap();
+        let cached = get_cached_value::<Study>(&pool, &study.id).await.unwrap();
+        assert!(cached.is_none());
+    }
+
+    #[tokio::test]
+    async fn user_cache_round_trip() {
+        let pool = valkey_pool().await;
+        let user = User {
+            id: Uuid::new_v4().to_string(),
+            user_name: Uuid::new_v4().to_string(),
+            first_name: "Arthur".to_string(),
+            last_name: "Dent".to_string(),
+            email: "arthur@heartofgold.com".to_string(),
+            phone: None,
+            organization: organization().await,
+            studies: None,
+            active: true,
+        };
+
+        add_cached_value(&pool, &user).await.unwrap();
+        let cached = get_cached_value::<User>(&pool, &user.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached.user_name, user.user_name);
+
+        delete_cached_value::<User>(&pool, &user.id).await.unwrap();
+        let cached = get_cached_value::<User>(&pool, &user.id).await.unwrap();
+        assert!(cached.is_none());
     }
 }
@@ -0,0 +1,47 @@
+use anyhow::{bail, Result};
+use bb8::Pool;
+use bb8_redis::RedisConnectionManager;
+
+use crate::services::cache_services::connection;
+
+/// Valkey key holding the runtime maintenance flag, shared by every instance
+const MAINTENANCE_KEY: &str = "maintenance_mode";
+
+/// Whether maintenance mode has been turned on at runtime. An unreachable cache is treated as off
+/// so an outage doesn't also block writes.
+pub async fn get_maintenance_service(valkey_pool: &Pool<RedisConnectionManager>) -> Result<bool> {
+    let Some(mut conn) = connection(valkey_pool).await else {
+        tracing::debug!("Cache unavailable, treating maintenance mode as off");
+        return Ok(false);
+    };
+    let value: Option<String> = redis::cmd("GET")
+        .arg(MAINTENANCE_KEY)
+        .query_async(&mut *conn)
+        .await?;
+
+    Ok(value.as_deref() == Some("1"))
+}
+
+pub async fn set_maintenance_service(
+    valkey_pool: &Pool<RedisConnectionManager>,
+    enabled: bool,
+) -> Result<()> {
+    let Some(mut conn) = connection(valkey_pool).await else {
+        bail!("Cache unavailable, unable to change maintenance mode");
+    };
+
+    if enabled {
+        redis::cmd("SET")
+            .arg(MAINTENANCE_KEY)
+            .arg("1")
+            .query_async(&mut *conn)
+            .await?;
+    } else {
+        redis::cmd("DEL")
+            .arg(MAINTENANCE_KEY)
+            .query_async(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
@@ -1,4 +1,8 @@
+pub mod activity_services;
 pub mod cache_services;
+pub mod maintenance_services;
 pub mod organization_services;
+pub mod search_services;
 pub mod study_services;
 pub mod user_services;
+pub mod webhook_services;
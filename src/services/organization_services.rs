@@ -1,96 +1,463 @@
+use std::collections::HashMap;
+
 use anyhow::{bail, Result};
 use bb8::Pool;
 use bb8_redis::RedisConnectionManager;
 use chrono::Utc;
-use sqlx::postgres::PgPool;
+use sqlx::{postgres::PgPool, PgConnection};
 
 use crate::{
-    models::organization::{Organization, OrganizationCreate, OrganizationUpdate},
-    services::cache_services::{add_cached_value, delete_cached_value, get_cached_value},
+    db::{classify_error, DbErrorKind},
+    models::{
+        organization::{
+            Organization, OrganizationBootstrap, OrganizationBootstrapped, OrganizationCreate,
+            OrganizationId, OrganizationInDb, OrganizationUpdate,
+        },
+        pagination::Pagination,
+        response::ListResponse,
+        sort::Sort,
+        study::Study,
+        user::{AccessLevel, User, UserInDb},
+    },
+    services::cache_services::{
+        add_cached_value, delete_cached_value, delete_cached_values, get_cached_value_or_refresh,
+        get_cached_values,
+    },
+    utils::{normalize_email, validate_phone, PasswordHashPermits},
 };
 
+/// Organization services bound to the app's pools so routes don't have to pick and pass them.
+/// Counts and lists use the read pool, everything else the primary.
+pub struct Organizations<'a> {
+    db_pool: &'a PgPool,
+    read_pool: &'a PgPool,
+    valkey_pool: &'a Pool<RedisConnectionManager>,
+    password_hash_permits: &'a PasswordHashPermits,
+}
+
+impl<'a> Organizations<'a> {
+    pub fn new(
+        db_pool: &'a PgPool,
+        read_pool: &'a PgPool,
+        valkey_pool: &'a Pool<RedisConnectionManager>,
+        password_hash_permits: &'a PasswordHashPermits,
+    ) -> Self {
+        Self {
+            db_pool,
+            read_pool,
+            valkey_pool,
+            password_hash_permits,
+        }
+    }
+
+    pub async fn bootstrap(
+        &self,
+        bootstrap: &OrganizationBootstrap,
+    ) -> Result<OrganizationBootstrapped> {
+        bootstrap_organization_service(
+            self.db_pool,
+            self.valkey_pool,
+            self.password_hash_permits,
+            bootstrap,
+        )
+        .await
+    }
+
+    pub async fn create(&self, new_organization: &OrganizationCreate) -> Result<Organization> {
+        create_organization_service(self.db_pool, self.valkey_pool, new_organization).await
+    }
+
+    pub async fn delete(&self, organization_id: &OrganizationId, cascade: bool) -> Result<()> {
+        delete_organization_service(self.db_pool, self.valkey_pool, organization_id, cascade).await
+    }
+
+    pub async fn get(&self, organization_id: &OrganizationId) -> Result<Option<Organization>> {
+        get_organization_service(self.db_pool, self.valkey_pool, organization_id, false).await
+    }
+
+    pub async fn get_many(&self, organization_ids: &[OrganizationId]) -> Result<Vec<Organization>> {
+        get_organizations_by_id_service(self.db_pool, self.valkey_pool, organization_ids).await
+    }
+
+    pub async fn count(&self) -> Result<i64> {
+        count_organizations_service(self.read_pool).await
+    }
+
+    pub async fn list(
+        &self,
+        pagination: &Pagination,
+        sort: &Sort,
+    ) -> Result<ListResponse<Organization>> {
+        get_organizations_service(self.read_pool, pagination, sort).await
+    }
+
+    pub async fn update(&self, updated_organization: &OrganizationUpdate) -> Result<Organization> {
+        update_organization_service(self.db_pool, self.valkey_pool, updated_organization).await
+    }
+}
+
+/// Creates an organization and its first admin user in one transaction so a failure creating
+/// the admin doesn't leave behind an organization nobody can manage.
+pub async fn bootstrap_organization_service(
+    db_pool: &PgPool,
+    valkey_pool: &Pool<RedisConnectionManager>,
+    password_hash_permits: &PasswordHashPermits,
+    bootstrap: &OrganizationBootstrap,
+) -> Result<OrganizationBootstrapped> {
+    if let Some(phone) = &bootstrap.admin.phone {
+        validate_phone(phone)?;
+    }
+
+    let organization = OrganizationInDb::prepare_create(bootstrap.name.clone()).await?;
+    let mut prepped_admin = UserInDb::prepare_create(
+        bootstrap.admin.user_name.to_string(),
+        bootstrap.admin.first_name.to_string(),
+        bootstrap.admin.last_name.to_string(),
+        normalize_email(&bootstrap.admin.email),
+        bootstrap.admin.phone.clone(),
+        bootstrap.admin.password.to_string(),
+        organization.id.to_string(),
+        password_hash_permits,
+    )
+    .await?;
+    prepped_admin.access_level = AccessLevel::OrganizationAdmin;
+
+    let mut tx = db_pool.begin().await?;
+
+    let added_org = match sqlx::query_as!(
+        Organization,
+        r#"
+            INSERT INTO organizations(id, name, active, date_added, date_modified)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id AS "id: OrganizationId", name, active, date_added, date_modified
+        "#,
+        organization.id.as_str(),
+        organization.name,
+        organization.active,
+        organization.date_added,
+        organization.date_modified,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    {
+        Ok(o) => o,
+        Err(e) => {
+            if matches!(classify_error(&e), DbErrorKind::UniqueViolation { .. }) {
+                bail!(format!(
+                    "An organization with the name {} already exists (case-insensitive)",
+                    &bootstrap.name
+                ));
+            }
+            return Err(e.into());
+        }
+    };
+
+    let db_admin = match sqlx::query_as!(
+        UserInDb,
+        r#"
+            INSERT INTO users (
+                id,
+                user_name,
+                first_name,
+                last_name,
+                email,
+                phone,
+                hashed_password,
+                organization_id,
+                active,
+                access_level,
+                date_added,
+                date_modified
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            RETURNING
+                id,
+                user_name,
+                first_name,
+                last_name,
+                email,
+                phone,
+                hashed_password,
+                active,
+                organization_id,
+                access_level AS "access_level: AccessLevel",
+                date_added,
+                date_modified
+        "#,
+        prepped_admin.id,
+        prepped_admin.user_name,
+        prepped_admin.first_name,
+        prepped_admin.last_name,
+        prepped_admin.email,
+        prepped_admin.phone,
+        prepped_admin.hashed_password,
+        prepped_admin.organization_id,
+        prepped_admin.active,
+        prepped_admin.access_level as AccessLevel,
+        prepped_admin.date_added,
+        prepped_admin.date_modified,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    {
+        Ok(u) => u,
+        Err(e) => {
+            if matches!(classify_error(&e), DbErrorKind::UniqueViolation { .. }) {
+                bail!(format!(
+                    "A user with the user name {} already exists",
+                    &bootstrap.admin.user_name
+                ));
+            }
+            return Err(e.into());
+        }
+    };
+
+    tx.commit().await?;
+    tracing::debug!("Organization and admin successfully saved to database");
+
+    let admin = User {
+        id: db_admin.id,
+        user_name: db_admin.user_name,
+        first_name: db_admin.first_name,
+        last_name: db_admin.last_name,
+        email: db_admin.email,
+        phone: db_admin.phone,
+        organization: added_org.clone(),
+        studies: None,
+        active: db_admin.active,
+    };
+
+    tracing::debug!("Adding organization and admin to cache");
+    add_cached_value(valkey_pool, &added_org).await?;
+    add_cached_value(valkey_pool, &admin).await?;
+
+    Ok(OrganizationBootstrapped {
+        organization: added_org,
+        admin,
+    })
+}
+
 pub async fn create_organization_service(
     db_pool: &PgPool,
     valkey_pool: &Pool<RedisConnectionManager>,
     new_organization: &OrganizationCreate,
 ) -> Result<Organization> {
-    let organization = Organization::new(new_organization.name.clone());
+    let organization = OrganizationInDb::prepare_create(new_organization.name.clone()).await?;
 
-    let added_org = sqlx::query_as!(
+    let added_org = match sqlx::query_as!(
         Organization,
         r#"
             INSERT INTO organizations(id, name, active, date_added, date_modified)
             VALUES ($1, $2, $3, $4, $5)
-            RETURNING id, name, active, date_added, date_modified
+            RETURNING id AS "id: OrganizationId", name, active, date_added, date_modified
         "#,
-        organization.id,
+        organization.id.as_str(),
         organization.name,
         organization.active,
         organization.date_added,
         organization.date_modified,
     )
     .fetch_one(db_pool)
-    .await?;
+    .await
+    {
+        Ok(o) => o,
+        Err(e) => {
+            if matches!(classify_error(&e), DbErrorKind::UniqueViolation { .. }) {
+                bail!(format!(
+                    "An organization with the name {} already exists (case-insensitive)",
+                    &new_organization.name
+                ));
+            }
+            return Err(e.into());
+        }
+    };
 
     tracing::debug!("Adding organization to cache");
-    add_cached_value(valkey_pool, &organization).await?;
+    add_cached_value(valkey_pool, &added_org).await?;
     tracing::debug!("Organization successfully saved to cache");
 
     Ok(added_org)
 }
 
+/// Soft deletes the organization by setting `deleted_at`, after which reads no longer see it.
+/// With `cascade` its studies and users are soft deleted along with it, otherwise having any is
+/// an error.
 pub async fn delete_organization_service(
     db_pool: &PgPool,
     valkey_pool: &Pool<RedisConnectionManager>,
-    organization_id: &str,
+    organization_id: &OrganizationId,
+    cascade: bool,
 ) -> Result<()> {
-    let result = sqlx::query!(
+    let mut tx = db_pool.begin().await?;
+
+    // Lock the organization so writes referencing it, which take it with
+    // `lock_live_organization`, wait for the delete and then find it gone.
+    let organization = sqlx::query!(
         r#"
-            DELETE FROM organizations
-            WHERE id = $1
+            SELECT id
+            FROM organizations
+            WHERE id = $1 AND deleted_at IS NULL
+            FOR UPDATE
         "#,
-        organization_id,
+        organization_id.as_str(),
     )
-    .execute(db_pool)
+    .fetch_optional(&mut *tx)
     .await?;
 
-    if result.rows_affected() > 0 {
-        tracing::debug!("Organization successfully deleted from database, deleting from cache");
-
-        delete_cached_value(valkey_pool, "organizations", organization_id).await?;
-        tracing::debug!("Organization successfully deleted from cache");
-        Ok(())
-    } else {
+    if organization.is_none() {
         bail!(format!(
             "No organization with the id {organization_id} found"
         ));
     }
+
+    let study_ids = sqlx::query_scalar!(
+        r#"
+            SELECT id
+            FROM studies
+            WHERE organization_id = $1 AND deleted_at IS NULL
+        "#,
+        organization_id.as_str(),
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    // Users elsewhere assigned to one of the studies are included so their cached study lists
+    // are cleared as well
+    let user_ids = sqlx::query_scalar!(
+        r#"
+            SELECT id AS "id!"
+            FROM users
+            WHERE organization_id = $1 AND deleted_at IS NULL
+            UNION
+            SELECT user_studies.user_id
+            FROM user_studies
+            JOIN studies ON studies.id = user_studies.study_id
+            WHERE studies.organization_id = $1 AND studies.deleted_at IS NULL
+        "#,
+        organization_id.as_str(),
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if !cascade && (!study_ids.is_empty() || !user_ids.is_empty()) {
+        bail!(format!(
+            "Organization {organization_id} has dependent studies or users, set cascade=true to delete them"
+        ));
+    }
+
+    let deleted_at = Utc::now();
+    sqlx::query!(
+        r#"
+            UPDATE studies
+            SET deleted_at = $2
+            WHERE organization_id = $1 AND deleted_at IS NULL
+        "#,
+        organization_id.as_str(),
+        deleted_at,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            UPDATE users
+            SET deleted_at = $2
+            WHERE organization_id = $1 AND deleted_at IS NULL
+        "#,
+        organization_id.as_str(),
+        deleted_at,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            UPDATE organizations
+            SET deleted_at = $2
+            WHERE id = $1
+        "#,
+        organization_id.as_str(),
+        deleted_at,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    tracing::debug!("Organization successfully deleted from database, deleting from cache");
+
+    delete_cached_value::<Organization>(valkey_pool, organization_id.as_str()).await?;
+    delete_cached_values::<Study>(valkey_pool, &study_ids).await?;
+    delete_cached_values::<User>(valkey_pool, &user_ids).await?;
+    tracing::debug!("Organization successfully deleted from cache");
+
+    Ok(())
+}
+
+/// Share locks the organization for a write that references it, in the writer's transaction. A
+/// concurrent delete either commits first, and the organization is reported missing, or waits
+/// for the write to commit and takes its rows along. The foreign key alone would accept a soft
+/// deleted organization.
+pub(crate) async fn lock_live_organization(
+    conn: &mut PgConnection,
+    organization_id: &str,
+) -> Result<()> {
+    let organization = sqlx::query_scalar!(
+        r#"
+            SELECT id
+            FROM organizations
+            WHERE id = $1 AND deleted_at IS NULL
+            FOR SHARE
+        "#,
+        organization_id,
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    if organization.is_none() {
+        bail!(format!("No organization with id {organization_id} found"));
+    }
+
+    Ok(())
 }
 
 pub async fn get_organization_service(
     db_pool: &PgPool,
     valkey_pool: &Pool<RedisConnectionManager>,
-    organization_id: &str,
+    organization_id: &OrganizationId,
     skip_cache: bool,
 ) -> Result<Option<Organization>> {
     if !skip_cache {
         tracing::debug!("Checking for organization in cache");
-        let cached_organization =
-            get_cached_value(valkey_pool, "organizations", organization_id).await?;
+        let refresh_pool = db_pool.clone();
+        let refresh_id = organization_id.clone();
+        let cached_organization = get_cached_value_or_refresh(
+            valkey_pool,
+            organization_id.as_str(),
+            move || async move { fetch_organization(&refresh_pool, &refresh_id).await },
+        )
+        .await?;
         if cached_organization.is_some() {
             return Ok(cached_organization);
         } else {
             tracing::debug!("Organization not found in cache");
         }
     }
+
+    fetch_organization(db_pool, organization_id).await
+}
+
+async fn fetch_organization(
+    db_pool: &PgPool,
+    organization_id: &OrganizationId,
+) -> Result<Option<Organization>> {
     let organization = sqlx::query_as!(
         Organization,
         r#"
-            SELECT id, name, active, date_added, date_modified
+            SELECT id AS "id: OrganizationId", name, active, date_added, date_modified
             FROM organizations
-            WHERE id = $1
+            WHERE id = $1 AND deleted_at IS NULL
         "#,
-        organization_id,
+        organization_id.as_str(),
     )
     .fetch_optional(db_pool)
     .await?;
@@ -98,18 +465,102 @@ pub async fn get_organization_service(
     Ok(organization)
 }
 
-pub async fn get_organizations_service(db_pool: &PgPool) -> Result<Vec<Organization>> {
+/// Looks up each id in the cache first and fetches the misses from the database in a single
+/// query, backfilling the cache with what it finds. Ids that don't exist are skipped and the
+/// results keep the order of `organization_ids`.
+pub async fn get_organizations_by_id_service(
+    db_pool: &PgPool,
+    valkey_pool: &Pool<RedisConnectionManager>,
+    organization_ids: &[OrganizationId],
+) -> Result<Vec<Organization>> {
+    let mut ids: Vec<&str> = Vec::new();
+    for id in organization_ids.iter().map(OrganizationId::as_str) {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+
+    let mut found: HashMap<String, Organization> = HashMap::new();
+    let mut misses: Vec<String> = Vec::new();
+    let cached = get_cached_values::<Organization>(valkey_pool, &ids).await?;
+    for (id, organization) in ids.into_iter().zip(cached) {
+        match organization {
+            Some(o) => {
+                found.insert(id.to_string(), o);
+            }
+            None => misses.push(id.to_string()),
+        }
+    }
+
+    if !misses.is_empty() {
+        tracing::debug!("{} organizations not found in cache", misses.len());
+        let db_organizations = sqlx::query_as!(
+            Organization,
+            r#"
+                SELECT id AS "id: OrganizationId", name, active, date_added, date_modified
+                FROM organizations
+                WHERE id = ANY($1) AND deleted_at IS NULL
+            "#,
+            &misses[..],
+        )
+        .fetch_all(db_pool)
+        .await?;
+
+        for organization in db_organizations.into_iter() {
+            add_cached_value(valkey_pool, &organization).await?;
+            found.insert(organization.id.to_string(), organization);
+        }
+    }
+
+    let organizations = organization_ids
+        .iter()
+        .filter_map(|id| found.remove(id.as_str()))
+        .collect();
+
+    Ok(organizations)
+}
+
+/// Counts the organizations the list endpoint pages through, without fetching them
+pub async fn count_organizations_service(db_pool: &PgPool) -> Result<i64> {
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM organizations WHERE deleted_at IS NULL"#
+    )
+    .fetch_one(db_pool)
+    .await?;
+
+    Ok(count)
+}
+
+pub async fn get_organizations_service(
+    db_pool: &PgPool,
+    pagination: &Pagination,
+    sort: &Sort,
+) -> Result<ListResponse<Organization>> {
+    let total = count_organizations_service(db_pool).await?;
     let organizations = sqlx::query_as!(
         Organization,
         r#"
-            SELECT id, name, active, date_added, date_modified
+            SELECT id AS "id: OrganizationId", name, active, date_added, date_modified
             FROM organizations
-        "#
+            WHERE deleted_at IS NULL
+            ORDER BY
+                CASE WHEN $3 = 'name' AND $4 = 'asc' THEN name END ASC,
+                CASE WHEN $3 = 'name' AND $4 = 'desc' THEN name END DESC,
+                CASE WHEN $3 = 'date_added' AND $4 = 'asc' THEN date_added END ASC,
+                CASE WHEN $3 = 'date_added' AND $4 = 'desc' THEN date_added END DESC,
+                id
+            LIMIT $1
+            OFFSET $2
+        "#,
+        pagination.limit,
+        pagination.offset,
+        sort.field(),
+        sort.direction(),
     )
     .fetch_all(db_pool)
     .await?;
 
-    Ok(organizations)
+    Ok(ListResponse::new(organizations, total, pagination))
 }
 
 pub async fn update_organization_service(
@@ -118,13 +569,13 @@ pub async fn update_organization_service(
     updated_organization: &OrganizationUpdate,
 ) -> Result<Organization> {
     tracing::debug!("Updating organization in database");
-    let updated_org = sqlx::query_as!(
+    let updated_org = match sqlx::query_as!(
         Organization,
         r#"
             UPDATE organizations
             SET name = $2, active = $3, date_modified = $4
-            WHERE id = $1
-            RETURNING id, name, active, date_added, date_modified
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING id AS "id: OrganizationId", name, active, date_added, date_modified
         "#,
         updated_organization.id,
         updated_organization.name,
@@ -132,11 +583,70 @@ pub async fn update_organization_service(
         Utc::now(),
     )
     .fetch_one(db_pool)
-    .await?;
+    .await
+    {
+        Ok(o) => o,
+        Err(e) => {
+            if matches!(classify_error(&e), DbErrorKind::UniqueViolation { .. }) {
+                bail!(format!(
+                    "An organization with the name {} already exists (case-insensitive)",
+                    &updated_organization.name
+                ));
+            }
+            return Err(e.into());
+        }
+    };
     tracing::debug!("Successfully updated organization in database");
 
     tracing::debug!("Adding updated organization to cache");
     add_cached_value(valkey_pool, &updated_org).await?;
+    invalidate_organization_dependents(db_pool, valkey_pool, &updated_org.id).await?;
 
     Ok(updated_org)
 }
+
+/// Removes the cached studies and users that embed the organization so they are rebuilt with
+/// its current values on their next read. That covers the organization's own studies and users
+/// as well as users elsewhere assigned to one of its studies.
+async fn invalidate_organization_dependents(
+    db_pool: &PgPool,
+    valkey_pool: &Pool<RedisConnectionManager>,
+    organization_id: &OrganizationId,
+) -> Result<()> {
+    let study_ids = sqlx::query_scalar!(
+        r#"
+            SELECT id
+            FROM studies
+            WHERE organization_id = $1 AND deleted_at IS NULL
+        "#,
+        organization_id.as_str(),
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let user_ids = sqlx::query_scalar!(
+        r#"
+            SELECT id AS "id!"
+            FROM users
+            WHERE organization_id = $1 AND deleted_at IS NULL
+            UNION
+            SELECT user_studies.user_id
+            FROM user_studies
+            JOIN studies ON studies.id = user_studies.study_id
+            WHERE studies.organization_id = $1 AND studies.deleted_at IS NULL
+        "#,
+        organization_id.as_str(),
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    tracing::debug!(
+        "Removing {} studies and {} users embedding organization {organization_id} from cache",
+        study_ids.len(),
+        user_ids.len()
+    );
+    delete_cached_values::<Study>(valkey_pool, &study_ids).await?;
+    delete_cached_values::<User>(valkey_pool, &user_ids).await?;
+
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use sqlx::postgres::PgPool;
+
+use crate::{
+    models::{
+        organization::{Organization, OrganizationId},
+        search::{contains_pattern, SearchResults, MAX_SEARCH_RESULTS},
+        study::{Study, StudyInDb},
+        user::{AccessLevel, UserInDb},
+    },
+    services::user_services::users_with_studies,
+};
+
+/// Searches each of `types` for `text`, returning at most `MAX_SEARCH_RESULTS` of each. With an
+/// `organization_id` only that organization and its studies and users can match.
+pub async fn search_service(
+    db_pool: &PgPool,
+    text: &str,
+    types: &[&str],
+    organization_id: Option<&str>,
+) -> Result<SearchResults> {
+    let pattern = contains_pattern(text);
+    let mut results = SearchResults::default();
+
+    if types.contains(&"organizations") {
+        results.organizations = sqlx::query_as!(
+            Organization,
+            r#"
+                SELECT id AS "id: OrganizationId", name, active, date_added, date_modified
+                FROM organizations
+                WHERE deleted_at IS NULL
+                  AND name ILIKE $1
+                  AND ($2::text IS NULL OR id = $2)
+                ORDER BY name, id
+                LIMIT $3
+            "#,
+            pattern,
+            organization_id,
+            MAX_SEARCH_RESULTS,
+        )
+        .fetch_all(db_pool)
+        .await?;
+    }
+
+    if types.contains(&"studies") {
+        let db_studies = sqlx::query_as!(
+            StudyInDb,
+            r#"
+                SELECT
+                    id,
+                    study_id,
+                    study_name,
+                    study_description,
+                    organization_id,
+                    locked,
+                    protocol_version,
+                    date_added,
+                    date_modified
+                FROM studies
+                WHERE deleted_at IS NULL
+                  AND (study_id ILIKE $1 OR study_name ILIKE $1)
+                  AND ($2::text IS NULL OR organization_id = $2)
+                ORDER BY study_id, id
+                LIMIT $3
+            "#,
+            pattern,
+            organization_id,
+            MAX_SEARCH_RESULTS,
+        )
+        .fetch_all(db_pool)
+        .await?;
+        results.studies = studies_with_organizations(db_pool, db_studies).await?;
+    }
+
+    if types.contains(&"users") {
+        let db_users = sqlx::query_as!(
+            UserInDb,
+            r#"
+                SELECT
+                    id,
+                    user_name,
+                    first_name,
+                    last_name,
+                    email,
+                    phone,
+                    hashed_password,
+                    organization_id,
+                    active,
+                    access_level AS "access_level: AccessLevel",
+                    date_added,
+                    date_modified
+                FROM users
+                WHERE deleted_at IS NULL
+                  AND (
+                    user_name ILIKE $1
+                    OR first_name ILIKE $1
+                    OR last_name ILIKE $1
+                    OR email ILIKE $1
+                  )
+                  AND ($2::text IS NULL OR organization_id = $2)
+                ORDER BY user_name, id
+                LIMIT $3
+            "#,
+            pattern,
+            organization_id,
+            MAX_SEARCH_RESULTS,
+        )
+        .fetch_all(db_pool)
+        .await?;
+        results.users = users_with_studies(db_pool, db_users).await?;
+    }
+
+    Ok(results)
+}
+
+/// Builds API studies, loading their organizations in one query. A study whose organization
+/// was deleted mid search is left out.
+async fn studies_with_organizations(
+    db_pool: &PgPool,
+    db_studies: Vec<StudyInDb>,
+) -> Result<Vec<Study>> {
+    let organization_ids: Vec<String> = db_studies
+        .iter()
+        .map(|s| s.organization_id.clone())
+        .collect();
+    let organizations: HashMap<String, Organization> = sqlx::query_as!(
+        Organization,
+        r#"
+            SELECT id AS "id: OrganizationId", name, active, date_added, date_modified
+            FROM organizations
+            WHERE id = ANY($1) AND deleted_at IS NULL
+        "#,
+        &organization_ids[..],
+    )
+    .fetch_all(db_pool)
+    .await?
+    .into_iter()
+    .map(|o| (o.id.to_string(), o))
+    .collect();
+
+    let studies = db_studies
+        .into_iter()
+        .filter_map(|db_study| {
+            let organization = organizations.get(&db_study.organization_id).cloned()?;
+            Some(Study {
+                id: db_study.id,
+                study_id: db_study.study_id,
+                study_name: db_study.study_name,
+                study_description: db_study.study_description,
+                locked: db_study.locked,
+                protocol_version: db_study.protocol_version,
+                organization,
+            })
+        })
+        .collect();
+
+    Ok(studies)
+}
@@ -1,15 +1,30 @@
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use bb8::Pool;
 use bb8_redis::RedisConnectionManager;
 use chrono::Utc;
 use sqlx::postgres::PgPool;
 
 use crate::{
-    models::study::{Study, StudyCreate, StudyInDb, StudyUpdate},
+    db::{classify_error, DbErrorKind},
+    models::{
+        created_range::CreatedRange,
+        organization::OrganizationId,
+        pagination::Pagination,
+        response::ListResponse,
+        sort::Sort,
+        study::{
+            Study, StudyAmend, StudyCreate, StudyIdIndex, StudyInDb, StudyPatch, StudyUpdate,
+            MAX_AMENDMENT_NOTE_LENGTH, MAX_STUDY_DESCRIPTION_LENGTH, MAX_STUDY_ID_LENGTH,
+            MAX_STUDY_NAME_LENGTH,
+        },
+    },
     services::{
-        cache_services::{add_cached_value, delete_cached_value, get_cached_value},
-        organization_services::get_organization_service,
+        cache_services::{
+            add_cached_value, delete_cached_value, get_cached_value, get_cached_value_or_refresh,
+        },
+        organization_services::{get_organization_service, lock_live_organization},
     },
+    utils::generate_db_id,
 };
 
 pub async fn create_study_service(
@@ -17,22 +32,32 @@ pub async fn create_study_service(
     valkey_pool: &Pool<RedisConnectionManager>,
     new_study: &StudyCreate,
 ) -> Result<Study> {
-    let organization =
-        match get_organization_service(db_pool, valkey_pool, &new_study.organization_id, false)
-            .await
-        {
-            Ok(org) => {
-                if let Some(o) = org {
-                    o
-                } else {
-                    bail!(format!(
-                        "No organization with id {} found",
-                        &new_study.organization_id
-                    ));
-                }
+    validate_study_fields(
+        &new_study.study_id,
+        new_study.study_name.as_deref(),
+        new_study.study_description.as_deref(),
+    )?;
+
+    let organization = match get_organization_service(
+        db_pool,
+        valkey_pool,
+        &OrganizationId::from(new_study.organization_id.as_str()),
+        false,
+    )
+    .await
+    {
+        Ok(org) => {
+            if let Some(o) = org {
+                o
+            } else {
+                bail!(format!(
+                    "No organization with id {} found",
+                    &new_study.organization_id
+                ));
             }
-            Err(_) => bail!("Error retrieving organization"),
-        };
+        }
+        Err(_) => bail!("Error retrieving organization"),
+    };
 
     let prepped_study = StudyInDb::prepare_create(
         new_study.study_id.clone(),
@@ -42,6 +67,9 @@ pub async fn create_study_service(
     )
     .await?;
 
+    let mut tx = db_pool.begin().await?;
+    lock_live_organization(&mut tx, &new_study.organization_id).await?;
+
     let db_study = sqlx::query_as!(
         StudyInDb,
         r#"
@@ -51,16 +79,20 @@ pub async fn create_study_service(
                 study_name,
                 study_description,
                 organization_id,
+                locked,
+                protocol_version,
                 date_added,
                 date_modified
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             RETURNING
                 id,
                 study_id,
                 study_name,
                 study_description,
                 organization_id,
+                locked,
+                protocol_version,
                 date_added,
                 date_modified
         "#,
@@ -69,17 +101,23 @@ pub async fn create_study_service(
         prepped_study.study_name,
         prepped_study.study_description,
         prepped_study.organization_id,
+        prepped_study.locked,
+        prepped_study.protocol_version,
         prepped_study.date_added,
         prepped_study.date_modified,
     )
-    .fetch_one(db_pool)
-    .await?;
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| study_write_error(e, &new_study.study_id, &new_study.organization_id))?;
+    tx.commit().await?;
 
     let study = Study {
         id: db_study.id,
         study_id: db_study.study_id,
         study_name: db_study.study_name,
         study_description: db_study.study_description,
+        locked: db_study.locked,
+        protocol_version: db_study.protocol_version,
         organization,
     };
 
@@ -98,7 +136,7 @@ pub async fn delete_study_service(
     let result = sqlx::query!(
         r#"
             DELETE FROM studies
-            WHERE id = $1
+            WHERE id = $1 AND deleted_at IS NULL
         "#,
         study_id,
     )
@@ -107,7 +145,7 @@ pub async fn delete_study_service(
 
     if result.rows_affected() > 0 {
         tracing::debug!("Study successfully deleted from database, deleting from cache");
-        delete_cached_value(valkey_pool, "studies", study_id).await?;
+        delete_cached_value::<Study>(valkey_pool, study_id).await?;
         tracing::debug!("Study successfully deleted from cache");
         Ok(())
     } else {
@@ -123,7 +161,12 @@ pub async fn get_study_service(
 ) -> Result<Option<Study>> {
     if !skip_cache {
         tracing::debug!("Checking for study in cache");
-        let cached_study = get_cached_value(valkey_pool, "studies", study_id).await?;
+        let refresh_pools = (db_pool.clone(), valkey_pool.clone());
+        let refresh_id = study_id.to_string();
+        let cached_study = get_cached_value_or_refresh(valkey_pool, study_id, move || async move {
+            fetch_study(&refresh_pools.0, &refresh_pools.1, &refresh_id).await
+        })
+        .await?;
         if cached_study.is_some() {
             return Ok(cached_study);
         } else {
@@ -131,6 +174,21 @@ pub async fn get_study_service(
         }
     }
 
+    let study = fetch_study(db_pool, valkey_pool, study_id).await?;
+    if let Some(s) = &study {
+        tracing::debug!("Study found in database, adding to cache");
+        add_cached_value(valkey_pool, s).await?;
+        tracing::debug!("Study successfully added to cache");
+    }
+
+    Ok(study)
+}
+
+async fn fetch_study(
+    db_pool: &PgPool,
+    valkey_pool: &Pool<RedisConnectionManager>,
+    study_id: &str,
+) -> Result<Option<Study>> {
     tracing::debug!("Checking for study in database");
     let db_study = sqlx::query_as!(
         StudyInDb,
@@ -141,10 +199,12 @@ pub async fn get_study_service(
                 study_name,
                 study_description,
                 organization_id,
+                locked,
+                protocol_version,
                 date_added,
                 date_modified
             FROM studies
-            WHERE id = $1
+            WHERE id = $1 AND deleted_at IS NULL
         "#,
         study_id,
     )
@@ -152,8 +212,13 @@ pub async fn get_study_service(
     .await?;
 
     if let Some(s) = db_study {
-        let organization =
-            get_organization_service(db_pool, valkey_pool, &s.organization_id, false).await;
+        let organization = get_organization_service(
+            db_pool,
+            valkey_pool,
+            &OrganizationId::from(s.organization_id.as_str()),
+            false,
+        )
+        .await;
 
         if let Ok(org) = organization {
             if let Some(o) = org {
@@ -162,13 +227,11 @@ pub async fn get_study_service(
                     study_id: s.study_id,
                     study_name: s.study_name,
                     study_description: s.study_description,
+                    locked: s.locked,
+                    protocol_version: s.protocol_version,
                     organization: o,
                 };
 
-                tracing::debug!("Study found in database, adding to cache");
-                add_cached_value(valkey_pool, &study).await?;
-                tracing::debug!("Study successfully added to cache");
-
                 Ok(Some(study))
             } else {
                 bail!("No organization found for study");
@@ -181,10 +244,81 @@ pub async fn get_study_service(
     }
 }
 
+/// Counts the studies the list endpoint pages through, without fetching them
+pub async fn count_studies_service(db_pool: &PgPool, created: &CreatedRange) -> Result<i64> {
+    let count = sqlx::query_scalar!(
+        r#"
+            SELECT COUNT(*) AS "count!"
+            FROM studies
+            WHERE deleted_at IS NULL
+              AND ($1::timestamptz IS NULL OR date_added >= $1)
+              AND ($2::timestamptz IS NULL OR date_added < $2)
+        "#,
+        created.after(),
+        created.before(),
+    )
+    .fetch_one(db_pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// Looks a study up by its protocol `study_id`. The database id it maps to is cached separately
+/// so a hit goes through the regular study cache.
+pub async fn get_study_by_study_id_service(
+    db_pool: &PgPool,
+    valkey_pool: &Pool<RedisConnectionManager>,
+    study_id: &str,
+) -> Result<Option<Study>> {
+    tracing::debug!("Checking for study id in cache");
+    if let Some(index) = get_cached_value::<StudyIdIndex>(valkey_pool, study_id).await? {
+        if let Some(study) = get_study_service(db_pool, valkey_pool, &index.id, false).await? {
+            // The study_id can be changed by an update so the index may point at the wrong study
+            if study.study_id == study_id {
+                return Ok(Some(study));
+            }
+        }
+        tracing::debug!("Cached study id {study_id} is stale");
+    }
+
+    let id = sqlx::query_scalar!(
+        r#"
+            SELECT id
+            FROM studies
+            WHERE study_id = $1 AND deleted_at IS NULL
+        "#,
+        study_id,
+    )
+    .fetch_optional(db_pool)
+    .await?;
+
+    let Some(id) = id else {
+        return Ok(None);
+    };
+
+    let study = get_study_service(db_pool, valkey_pool, &id, true).await?;
+    if study.is_some() {
+        add_cached_value(
+            valkey_pool,
+            &StudyIdIndex {
+                study_id: study_id.to_string(),
+                id,
+            },
+        )
+        .await?;
+    }
+
+    Ok(study)
+}
+
 pub async fn get_studies_service(
     db_pool: &PgPool,
     valkey_pool: &Pool<RedisConnectionManager>,
-) -> Result<Vec<Study>> {
+    pagination: &Pagination,
+    sort: &Sort,
+    created: &CreatedRange,
+) -> Result<ListResponse<Study>> {
+    let total = count_studies_service(db_pool, created).await?;
     let db_studies = sqlx::query_as!(
         StudyInDb,
         r#"
@@ -194,10 +328,29 @@ pub async fn get_studies_service(
                 study_id,
                 study_description,
                 organization_id,
+                locked,
+                protocol_version,
                 date_added,
                 date_modified
             FROM studies
+            WHERE deleted_at IS NULL
+              AND ($5::timestamptz IS NULL OR date_added >= $5)
+              AND ($6::timestamptz IS NULL OR date_added < $6)
+            ORDER BY
+                CASE WHEN $3 = 'name' AND $4 = 'asc' THEN study_name END ASC,
+                CASE WHEN $3 = 'name' AND $4 = 'desc' THEN study_name END DESC,
+                CASE WHEN $3 = 'date_added' AND $4 = 'asc' THEN date_added END ASC,
+                CASE WHEN $3 = 'date_added' AND $4 = 'desc' THEN date_added END DESC,
+                id
+            LIMIT $1
+            OFFSET $2
         "#,
+        pagination.limit,
+        pagination.offset,
+        sort.field(),
+        sort.direction(),
+        created.after(),
+        created.before(),
     )
     .fetch_all(db_pool)
     .await?;
@@ -205,8 +358,13 @@ pub async fn get_studies_service(
     let mut studies: Vec<Study> = Vec::new();
 
     for db_study in db_studies.into_iter() {
-        let organization =
-            get_organization_service(db_pool, valkey_pool, &db_study.organization_id, false).await;
+        let organization = get_organization_service(
+            db_pool,
+            valkey_pool,
+            &OrganizationId::from(db_study.organization_id.as_str()),
+            false,
+        )
+        .await;
 
         if let Ok(org) = organization {
             if let Some(o) = org {
@@ -215,6 +373,8 @@ pub async fn get_studies_service(
                     study_id: db_study.study_id,
                     study_name: db_study.study_name,
                     study_description: db_study.study_description,
+                    locked: db_study.locked,
+                    protocol_version: db_study.protocol_version,
                     organization: o,
                 };
 
@@ -227,7 +387,37 @@ pub async fn get_studies_service(
         }
     }
 
-    Ok(studies)
+    Ok(ListResponse::new(studies, total, pagination))
+}
+
+pub async fn set_study_lock_service(
+    db_pool: &PgPool,
+    valkey_pool: &Pool<RedisConnectionManager>,
+    study_id: &str,
+    locked: bool,
+) -> Result<Study> {
+    tracing::debug!("Setting study lock to {locked} in database");
+    let result = sqlx::query!(
+        r#"
+            UPDATE studies
+            SET locked = $2, date_modified = $3
+            WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        study_id,
+        locked,
+        Utc::now(),
+    )
+    .execute(db_pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        bail!(format!("No study with the id {study_id} found"));
+    }
+
+    match get_study_service(db_pool, valkey_pool, study_id, true).await? {
+        Some(study) => Ok(study),
+        None => bail!(format!("No study with the id {study_id} found")),
+    }
 }
 
 pub async fn update_study_service(
@@ -235,21 +425,33 @@ pub async fn update_study_service(
     valkey_pool: &Pool<RedisConnectionManager>,
     updated_study: &StudyUpdate,
 ) -> Result<Study> {
-    let organization =
-        match get_organization_service(db_pool, valkey_pool, &updated_study.organization_id, false)
-            .await
-        {
-            Ok(org) => {
-                if let Some(o) = org {
-                    o
-                } else {
-                    bail!("No organization found for study");
-                }
+    validate_study_fields(
+        &updated_study.study_id,
+        updated_study.study_name.as_deref(),
+        updated_study.study_description.as_deref(),
+    )?;
+
+    let organization = match get_organization_service(
+        db_pool,
+        valkey_pool,
+        &OrganizationId::from(updated_study.organization_id.as_str()),
+        false,
+    )
+    .await
+    {
+        Ok(org) => {
+            if let Some(o) = org {
+                o
+            } else {
+                bail!("No organization found for study");
             }
-            Err(_) => bail!("Error retrieving organization"),
-        };
+        }
+        Err(_) => bail!("Error retrieving organization"),
+    };
 
     tracing::debug!("Updating study in database");
+    let mut tx = db_pool.begin().await?;
+    lock_live_organization(&mut tx, &updated_study.organization_id).await?;
     let db_study = sqlx::query_as!(
         StudyInDb,
         r#"
@@ -260,13 +462,15 @@ pub async fn update_study_service(
               study_description = $4,
               organization_id = $5,
               date_modified = $6
-            WHERE id = $1
+            WHERE id = $1 AND NOT locked AND deleted_at IS NULL
             RETURNING
                 id,
                 study_id,
                 study_name,
                 study_description,
                 organization_id,
+                locked,
+                protocol_version,
                 date_added,
                 date_modified
         "#,
@@ -277,8 +481,19 @@ pub async fn update_study_service(
         updated_study.organization_id,
         Utc::now(),
     )
-    .fetch_one(db_pool)
-    .await?;
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| study_write_error(e, &updated_study.study_id, &updated_study.organization_id))?;
+    tx.commit().await?;
+
+    // Locking is checked by the update itself so a study locked after the request arrived is
+    // never written
+    let Some(db_study) = db_study else {
+        if study_exists(db_pool, &updated_study.id).await? {
+            bail!(format!("Study {} is locked", updated_study.id));
+        }
+        return Err(sqlx::Error::RowNotFound.into());
+    };
     tracing::debug!("Successfully updated study in database");
 
     let study = Study {
@@ -286,6 +501,8 @@ pub async fn update_study_service(
         study_id: db_study.study_id,
         study_name: db_study.study_name,
         study_description: db_study.study_description,
+        locked: db_study.locked,
+        protocol_version: db_study.protocol_version,
         organization,
     };
 
@@ -294,3 +511,359 @@ pub async fn update_study_service(
 
     Ok(study)
 }
+
+/// Applies only the fields present in `patch`, so a client can clear `study_name` or
+/// `study_description` without resending the rest of the study.
+pub async fn patch_study_service(
+    db_pool: &PgPool,
+    valkey_pool: &Pool<RedisConnectionManager>,
+    id: &str,
+    patch: &StudyPatch,
+) -> Result<Study> {
+    let current = sqlx::query_as!(
+        StudyInDb,
+        r#"
+            SELECT
+                id,
+                study_id,
+                study_name,
+                study_description,
+                organization_id,
+                locked,
+                protocol_version,
+                date_added,
+                date_modified
+            FROM studies
+            WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        id,
+    )
+    .fetch_optional(db_pool)
+    .await?;
+
+    let Some(current) = current else {
+        bail!(format!("No study with the id {id} found"));
+    };
+
+    let study_name = match &patch.study_name {
+        Some(n) => n.as_deref(),
+        None => current.study_name.as_deref(),
+    };
+    let study_description = match &patch.study_description {
+        Some(d) => d.as_deref(),
+        None => current.study_description.as_deref(),
+    };
+    validate_study_fields(
+        patch.study_id.as_deref().unwrap_or(&current.study_id),
+        study_name,
+        study_description,
+    )?;
+
+    tracing::debug!("Patching study in database");
+    let db_study = sqlx::query_as!(
+        StudyInDb,
+        r#"
+            UPDATE studies
+            SET
+              study_id = COALESCE($2, study_id),
+              study_name = CASE WHEN $3 THEN $4 ELSE study_name END,
+              study_description = CASE WHEN $5 THEN $6 ELSE study_description END,
+              date_modified = $7
+            WHERE id = $1 AND NOT locked AND deleted_at IS NULL
+            RETURNING
+                id,
+                study_id,
+                study_name,
+                study_description,
+                organization_id,
+                locked,
+                protocol_version,
+                date_added,
+                date_modified
+        "#,
+        id,
+        patch.study_id,
+        patch.study_name.is_some(),
+        patch.study_name.as_ref().and_then(|n| n.as_deref()),
+        patch.study_description.is_some(),
+        patch.study_description.as_ref().and_then(|d| d.as_deref()),
+        Utc::now(),
+    )
+    .fetch_optional(db_pool)
+    .await
+    .map_err(|e| {
+        study_write_error(
+            e,
+            patch.study_id.as_deref().unwrap_or(&current.study_id),
+            &current.organization_id,
+        )
+    })?;
+
+    let Some(db_study) = db_study else {
+        if study_exists(db_pool, id).await? {
+            bail!(format!("Study {id} is locked"));
+        }
+        bail!(format!("No study with the id {id} found"));
+    };
+    tracing::debug!("Successfully patched study in database");
+
+    let Some(organization) = get_organization_service(
+        db_pool,
+        valkey_pool,
+        &OrganizationId::from(db_study.organization_id.as_str()),
+        false,
+    )
+    .await?
+    else {
+        bail!("No organization found for study");
+    };
+
+    let study = Study {
+        id: db_study.id,
+        study_id: db_study.study_id,
+        study_name: db_study.study_name,
+        study_description: db_study.study_description,
+        locked: db_study.locked,
+        protocol_version: db_study.protocol_version,
+        organization,
+    };
+
+    tracing::debug!("Adding patched study to cache");
+    add_cached_value(valkey_pool, &study).await?;
+
+    Ok(study)
+}
+
+/// Bumps the study's protocol version and records the amendment note. The version is read with
+/// `FOR UPDATE` so concurrent amendments can't produce the same version twice.
+pub async fn amend_study_service(
+    db_pool: &PgPool,
+    valkey_pool: &Pool<RedisConnectionManager>,
+    id: &str,
+    amend: &StudyAmend,
+) -> Result<Study> {
+    if amend.note.trim().is_empty() {
+        bail!("Invalid amendment: note must not be blank");
+    }
+    if amend.note.chars().count() > MAX_AMENDMENT_NOTE_LENGTH {
+        bail!(format!(
+            "Invalid amendment: note must be at most {MAX_AMENDMENT_NOTE_LENGTH} characters"
+        ));
+    }
+
+    let mut tx = db_pool.begin().await?;
+
+    let current = sqlx::query!(
+        r#"
+            SELECT locked, protocol_version
+            FROM studies
+            WHERE id = $1 AND deleted_at IS NULL
+            FOR UPDATE
+        "#,
+        id,
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(current) = current else {
+        bail!(format!("No study with the id {id} found"));
+    };
+
+    if current.locked {
+        bail!(format!("Study {id} is locked"));
+    }
+
+    let protocol_version = next_protocol_version(&current.protocol_version)?;
+    let now = Utc::now();
+
+    tracing::debug!("Amending study {id} to protocol version {protocol_version}");
+    sqlx::query!(
+        r#"
+            UPDATE studies
+            SET protocol_version = $2, date_modified = $3
+            WHERE id = $1
+        "#,
+        id,
+        protocol_version,
+        now,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO study_amendments (
+                id,
+                study_id,
+                protocol_version,
+                note,
+                date_added
+            )
+            VALUES ($1, $2, $3, $4, $5)
+        "#,
+        generate_db_id(),
+        id,
+        protocol_version,
+        amend.note,
+        now,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    tracing::debug!("Successfully amended study in database");
+
+    match get_study_service(db_pool, valkey_pool, id, true).await? {
+        Some(study) => Ok(study),
+        None => bail!(format!("No study with the id {id} found")),
+    }
+}
+
+/// Whether a study with the database id `id` exists, used to tell a locked study from a missing
+/// one when an update guarded by `NOT locked` changes nothing
+async fn study_exists(db_pool: &PgPool, id: &str) -> Result<bool> {
+    let exists = sqlx::query_scalar!(
+        r#"
+            SELECT EXISTS(
+                SELECT 1
+                FROM studies
+                WHERE id = $1 AND deleted_at IS NULL
+            ) AS "exists!"
+        "#,
+        id,
+    )
+    .fetch_one(db_pool)
+    .await?;
+
+    Ok(exists)
+}
+
+/// Amendments are a new major version of the protocol, `1.0` becomes `2.0`
+fn next_protocol_version(current: &str) -> Result<String> {
+    let major = current.split('.').next().unwrap_or_default();
+    match major.parse::<u32>() {
+        Ok(m) => Ok(format!("{}.0", m + 1)),
+        Err(_) => bail!(format!("Unrecognized protocol version {current}")),
+    }
+}
+
+/// Checks the user supplied study fields before anything is written so an unusable study can't
+/// be saved. All problems are reported together.
+fn validate_study_fields(
+    study_id: &str,
+    study_name: Option<&str>,
+    study_description: Option<&str>,
+) -> Result<()> {
+    let mut problems = Vec::new();
+
+    if study_id.trim().is_empty() {
+        problems.push("study_id must not be blank".to_string());
+    } else if study_id.chars().count() > MAX_STUDY_ID_LENGTH {
+        problems.push(format!(
+            "study_id must be at most {MAX_STUDY_ID_LENGTH} characters"
+        ));
+    }
+
+    if study_name.is_some_and(|n| n.chars().count() > MAX_STUDY_NAME_LENGTH) {
+        problems.push(format!(
+            "study_name must be at most {MAX_STUDY_NAME_LENGTH} characters"
+        ));
+    }
+
+    if study_description.is_some_and(|d| d.chars().count() > MAX_STUDY_DESCRIPTION_LENGTH) {
+        problems.push(format!(
+            "study_description must be at most {MAX_STUDY_DESCRIPTION_LENGTH} characters"
+        ));
+    }
+
+    if !problems.is_empty() {
+        bail!(format!("Invalid study: {}", problems.join(", ")));
+    }
+
+    Ok(())
+}
+
+/// Turns constraint violations from writing a study into errors the routes can report
+fn study_write_error(e: sqlx::Error, study_id: &str, organization_id: &str) -> anyhow::Error {
+    match classify_error(&e) {
+        DbErrorKind::UniqueViolation { .. } => {
+            anyhow!(format!(
+                "A study with the study id {study_id} already exists"
+            ))
+        }
+        // Deletes through the API are soft and caught by `lock_live_organization`, this covers
+        // an organization row removed outright
+        DbErrorKind::ForeignKeyViolation { .. } => {
+            anyhow!(format!("No organization with id {organization_id} found"))
+        }
+        _ => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn study_fields_valid() {
+        assert!(validate_study_fields("STUDY-1", Some("name"), Some("description")).is_ok());
+        assert!(validate_study_fields("STUDY-1", None, None).is_ok());
+    }
+
+    #[test]
+    fn study_fields_blank_study_id() {
+        let err = validate_study_fields("  ", None, None)
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("study_id must not be blank"));
+    }
+
+    #[test]
+    fn study_fields_too_long() {
+        let err = validate_study_fields(
+            &"a".repeat(MAX_STUDY_ID_LENGTH + 1),
+            Some(&"a".repeat(MAX_STUDY_NAME_LENGTH + 1)),
+            Some(&"a".repeat(MAX_STUDY_DESCRIPTION_LENGTH + 1)),
+        )
+        .unwrap_err()
+        .to_string();
+
+        assert!(err.contains("study_id must be at most"));
+        assert!(err.contains("study_name must be at most"));
+        assert!(err.contains("study_description must be at most"));
+    }
+
+    #[test]
+    fn study_fields_at_max_length() {
+        assert!(validate_study_fields(
+            &"a".repeat(MAX_STUDY_ID_LENGTH),
+            Some(&"a".repeat(MAX_STUDY_NAME_LENGTH)),
+            Some(&"a".repeat(MAX_STUDY_DESCRIPTION_LENGTH)),
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn protocol_version_bumps_major() {
+        assert_eq!(next_protocol_version("1.0").unwrap(), "2.0");
+        assert_eq!(next_protocol_version("9.3").unwrap(), "10.0");
+        assert_eq!(next_protocol_version("4").unwrap(), "5.0");
+    }
+
+    #[test]
+    fn protocol_version_unrecognized() {
+        assert!(next_protocol_version("draft").is_err());
+        assert!(next_protocol_version("").is_err());
+    }
+
+    #[test]
+    fn study_write_error_passes_other_errors() {
+        let err = study_write_error(sqlx::Error::RowNotFound, "STUDY-1", "organization");
+
+        assert!(matches!(
+            err.downcast_ref::<sqlx::Error>(),
+            Some(sqlx::Error::RowNotFound)
+        ));
+    }
+}
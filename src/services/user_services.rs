@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use anyhow::{bail, Result};
 use bb8::Pool;
 use bb8_redis::RedisConnectionManager;
@@ -5,18 +7,88 @@ use chrono::Utc;
 use sqlx::postgres::PgPool;
 
 use crate::{
+    db::{is_serialization_failure, is_unique_violation},
     models::{
+        created_range::CreatedRange,
+        organization::{Organization, OrganizationId},
+        pagination::Pagination,
+        response::ListResponse,
+        sort::Sort,
         study::{Study, StudyInDb},
         user::{AccessLevel, User, UserCreate, UserInDb, UserUpdate},
     },
     services::{
-        cache_services::{add_cached_value, delete_cached_value, get_cached_value},
-        organization_services::get_organization_service,
+        cache_services::{add_cached_value, delete_cached_value, get_cached_value_or_refresh},
+        organization_services::{get_organization_service, lock_live_organization},
         study_services::get_study_service,
     },
-    utils::{generate_db_id, hash_password},
+    utils::{generate_db_id, hash_password, normalize_email, validate_phone, PasswordHashPermits},
 };
 
+/// Attempts made to add a user to a study when the transaction hits a serialization failure
+const ADD_USER_STUDY_ATTEMPTS: u32 = 2;
+
+/// Inserts the user_studies row in a serializable transaction so concurrent adds of the same
+/// user and study settle on one winner, the other always failing with "already been added".
+async fn insert_user_study(db_pool: &PgPool, user_id: &str, study_id: &str) -> Result<()> {
+    let mut tx = db_pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL SERIALIZABLE")
+        .execute(&mut *tx)
+        .await?;
+
+    let already_added = sqlx::query_scalar!(
+        r#"
+            SELECT EXISTS(
+                SELECT 1 FROM user_studies WHERE user_id = $1 AND study_id = $2
+            ) AS "exists!"
+        "#,
+        user_id,
+        study_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if already_added {
+        bail!(format!(
+            "User {user_id} has already been added to study {study_id}"
+        ));
+    }
+
+    let inserted = sqlx::query!(
+        r#"
+            INSERT INTO user_studies (
+                id,
+                user_id,
+                study_id,
+                date_added,
+                date_modified
+            )
+            VALUES ($1, $2, $3, $4, $5)
+        "#,
+        generate_db_id(),
+        user_id,
+        study_id,
+        Utc::now(),
+        Utc::now(),
+    )
+    .execute(&mut *tx)
+    .await;
+
+    if let Err(e) = inserted {
+        let e = anyhow::Error::from(e);
+        if is_unique_violation(&e) {
+            bail!(format!(
+                "User {user_id} has already been added to study {study_id}"
+            ));
+        }
+        return Err(e);
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
 pub async fn add_user_to_study_service(
     db_pool: &PgPool,
     valkey_pool: &Pool<RedisConnectionManager>,
@@ -40,28 +112,18 @@ pub async fn add_user_to_study_service(
         bail!("Study id {study_id} not found");
     }
 
-    let db_id = generate_db_id();
-
     tracing::debug!("Adding user to study in database");
-    sqlx::query!(
-        r#"
-            INSERT INTO user_studies (
-                id,
-                user_id,
-                study_id,
-                date_added,
-                date_modified
-            )
-            VALUES ($1, $2, $3, $4, $5)
-        "#,
-        db_id,
-        user_id,
-        study_id,
-        Utc::now(),
-        Utc::now(),
-    )
-    .execute(db_pool)
-    .await?;
+    let mut attempt = 1;
+    loop {
+        match insert_user_study(db_pool, user_id, study_id).await {
+            Ok(_) => break,
+            Err(e) if attempt < ADD_USER_STUDY_ATTEMPTS && is_serialization_failure(&e) => {
+                tracing::debug!("Serialization failure adding user to study, retrying");
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
 
     if let Some(user) = get_user_service(db_pool, valkey_pool, user_id, true).await? {
         tracing::debug!("User successfully added to study in database, updating cache");
@@ -76,12 +138,13 @@ pub async fn add_user_to_study_service(
 pub async fn create_user_service(
     db_pool: &PgPool,
     valkey_pool: &Pool<RedisConnectionManager>,
+    password_hash_permits: &PasswordHashPermits,
     new_user: &UserCreate,
 ) -> Result<User> {
     let organization = match get_organization_service(
         db_pool,
         valkey_pool,
-        &new_user.organization_id,
+        &OrganizationId::from(new_user.organization_id.as_str()),
         false,
     )
     .await
@@ -96,16 +159,25 @@ pub async fn create_user_service(
         Err(_) => bail!("Error retrieving organization"),
     };
 
+    if let Some(phone) = &new_user.phone {
+        validate_phone(phone)?;
+    }
+
     let prepped_user = UserInDb::prepare_create(
         new_user.user_name.to_string(),
         new_user.first_name.to_string(),
         new_user.last_name.to_string(),
-        new_user.email.to_string(),
+        normalize_email(&new_user.email),
+        new_user.phone.clone(),
         new_user.password.to_string(),
-        organization.id.clone(),
+        organization.id.to_string(),
+        password_hash_permits,
     )
     .await?;
 
+    let mut tx = db_pool.begin().await?;
+    lock_live_organization(&mut tx, &new_user.organization_id).await?;
+
     let db_user = sqlx::query_as!(
         UserInDb,
         r#"
@@ -115,6 +187,7 @@ pub async fn create_user_service(
                 first_name,
                 last_name,
                 email,
+                phone,
                 hashed_password,
                 organization_id,
                 active,
@@ -122,13 +195,14 @@ pub async fn create_user_service(
                 date_added,
                 date_modified
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
             RETURNING
                 id,
                 user_name,
                 first_name,
                 last_name,
                 email,
+                phone,
                 hashed_password,
                 active,
                 organization_id,
@@ -141,6 +215,7 @@ pub async fn create_user_service(
         prepped_user.first_name,
         prepped_user.last_name,
         prepped_user.email,
+        prepped_user.phone,
         prepped_user.hashed_password,
         prepped_user.organization_id,
         prepped_user.active,
@@ -148,8 +223,9 @@ pub async fn create_user_service(
         prepped_user.date_added,
         prepped_user.date_modified,
     )
-    .fetch_one(db_pool)
+    .fetch_one(&mut *tx)
     .await?;
+    tx.commit().await?;
 
     tracing::debug!("User successfully saved to database");
 
@@ -160,6 +236,7 @@ pub async fn create_user_service(
         first_name: db_user.first_name,
         last_name: db_user.last_name,
         email: db_user.email,
+        phone: db_user.phone,
         organization,
         studies,
         active: db_user.active,
@@ -180,7 +257,7 @@ pub async fn delete_user_service(
     let result = sqlx::query!(
         r#"
             DELETE FROM users
-            WHERE id = $1
+            WHERE id = $1 AND deleted_at IS NULL
         "#,
         user_id,
     )
@@ -189,7 +266,7 @@ pub async fn delete_user_service(
 
     if result.rows_affected() > 0 {
         tracing::debug!("User successfully deleted from database, deleting from cache");
-        delete_cached_value(valkey_pool, "users", user_id).await?;
+        delete_cached_value::<User>(valkey_pool, user_id).await?;
         tracing::debug!("User successfully deleted from cache");
         Ok(())
     } else {
@@ -205,7 +282,12 @@ pub async fn get_user_service(
 ) -> Result<Option<User>> {
     if !skip_cache {
         tracing::debug!("Checking for user in cache");
-        let cached_user = get_cached_value(valkey_pool, "users", user_id).await?;
+        let refresh_pools = (db_pool.clone(), valkey_pool.clone());
+        let refresh_id = user_id.to_string();
+        let cached_user = get_cached_value_or_refresh(valkey_pool, user_id, move || async move {
+            fetch_user(&refresh_pools.0, &refresh_pools.1, &refresh_id).await
+        })
+        .await?;
         if cached_user.is_some() {
             return Ok(cached_user);
         } else {
@@ -213,6 +295,21 @@ pub async fn get_user_service(
         }
     }
 
+    let user = fetch_user(db_pool, valkey_pool, user_id).await?;
+    if let Some(u) = &user {
+        tracing::debug!("User found in database, adding to cache");
+        add_cached_value(valkey_pool, u).await?;
+        tracing::debug!("User successfully added to cache");
+    }
+
+    Ok(user)
+}
+
+async fn fetch_user(
+    db_pool: &PgPool,
+    valkey_pool: &Pool<RedisConnectionManager>,
+    user_id: &str,
+) -> Result<Option<User>> {
     tracing::debug!("Checking for user in database");
     let db_user = sqlx::query_as!(
         UserInDb,
@@ -223,6 +320,7 @@ pub async fn get_user_service(
                 first_name,
                 last_name,
                 email,
+                phone,
                 hashed_password,
                 organization_id,
                 active,
@@ -230,7 +328,7 @@ pub async fn get_user_service(
                 date_added,
                 date_modified
             FROM users
-            WHERE id = $1
+            WHERE id = $1 AND deleted_at IS NULL
         "#,
         user_id,
     )
@@ -238,8 +336,13 @@ pub async fn get_user_service(
     .await?;
 
     if let Some(u) = db_user {
-        let organization =
-            get_organization_service(db_pool, valkey_pool, &u.organization_id, false).await;
+        let organization = get_organization_service(
+            db_pool,
+            valkey_pool,
+            &OrganizationId::from(u.organization_id.as_str()),
+            false,
+        )
+        .await;
         let studies = get_user_studies_service(db_pool, valkey_pool, &u.id).await?;
 
         if let Ok(org) = organization {
@@ -250,17 +353,22 @@ pub async fn get_user_service(
                     first_name: u.first_name,
                     last_name: u.last_name,
                     email: u.email,
+                    phone: u.phone,
                     active: u.active,
                     organization: o,
                     studies,
                 };
 
-                tracing::debug!("User found in database, adding to cache");
-                add_cached_value(valkey_pool, &user).await?;
-                tracing::debug!("User successfully added to cache");
                 Ok(Some(user))
             } else {
-                bail!("No organization found for user");
+                // Same as the list endpoints, a user whose organization is gone, e.g. deleted
+                // mid request, is reported as not found rather than as an error
+                tracing::warn!(
+                    "Skipping user {}, organization {} not found",
+                    u.id,
+                    u.organization_id
+                );
+                Ok(None)
             }
         } else {
             bail!("An error occurred retrieving the user: organization not found");
@@ -285,10 +393,13 @@ pub async fn get_user_studies_service(
                 study_name,
                 study_description,
                 organization_id,
+                locked,
+                protocol_version,
                 date_added,
                 date_modified
             FROM studies
             WHERE id in (SELECT study_id FROM user_studies WHERE user_id = $1)
+              AND deleted_at IS NULL
         "#,
         user_id,
     )
@@ -299,7 +410,7 @@ pub async fn get_user_studies_service(
         let organization = match get_organization_service(
             db_pool,
             valkey_pool,
-            &db_studies[0].organization_id,
+            &OrganizationId::from(db_studies[0].organization_id.as_str()),
             false,
         )
         .await
@@ -320,6 +431,8 @@ pub async fn get_user_studies_service(
                 study_id: study.study_id,
                 study_name: study.study_name,
                 study_description: study.study_description,
+                locked: study.locked,
+                protocol_version: study.protocol_version,
                 organization: organization.clone(),
             };
             studies.push(s);
@@ -330,10 +443,130 @@ pub async fn get_user_studies_service(
     }
 }
 
-pub async fn get_users_service(
+pub async fn get_user_studies_page_service(
     db_pool: &PgPool,
     valkey_pool: &Pool<RedisConnectionManager>,
-) -> Result<Vec<User>> {
+    user_id: &str,
+    pagination: &Pagination,
+) -> Result<ListResponse<Study>> {
+    let user = sqlx::query_scalar!(
+        r#"
+            SELECT id
+            FROM users
+            WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        user_id,
+    )
+    .fetch_optional(db_pool)
+    .await?;
+
+    if user.is_none() {
+        bail!(format!("No user with the id {user_id} found"));
+    }
+
+    let total = sqlx::query_scalar!(
+        r#"
+            SELECT COUNT(*) AS "count!"
+            FROM user_studies
+            JOIN studies ON studies.id = user_studies.study_id
+            WHERE user_studies.user_id = $1 AND studies.deleted_at IS NULL
+        "#,
+        user_id,
+    )
+    .fetch_one(db_pool)
+    .await?;
+
+    let db_studies = sqlx::query_as!(
+        StudyInDb,
+        r#"
+            SELECT
+                studies.id,
+                studies.study_id,
+                studies.study_name,
+                studies.study_description,
+                studies.organization_id,
+                studies.locked,
+                studies.protocol_version,
+                studies.date_added,
+                studies.date_modified
+            FROM studies
+            INNER JOIN user_studies ON user_studies.study_id = studies.id
+            WHERE user_studies.user_id = $1 AND studies.deleted_at IS NULL
+            ORDER BY studies.study_id
+            LIMIT $2
+            OFFSET $3
+        "#,
+        user_id,
+        pagination.limit,
+        pagination.offset,
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let mut organizations: HashMap<String, Organization> = HashMap::new();
+    let mut studies: Vec<Study> = Vec::new();
+
+    for db_study in db_studies.into_iter() {
+        let organization = match organizations.get(&db_study.organization_id) {
+            Some(o) => o.clone(),
+            None => {
+                match get_organization_service(
+                    db_pool,
+                    valkey_pool,
+                    &OrganizationId::from(db_study.organization_id.as_str()),
+                    false,
+                )
+                .await?
+                {
+                    Some(o) => {
+                        organizations.insert(o.id.to_string(), o.clone());
+                        o
+                    }
+                    None => bail!("No organization found for study"),
+                }
+            }
+        };
+
+        studies.push(Study {
+            id: db_study.id,
+            study_id: db_study.study_id,
+            study_name: db_study.study_name,
+            study_description: db_study.study_description,
+            locked: db_study.locked,
+            protocol_version: db_study.protocol_version,
+            organization,
+        });
+    }
+
+    Ok(ListResponse::new(studies, total, pagination))
+}
+
+/// Counts the users the list endpoint pages through, without fetching them
+pub async fn count_users_service(db_pool: &PgPool, created: &CreatedRange) -> Result<i64> {
+    let count = sqlx::query_scalar!(
+        r#"
+            SELECT COUNT(*) AS "count!"
+            FROM users
+            WHERE deleted_at IS NULL
+              AND ($1::timestamptz IS NULL OR date_added >= $1)
+              AND ($2::timestamptz IS NULL OR date_added < $2)
+        "#,
+        created.after(),
+        created.before(),
+    )
+    .fetch_one(db_pool)
+    .await?;
+
+    Ok(count)
+}
+
+pub async fn get_users_service(
+    db_pool: &PgPool,
+    pagination: &Pagination,
+    sort: &Sort,
+    created: &CreatedRange,
+) -> Result<ListResponse<User>> {
+    let total = count_users_service(db_pool, created).await?;
     let db_users = sqlx::query_as!(
         UserInDb,
         r#"
@@ -343,6 +576,7 @@ pub async fn get_users_service(
                 first_name,
                 last_name,
                 email,
+                phone,
                 hashed_password,
                 organization_id,
                 active,
@@ -350,41 +584,208 @@ pub async fn get_users_service(
                 date_added,
                 date_modified
             FROM users
+            WHERE deleted_at IS NULL
+              AND ($5::timestamptz IS NULL OR date_added >= $5)
+              AND ($6::timestamptz IS NULL OR date_added < $6)
+            ORDER BY
+                CASE WHEN $3 = 'name' AND $4 = 'asc' THEN user_name END ASC,
+                CASE WHEN $3 = 'name' AND $4 = 'desc' THEN user_name END DESC,
+                CASE WHEN $3 = 'date_added' AND $4 = 'asc' THEN date_added END ASC,
+                CASE WHEN $3 = 'date_added' AND $4 = 'desc' THEN date_added END DESC,
+                id
+            LIMIT $1
+            OFFSET $2
         "#,
+        pagination.limit,
+        pagination.offset,
+        sort.field(),
+        sort.direction(),
+        created.after(),
+        created.before(),
     )
     .fetch_all(db_pool)
     .await?;
+    let users = users_with_studies(db_pool, db_users).await?;
 
+    Ok(ListResponse::new(users, total, pagination))
+}
+
+/// Users of the organization `organization_id` ordered by user name
+pub async fn get_organization_users_service(
+    db_pool: &PgPool,
+    organization_id: &OrganizationId,
+    pagination: &Pagination,
+) -> Result<ListResponse<User>> {
+    let organization = sqlx::query_scalar!(
+        r#"
+            SELECT id
+            FROM organizations
+            WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        organization_id.as_str(),
+    )
+    .fetch_optional(db_pool)
+    .await?;
+
+    if organization.is_none() {
+        bail!(format!(
+            "No organization with the id {organization_id} found"
+        ));
+    }
+
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM users WHERE organization_id = $1 AND deleted_at IS NULL"#,
+        organization_id.as_str(),
+    )
+    .fetch_one(db_pool)
+    .await?;
+
+    let db_users = sqlx::query_as!(
+        UserInDb,
+        r#"
+            SELECT
+                id,
+                user_name,
+                first_name,
+                last_name,
+                email,
+                phone,
+                hashed_password,
+                organization_id,
+                active,
+                access_level AS "access_level: AccessLevel",
+                date_added,
+                date_modified
+            FROM users
+            WHERE organization_id = $1 AND deleted_at IS NULL
+            ORDER BY user_name, id
+            LIMIT $2
+            OFFSET $3
+        "#,
+        organization_id.as_str(),
+        pagination.limit,
+        pagination.offset,
+    )
+    .fetch_all(db_pool)
+    .await?;
+    let users = users_with_studies(db_pool, db_users).await?;
+
+    Ok(ListResponse::new(users, total, pagination))
+}
+
+/// Loads the studies and organizations for a page of database users in two queries, rather than
+/// a couple per user, and builds the API users from them
+pub async fn users_with_studies(db_pool: &PgPool, db_users: Vec<UserInDb>) -> Result<Vec<User>> {
+    let user_ids: Vec<String> = db_users.iter().map(|u| u.id.clone()).collect();
+    let db_user_studies = sqlx::query!(
+        r#"
+            SELECT
+                user_studies.user_id,
+                studies.id,
+                studies.study_id,
+                studies.study_name,
+                studies.study_description,
+                studies.organization_id,
+                studies.locked,
+                studies.protocol_version
+            FROM user_studies
+            INNER JOIN studies ON studies.id = user_studies.study_id
+            WHERE user_studies.user_id = ANY($1) AND studies.deleted_at IS NULL
+        "#,
+        &user_ids[..],
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    let mut organization_ids: Vec<String> = db_users
+        .iter()
+        .map(|u| u.organization_id.clone())
+        .chain(db_user_studies.iter().map(|s| s.organization_id.clone()))
+        .collect();
+    organization_ids.sort();
+    organization_ids.dedup();
+
+    let organizations: HashMap<String, Organization> = sqlx::query_as!(
+        Organization,
+        r#"
+            SELECT id AS "id: OrganizationId", name, active, date_added, date_modified
+            FROM organizations
+            WHERE id = ANY($1) AND deleted_at IS NULL
+        "#,
+        &organization_ids[..],
+    )
+    .fetch_all(db_pool)
+    .await?
+    .into_iter()
+    .map(|o| (o.id.to_string(), o))
+    .collect();
+
+    let mut user_studies: HashMap<String, Vec<Study>> = HashMap::new();
+    for db_study in db_user_studies.into_iter() {
+        let Some(organization) = organizations.get(&db_study.organization_id).cloned() else {
+            tracing::warn!(
+                "Skipping study {} for user {}, organization {} not found",
+                db_study.id,
+                db_study.user_id,
+                db_study.organization_id
+            );
+            continue;
+        };
+        let study = Study {
+            id: db_study.id,
+            study_id: db_study.study_id,
+            study_name: db_study.study_name,
+            study_description: db_study.study_description,
+            locked: db_study.locked,
+            protocol_version: db_study.protocol_version,
+            organization,
+        };
+        user_studies
+            .entry(db_study.user_id)
+            .or_default()
+            .push(study);
+    }
+
+    Ok(assemble_users(db_users, &organizations, user_studies))
+}
+
+/// Builds API users from database rows. A user whose organization can't be found, e.g. because
+/// it was deleted mid request, is left out with a warning so one bad row doesn't fail the whole
+/// list.
+fn assemble_users(
+    db_users: Vec<UserInDb>,
+    organizations: &HashMap<String, Organization>,
+    mut user_studies: HashMap<String, Vec<Study>>,
+) -> Vec<User> {
     let mut users: Vec<User> = Vec::new();
 
     for db_user in db_users.into_iter() {
-        let organization =
-            get_organization_service(db_pool, valkey_pool, &db_user.organization_id, false).await;
-        let studies = get_user_studies_service(db_pool, valkey_pool, &db_user.id).await?;
+        let Some(organization) = organizations.get(&db_user.organization_id).cloned() else {
+            tracing::warn!(
+                "Skipping user {}, organization {} not found",
+                db_user.id,
+                db_user.organization_id
+            );
+            continue;
+        };
+        let studies = user_studies.remove(&db_user.id);
 
-        if let Ok(org) = organization {
-            if let Some(o) = org {
-                let user = User {
-                    id: db_user.id,
-                    user_name: db_user.user_name,
-                    first_name: db_user.first_name,
-                    last_name: db_user.last_name,
-                    email: db_user.email,
-                    active: db_user.active,
-                    organization: o,
-                    studies,
-                };
+        let user = User {
+            id: db_user.id,
+            user_name: db_user.user_name,
+            first_name: db_user.first_name,
+            last_name: db_user.last_name,
+            email: db_user.email,
+            phone: db_user.phone,
+            active: db_user.active,
+            organization,
+            studies,
+        };
 
-                users.push(user);
-            } else {
-                bail!("No organization found for user");
-            }
-        } else {
-            bail!("An error occurred retrieving the user: organization not found");
-        }
+        users.push(user);
     }
 
-    Ok(users)
+    users
 }
 
 pub async fn remove_user_from_study_service(
@@ -427,30 +828,224 @@ pub async fn remove_user_from_study_service(
     }
 }
 
+/// Removes every study link for a user in one statement and returns the ids of the studies the
+/// user was removed from.
+pub async fn remove_user_from_all_studies_service(
+    db_pool: &PgPool,
+    valkey_pool: &Pool<RedisConnectionManager>,
+    user_id: &str,
+) -> Result<Vec<String>> {
+    if get_user_service(db_pool, valkey_pool, user_id, true)
+        .await?
+        .is_none()
+    {
+        bail!(format!("No user with the id {user_id} found"));
+    }
+
+    tracing::debug!("Removing user from all studies in database");
+    let study_ids = sqlx::query_scalar!(
+        r#"
+            DELETE FROM user_studies
+            WHERE user_id = $1
+            RETURNING study_id
+        "#,
+        user_id,
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    tracing::debug!(
+        "Removed user from {} studies, updating cache",
+        study_ids.len()
+    );
+    match get_user_service(db_pool, valkey_pool, user_id, true).await {
+        Ok(Some(u)) => add_cached_value(valkey_pool, &u).await?,
+        Ok(None) => tracing::debug!("Error updating cache, user not found"),
+        Err(e) => {
+            tracing::error!("Error adding user to cache: {}", e.to_string());
+        }
+    }
+
+    Ok(study_ids)
+}
+
+/// Moves every study assignment of `user_id` to `target_user_id` in one transaction, returning
+/// the ids of the studies moved. Both users must belong to the organization of each study.
+/// Studies the target is already part of are only removed from the source user.
+pub async fn transfer_user_studies_service(
+    db_pool: &PgPool,
+    valkey_pool: &Pool<RedisConnectionManager>,
+    user_id: &str,
+    target_user_id: &str,
+) -> Result<Vec<String>> {
+    if user_id == target_user_id {
+        bail!("Invalid transfer: the target user is the user being transferred from");
+    }
+
+    let mut tx = db_pool.begin().await?;
+
+    // Lock both users so neither can move organization or be deleted part way through
+    let users = sqlx::query!(
+        r#"
+            SELECT id, organization_id
+            FROM users
+            WHERE (id = $1 OR id = $2) AND deleted_at IS NULL
+            FOR UPDATE
+        "#,
+        user_id,
+        target_user_id,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let organization_of = |id: &str| {
+        users
+            .iter()
+            .find(|u| u.id == id)
+            .map(|u| u.organization_id.clone())
+    };
+    let Some(source_org) = organization_of(user_id) else {
+        bail!(format!("No user with the id {user_id} found"));
+    };
+    let Some(target_org) = organization_of(target_user_id) else {
+        bail!(format!("No user with the id {target_user_id} found"));
+    };
+
+    let studies = sqlx::query!(
+        r#"
+            SELECT user_studies.study_id, studies.organization_id
+            FROM user_studies
+            JOIN studies ON studies.id = user_studies.study_id
+            WHERE user_studies.user_id = $1 AND studies.deleted_at IS NULL
+            FOR UPDATE OF user_studies
+        "#,
+        user_id,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for study in studies.iter() {
+        if study.organization_id != source_org || study.organization_id != target_org {
+            bail!(format!(
+                "Invalid transfer: users {user_id} and {target_user_id} are not both in the organization of study {}",
+                study.study_id
+            ));
+        }
+    }
+
+    tracing::debug!(
+        "Transferring {} studies from user {user_id} to user {target_user_id} in database",
+        studies.len()
+    );
+    sqlx::query!(
+        r#"
+            UPDATE user_studies
+            SET user_id = $2, date_modified = $3
+            WHERE user_id = $1
+              AND study_id NOT IN (
+                SELECT study_id FROM user_studies WHERE user_id = $2
+              )
+        "#,
+        user_id,
+        target_user_id,
+        Utc::now(),
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            DELETE FROM user_studies
+            WHERE user_id = $1
+        "#,
+        user_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    tracing::debug!("Studies transferred, updating cache for both users");
+    for id in [user_id, target_user_id] {
+        if let Err(e) = get_user_service(db_pool, valkey_pool, id, true).await {
+            tracing::error!("Error updating cache for user {id}: {}", e.to_string());
+        }
+    }
+
+    Ok(studies.into_iter().map(|s| s.study_id).collect())
+}
+
+pub async fn set_user_active_service(
+    db_pool: &PgPool,
+    valkey_pool: &Pool<RedisConnectionManager>,
+    user_id: &str,
+    active: bool,
+) -> Result<User> {
+    tracing::debug!("Setting user active to {active} in database");
+    let result = sqlx::query!(
+        r#"
+            UPDATE users
+            SET active = $2, date_modified = $3
+            WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        user_id,
+        active,
+        Utc::now(),
+    )
+    .execute(db_pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        bail!(format!("No user with the id {user_id} found"));
+    }
+
+    match get_user_service(db_pool, valkey_pool, user_id, true).await? {
+        Some(user) => Ok(user),
+        None => bail!(format!("No user with the id {user_id} found")),
+    }
+}
+
 pub async fn update_user_service(
     db_pool: &PgPool,
     valkey_pool: &Pool<RedisConnectionManager>,
+    password_hash_permits: &PasswordHashPermits,
     updated_user: &UserUpdate,
 ) -> Result<User> {
-    let organization =
-        match get_organization_service(db_pool, valkey_pool, &updated_user.organization_id, false)
-            .await
-        {
-            Ok(org) => {
-                if let Some(o) = org {
-                    o
-                } else {
-                    bail!("No organization found for user");
-                }
+    let organization = match get_organization_service(
+        db_pool,
+        valkey_pool,
+        &OrganizationId::from(updated_user.organization_id.as_str()),
+        false,
+    )
+    .await
+    {
+        Ok(org) => {
+            if let Some(o) = org {
+                o
+            } else {
+                bail!("No organization found for user");
             }
-            Err(_) => bail!("Error retrieving organization"),
-        };
+        }
+        Err(_) => bail!("Error retrieving organization"),
+    };
 
     let studies = get_user_studies_service(db_pool, valkey_pool, &updated_user.id).await?;
 
+    let email = normalize_email(&updated_user.email);
+
+    if let Some(phone) = &updated_user.phone {
+        validate_phone(phone)?;
+    }
+
+    // Hashed before the transaction so the organization isn't held locked while it runs
+    let hashed_password = match &updated_user.password {
+        Some(password) => Some(hash_password(password_hash_permits, password).await?),
+        None => None,
+    };
+
     tracing::debug!("Updating user in database");
-    let db_user = if let Some(password) = &updated_user.password {
-        let hashed_password = hash_password(password).await?;
+    let mut tx = db_pool.begin().await?;
+    lock_live_organization(&mut tx, &updated_user.organization_id).await?;
+    let db_user = if let Some(hashed_password) = hashed_password {
         sqlx::query_as!(
             UserInDb,
             r#"
@@ -460,17 +1055,19 @@ pub async fn update_user_service(
                   first_name = $3,
                   last_name = $4,
                   email = $5,
-                  hashed_password = $6,
-                  active = $7,
-                  organization_id = $8,
-                  date_modified = $9
-                WHERE id = $1
+                  phone = $6,
+                  hashed_password = $7,
+                  active = $8,
+                  organization_id = $9,
+                  date_modified = $10
+                WHERE id = $1 AND deleted_at IS NULL
                 RETURNING
                     id,
                     user_name,
                     first_name,
                     last_name,
                     email,
+                    phone,
                     hashed_password,
                     organization_id,
                     active,
@@ -482,13 +1079,14 @@ pub async fn update_user_service(
             updated_user.user_name,
             updated_user.first_name,
             updated_user.last_name,
-            updated_user.email,
+            email,
+            updated_user.phone,
             hashed_password,
             updated_user.active,
             updated_user.organization_id,
             Utc::now(),
         )
-        .fetch_one(db_pool)
+        .fetch_one(&mut *tx)
         .await?
     } else {
         sqlx::query_as!(
@@ -500,16 +1098,18 @@ pub async fn update_user_service(
                   first_name = $3,
                   last_name = $4,
                   email = $5,
-                  active = $6,
-                  organization_id = $7,
-                  date_modified = $8
-                WHERE id = $1
+                  phone = $6,
+                  active = $7,
+                  organization_id = $8,
+                  date_modified = $9
+                WHERE id = $1 AND deleted_at IS NULL
                 RETURNING
                     id,
                     user_name,
                     first_name,
                     last_name,
                     email,
+                    phone,
                     hashed_password,
                     organization_id,
                     active,
@@ -521,14 +1121,16 @@ pub async fn update_user_service(
             updated_user.user_name,
             updated_user.first_name,
             updated_user.last_name,
-            updated_user.email,
+            email,
+            updated_user.phone,
             updated_user.active,
             updated_user.organization_id,
             Utc::now(),
         )
-        .fetch_one(db_pool)
+        .fetch_one(&mut *tx)
         .await?
     };
+    tx.commit().await?;
     tracing::debug!("Successfully updated user in database");
 
     let user = User {
@@ -537,6 +1139,7 @@ pub async fn update_user_service(
         first_name: db_user.first_name,
         last_name: db_user.last_name,
         email: db_user.email,
+        phone: db_user.phone,
         organization,
         studies,
         active: db_user.active,
@@ -547,3 +1150,44 @@ pub async fn update_user_service(
 
     Ok(user)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::models::organization::OrganizationInDb;
+
+    async fn db_user(organization_id: &str) -> UserInDb {
+        UserInDb::prepare_create(
+            generate_db_id(),
+            "Arthur".to_string(),
+            "Dent".to_string(),
+            "arthur@heartofgold.com".to_string(),
+            None,
+            "Somepassword1!".to_string(),
+            organization_id.to_string(),
+            &PasswordHashPermits::default(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn assemble_users_skips_orphans() {
+        let organization: Organization = OrganizationInDb::prepare_create("org".to_string())
+            .await
+            .unwrap()
+            .into();
+        let organizations = HashMap::from([(organization.id.to_string(), organization.clone())]);
+        let healthy = db_user(organization.id.as_str()).await;
+        let orphan = db_user(&generate_db_id()).await;
+        let healthy_id = healthy.id.clone();
+        let db_users = vec![healthy, orphan, db_user(organization.id.as_str()).await];
+
+        let users = assemble_users(db_users, &organizations, HashMap::new());
+
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].id, healthy_id);
+        assert!(users.iter().all(|u| u.organization.id == organization.id));
+    }
+}
@@ -0,0 +1,511 @@
+use std::{
+    error::Error,
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::postgres::PgPool;
+use tokio::net::lookup_host;
+
+use crate::models::webhook::{Webhook, WebhookCreate, WebhookEvent, WebhookInDb, WebhookPayload};
+
+/// Header carrying the hex encoded HMAC-SHA256 of the body, prefixed with `sha256=`
+pub const SIGNATURE_HEADER: &str = "X-Open-EDC-Signature";
+
+const MAX_DELIVERY_ATTEMPTS: u32 = 3;
+
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Sends a signed payload to a subscriber. Kept behind a trait so tests can capture deliveries
+/// instead of making network calls.
+pub trait WebhookClient: Send + Sync {
+    fn send<'a>(
+        &'a self,
+        url: &'a str,
+        body: &'a [u8],
+        signature: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+}
+
+pub struct HttpWebhookClient {
+    client: reqwest::Client,
+}
+
+impl HttpWebhookClient {
+    /// Redirects aren't followed and hosts only resolve to public addresses, so a subscriber
+    /// can't steer deliveries into the server's own network
+    pub fn new() -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()?;
+
+        Ok(Self { client })
+    }
+}
+
+/// Resolves webhook hosts, dropping private, loopback and link-local addresses. Checking again at
+/// delivery means a name that was public when the webhook was registered can't later be pointed
+/// at an internal address.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(resolve_public(name))
+    }
+}
+
+async fn resolve_public(name: Name) -> Result<Addrs, Box<dyn Error + Send + Sync>> {
+    let addresses: Vec<SocketAddr> = lookup_host((name.as_str(), 0))
+        .await?
+        .filter(|a| is_public_address(a.ip()))
+        .collect();
+
+    if addresses.is_empty() {
+        return Err(format!("{} has no public addresses", name.as_str()).into());
+    }
+
+    Ok(Box::new(addresses.into_iter()))
+}
+
+impl WebhookClient for HttpWebhookClient {
+    fn send<'a>(
+        &'a self,
+        url: &'a str,
+        body: &'a [u8],
+        signature: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(async move {
+            check_webhook_url(url).await?;
+            let response = self
+                .client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(SIGNATURE_HEADER, signature)
+                .body(body.to_vec())
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                bail!(format!(
+                    "Webhook {url} responded with {}",
+                    response.status()
+                ));
+            }
+
+            Ok(())
+        })
+    }
+}
+
+pub async fn create_webhook_service(
+    db_pool: &PgPool,
+    new_webhook: &WebhookCreate,
+) -> Result<Webhook> {
+    check_webhook_url(&new_webhook.url).await?;
+
+    if new_webhook.event_types.is_empty() {
+        bail!("Invalid webhook, at least one event type is required");
+    }
+
+    if new_webhook.secret.is_empty() {
+        bail!("Invalid webhook, secret must not be empty");
+    }
+
+    let prepped_webhook = WebhookInDb::prepare_create(
+        new_webhook.url.clone(),
+        new_webhook.secret.clone(),
+        &new_webhook.event_types,
+    );
+
+    let db_webhook = sqlx::query_as!(
+        WebhookInDb,
+        r#"
+            INSERT INTO webhooks (
+                id,
+                url,
+                secret,
+                event_types,
+                date_added,
+                date_modified
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING
+                id,
+                url,
+                secret,
+                event_types,
+                date_added,
+                date_modified
+        "#,
+        prepped_webhook.id,
+        prepped_webhook.url,
+        prepped_webhook.secret,
+        &prepped_webhook.event_types[..],
+        prepped_webhook.date_added,
+        prepped_webhook.date_modified,
+    )
+    .fetch_one(db_pool)
+    .await?;
+
+    Ok(db_webhook.into())
+}
+
+/// Fails unless `url` is http or https and every address its host resolves to is public
+pub async fn check_webhook_url(url: &str) -> Result<()> {
+    let parsed = match reqwest::Url::parse(url) {
+        Ok(p) if matches!(p.scheme(), "http" | "https") => p,
+        _ => bail!(format!("Invalid webhook url {url}, must be http or https")),
+    };
+    let Some(host) = parsed.host_str() else {
+        bail!(format!("Invalid webhook url {url}, a host is required"));
+    };
+
+    let addresses: Vec<IpAddr> = match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => {
+            let port = parsed.port_or_known_default().unwrap_or(80);
+            match lookup_host((host, port)).await {
+                Ok(a) => a.map(|a| a.ip()).collect(),
+                Err(e) => bail!(format!(
+                    "Invalid webhook url {url}, {host} could not be resolved: {e}"
+                )),
+            }
+        }
+    };
+
+    if addresses.is_empty() || !addresses.iter().all(|a| is_public_address(*a)) {
+        bail!(format!(
+            "Invalid webhook url {url}, must not point at a private, loopback or link-local address"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Whether `ip` is reachable on the public internet rather than inside the server's network
+fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            // 100.64.0.0/10, shared address space used inside carrier and cloud networks
+            let shared = ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64;
+
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || shared)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public_address(mapped.into()),
+            None => {
+                let unique_local = (ip.segments()[0] & 0xfe00) == 0xfc00;
+                let link_local = (ip.segments()[0] & 0xffc0) == 0xfe80;
+
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || unique_local
+                    || link_local)
+            }
+        },
+    }
+}
+
+pub async fn delete_webhook_service(db_pool: &PgPool, id: &str) -> Result<()> {
+    let result = sqlx::query!(
+        r#"
+            DELETE FROM webhooks
+            WHERE id = $1
+        "#,
+        id,
+    )
+    .execute(db_pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        bail!(format!("No webhook with the id {id} found"));
+    }
+
+    Ok(())
+}
+
+async fn get_webhooks_for_event_service(
+    db_pool: &PgPool,
+    event: WebhookEvent,
+) -> Result<Vec<WebhookInDb>> {
+    let webhooks = sqlx::query_as!(
+        WebhookInDb,
+        r#"
+            SELECT
+                id,
+                url,
+                secret,
+                event_types,
+                date_added,
+                date_modified
+            FROM webhooks
+            WHERE $1 = ANY(event_types)
+        "#,
+        event.as_str(),
+    )
+    .fetch_all(db_pool)
+    .await?;
+
+    Ok(webhooks)
+}
+
+/// Notifies every webhook subscribed to `event` in a background task so the request that made
+/// the change isn't held up. Call it only after the change has been committed.
+pub fn dispatch_event<T: Serialize>(
+    db_pool: PgPool,
+    client: Arc<dyn WebhookClient>,
+    event: WebhookEvent,
+    data: &T,
+) {
+    let payload = WebhookPayload {
+        event,
+        timestamp: Utc::now(),
+        data,
+    };
+    let body = match serde_json::to_vec(&payload) {
+        Ok(b) => b,
+        Err(e) => {
+            tracing::error!(
+                "Error serializing {} webhook payload: {}",
+                event.as_str(),
+                e
+            );
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        let webhooks = match get_webhooks_for_event_service(&db_pool, event).await {
+            Ok(w) => w,
+            Err(e) => {
+                tracing::error!("Error retrieving webhooks for {}: {}", event.as_str(), e);
+                return;
+            }
+        };
+
+        for webhook in webhooks {
+            let client = client.clone();
+            let body = body.clone();
+            tokio::spawn(async move {
+                deliver(
+                    client.as_ref(),
+                    &webhook.url,
+                    &webhook.secret,
+                    &body,
+                    RETRY_BASE_DELAY,
+                )
+                .await;
+            });
+        }
+    });
+}
+
+/// Delivers a payload, retrying with exponential backoff from `base_delay`. Returns whether it
+/// was accepted.
+async fn deliver(
+    client: &dyn WebhookClient,
+    url: &str,
+    secret: &str,
+    body: &[u8],
+    base_delay: Duration,
+) -> bool {
+    let signature = sign_payload(secret, body);
+
+    for attempt in 0..MAX_DELIVERY_ATTEMPTS {
+        match client.send(url, body, &signature).await {
+            Ok(_) => {
+                tracing::debug!("Delivered webhook to {url}");
+                return true;
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Webhook delivery to {url} failed on attempt {}: {}",
+                    attempt + 1,
+                    e
+                );
+                if attempt + 1 < MAX_DELIVERY_ATTEMPTS {
+                    tokio::time::sleep(base_delay * 2u32.pow(attempt)).await;
+                }
+            }
+        }
+    }
+
+    tracing::error!("Giving up on webhook delivery to {url}");
+    false
+}
+
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body);
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    };
+
+    /// Captures deliveries in memory, failing the first `failures` attempts
+    #[derive(Default)]
+    pub struct RecordingWebhookClient {
+        pub deliveries: Mutex<Vec<(String, Vec<u8>, String)>>,
+        failures: AtomicU32,
+    }
+
+    impl WebhookClient for RecordingWebhookClient {
+        fn send<'a>(
+            &'a self,
+            url: &'a str,
+            body: &'a [u8],
+            signature: &'a str,
+        ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>> {
+            Box::pin(async move {
+                if self.failures.load(Ordering::Relaxed) > 0 {
+                    self.failures.fetch_sub(1, Ordering::Relaxed);
+                    bail!("Simulated failure");
+                }
+                self.deliveries.lock().unwrap().push((
+                    url.to_string(),
+                    body.to_vec(),
+                    signature.to_string(),
+                ));
+
+                Ok(())
+            })
+        }
+    }
+
+    #[test]
+    fn public_addresses() {
+        for ip in ["203.0.113.10", "8.8.8.8", "2001:4860:4860::8888"] {
+            assert!(is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn check_webhook_url_rejects_internal_targets() {
+        assert!(check_webhook_url("https://203.0.113.10/hook").await.is_ok());
+
+        for url in [
+            "ftp://203.0.113.10",
+            "http://localhost:3000/hook",
+            "http://127.0.0.1/hook",
+            "http://[::1]/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://10.0.0.5:8080",
+        ] {
+            let err = check_webhook_url(url).await.unwrap_err().to_string();
+            assert!(err.starts_with("Invalid webhook url"), "{url}: {err}");
+        }
+    }
+
+    #[test]
+    fn sign_payload_known_value() {
+        // Reference value from RFC 4231 test case 2
+        let signature = sign_payload("Jefe", b"what do ya want for nothing?");
+
+        assert_eq!(
+            signature,
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn deliver_signs_payload() {
+        let client = RecordingWebhookClient::default();
+        let delivered = deliver(
+            &client,
+            "http://example.com",
+            "secret",
+            b"{}",
+            Duration::ZERO,
+        )
+        .await;
+        let deliveries = client.deliveries.lock().unwrap();
+
+        assert!(delivered);
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].0, "http://example.com");
+        assert_eq!(deliveries[0].1, b"{}");
+        assert_eq!(deliveries[0].2, sign_payload("secret", b"{}"));
+    }
+
+    #[tokio::test]
+    async fn deliver_retries() {
+        let client = RecordingWebhookClient {
+            failures: AtomicU32::new(MAX_DELIVERY_ATTEMPTS - 1),
+            ..Default::default()
+        };
+
+        assert!(
+            deliver(
+                &client,
+                "http://example.com",
+                "secret",
+                b"{}",
+                Duration::ZERO,
+            )
+            .await
+        );
+        assert_eq!(client.deliveries.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn deliver_gives_up() {
+        let client = RecordingWebhookClient {
+            failures: AtomicU32::new(MAX_DELIVERY_ATTEMPTS),
+            ..Default::default()
+        };
+
+        assert!(
+            !deliver(
+                &client,
+                "http://example.com",
+                "secret",
+                b"{}",
+                Duration::ZERO,
+            )
+            .await
+        );
+        assert!(client.deliveries.lock().unwrap().is_empty());
+    }
+}
@@ -1,14 +1,33 @@
+use std::sync::{atomic::AtomicBool, Arc};
+
 use anyhow::{bail, Result};
 use axum::extract::FromRef;
 use bb8::Pool;
 use bb8_redis::RedisConnectionManager;
 use sqlx::postgres::PgPool;
 
-use crate::{config::Config, db::DbClient};
+use crate::{
+    config::Config,
+    db::DbClient,
+    services::{
+        activity_services::ActivityBus,
+        organization_services::Organizations,
+        webhook_services::{HttpWebhookClient, WebhookClient},
+    },
+    utils::PasswordHashPermits,
+};
 
 #[derive(Clone)]
 pub struct DbState {
     pub pool: PgPool,
+
+    /// Pool for read only queries. Points at the read replica when one is configured, otherwise
+    /// it is the primary pool. Reads that fill the cache use the primary instead, so a lagging
+    /// replica can't put stale rows in it.
+    pub read_pool: PgPool,
+
+    /// Result of the most recent background keepalive ping
+    pub healthy: Arc<AtomicBool>,
 }
 
 impl FromRef<AppState> for DbState {
@@ -26,7 +45,14 @@ impl DbState {
         let port = &config.database_port;
         let db_client = DbClient::new(address, user, user_password, port, "open_edc");
 
-        let pool = match db_client.create_pool(None, None).await {
+        let pool = match db_client
+            .with_statement_timeout(config.database_statement_timeout_ms)
+            .with_slow_query_threshold(config.slow_query_ms)
+            .with_idle_timeout(config.database_idle_timeout_secs)
+            .with_max_lifetime(config.database_max_lifetime_secs)
+            .create_pool(None, None)
+            .await
+        {
             Ok(p) => p,
             Err(e) => bail!("Unable to connect to the database: {}", e.to_string()),
         };
@@ -36,7 +62,31 @@ impl DbState {
             Err(_) => bail!("Error connecting to Postgres server"),
         };
 
-        let state = Self { pool: pool.clone() };
+        let read_pool = if let Some(replica_uri) = &config.database_replica_url {
+            tracing::debug!("Connecting to postgres read replica");
+            match DbClient::from_uri(replica_uri)
+                .with_statement_timeout(config.database_statement_timeout_ms)
+                .with_slow_query_threshold(config.slow_query_ms)
+                .with_idle_timeout(config.database_idle_timeout_secs)
+                .with_max_lifetime(config.database_max_lifetime_secs)
+                .create_pool(None, None)
+                .await
+            {
+                Ok(p) => p,
+                Err(e) => bail!(
+                    "Unable to connect to the database read replica: {}",
+                    e.to_string()
+                ),
+            }
+        } else {
+            pool.clone()
+        };
+
+        let state = Self {
+            pool: pool.clone(),
+            read_pool,
+            healthy: Arc::new(AtomicBool::new(true)),
+        };
 
         Ok(state)
     }
@@ -92,8 +142,18 @@ impl ValkeyState {
 
 #[derive(Clone)]
 pub struct AppState {
+    pub config: Config,
     pub db_state: DbState,
     pub valkey_state: ValkeyState,
+
+    /// Client used to deliver webhook events
+    pub webhook_client: Arc<dyn WebhookClient>,
+
+    /// Live study activity for SSE subscribers
+    pub activity: ActivityBus,
+
+    /// Bounds how many password hashes run at once, sized by `password_hash_concurrency`
+    pub password_hash_permits: PasswordHashPermits,
 }
 
 impl AppState {
@@ -118,9 +178,49 @@ impl AppState {
         };
         tracing::debug!("Successfully created valkey_state");
 
+        let webhook_client = match HttpWebhookClient::new() {
+            Ok(c) => c,
+            Err(e) => bail!("Error creating webhook client: {}", e.to_string()),
+        };
+
         Ok(Self {
+            config: config.clone(),
             db_state,
             valkey_state,
+            webhook_client: Arc::new(webhook_client),
+            activity: ActivityBus::default(),
+            password_hash_permits: PasswordHashPermits::new(
+                config.password_hash_concurrency.into(),
+            ),
         })
     }
+
+    pub fn organizations(&self) -> Organizations<'_> {
+        Organizations::new(
+            &self.db_state.pool,
+            &self.db_state.read_pool,
+            &self.valkey_state.pool,
+            &self.password_hash_permits,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dotenvy::dotenv;
+
+    #[tokio::test]
+    async fn read_pool_defaults_to_primary() {
+        dotenv().ok();
+        let mut config = Config::new();
+        config.database_replica_url = None;
+        let db_state = DbState::create_state(&config).await.unwrap();
+        let primary = db_state.pool.connect_options();
+        let read = db_state.read_pool.connect_options();
+
+        assert_eq!(read.get_host(), primary.get_host());
+        assert_eq!(read.get_port(), primary.get_port());
+        assert_eq!(read.get_database(), primary.get_database());
+    }
 }
@@ -0,0 +1,243 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use anyhow::{bail, Result};
+use axum::{
+    async_trait,
+    extract::{FromRequest, Request},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use serde_json::Value;
+
+use crate::{models::messages::GenericMessage, state::AppState};
+
+/// Request bodies that can be checked for fields they don't declare
+pub trait KnownFields {
+    /// JSON keys the body accepts
+    const FIELDS: &'static [&'static str];
+
+    /// Keys in `value` that aren't in `FIELDS`. Bodies with nested objects override this to check
+    /// those too.
+    fn unknown_fields(value: &Value) -> Vec<String> {
+        unknown_keys(value, Self::FIELDS, "")
+    }
+}
+
+/// Keys of the `value` object missing from `fields`, each prefixed with `prefix`
+pub fn unknown_keys(value: &Value, fields: &[&str], prefix: &str) -> Vec<String> {
+    value
+        .as_object()
+        .map(|o| {
+            o.keys()
+                .filter(|k| !fields.contains(&k.as_str()))
+                .map(|k| format!("{prefix}{k}"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Fails listing every key in `value` that `T` would otherwise silently drop
+pub fn reject_unknown_fields<T: KnownFields>(value: &Value) -> Result<()> {
+    let unknown = T::unknown_fields(value);
+    if !unknown.is_empty() {
+        bail!(format!("Unknown fields: {}", unknown.join(", ")));
+    }
+
+    Ok(())
+}
+
+/// Body left as JSON so the handler can fill in fields, like an id taken from the path, before
+/// deserializing it as `T`. Extracted with `StrictJson` its keys are checked against `T`'s.
+pub struct JsonFields<T>(pub Value, PhantomData<fn() -> T>);
+
+impl<T: KnownFields> KnownFields for JsonFields<T> {
+    const FIELDS: &'static [&'static str] = T::FIELDS;
+
+    fn unknown_fields(value: &Value) -> Vec<String> {
+        T::unknown_fields(value)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for JsonFields<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Value::deserialize(deserializer).map(|v| Self(v, PhantomData))
+    }
+}
+
+/// JSON body extractor that, when `STRICT_JSON` is on, rejects bodies containing fields `T`
+/// doesn't declare with a 400 instead of ignoring them. Otherwise it behaves exactly like `Json`.
+pub struct StrictJson<T>(pub T);
+
+#[async_trait]
+impl<T> FromRequest<Arc<AppState>> for StrictJson<T>
+where
+    T: DeserializeOwned + KnownFields,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        if !state.config.strict_json {
+            return Json::<T>::from_request(req, state)
+                .await
+                .map(|Json(body)| Self(body))
+                .map_err(IntoResponse::into_response);
+        }
+
+        let Json(value) = Json::<Value>::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        if let Err(e) = reject_unknown_fields::<T>(&value) {
+            tracing::debug!("Rejecting request body: {}", e.to_string());
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(GenericMessage {
+                    detail: e.to_string(),
+                }),
+            )
+                .into_response());
+        }
+
+        serde_json::from_value(value).map(Self).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(GenericMessage {
+                    detail: format!("Failed to deserialize the JSON body: {e}"),
+                }),
+            )
+                .into_response()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::{self, Visitor};
+    use serde_json::json;
+    use std::fmt;
+
+    use crate::models::{
+        maintenance::MaintenanceMode,
+        organization::{
+            OrganizationBatch, OrganizationBootstrap, OrganizationCreate, OrganizationUpdate,
+        },
+        study::{StudyAmend, StudyCreate, StudyPatch, StudyUpdate},
+        user::{OrganizationAdminCreate, UserCreate, UserStudiesTransfer, UserStudy, UserUpdate},
+        webhook::WebhookCreate,
+    };
+
+    /// Error carrying the field names serde's derive passed to `deserialize_struct`
+    #[derive(Debug)]
+    struct StructFields(&'static [&'static str]);
+
+    impl fmt::Display for StructFields {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+
+    impl std::error::Error for StructFields {}
+
+    impl de::Error for StructFields {
+        fn custom<M: fmt::Display>(_: M) -> Self {
+            Self(&[])
+        }
+    }
+
+    /// Deserializer that fails straight away, reporting the fields the struct asked for
+    struct FieldsDeserializer;
+
+    impl<'de> Deserializer<'de> for FieldsDeserializer {
+        type Error = StructFields;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, StructFields> {
+            Err(StructFields(&[]))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _: &'static str,
+            fields: &'static [&'static str],
+            _: V,
+        ) -> Result<V::Value, StructFields> {
+            Err(StructFields(fields))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+            ignored_any
+        }
+    }
+
+    /// Checks the hand written `FIELDS` against the fields serde actually accepts for `T`
+    fn assert_fields_match<T: KnownFields + DeserializeOwned>() {
+        let Err(StructFields(declared)) = T::deserialize(FieldsDeserializer) else {
+            panic!("{} isn't a struct", std::any::type_name::<T>());
+        };
+        let mut declared = declared.to_vec();
+        declared.sort_unstable();
+        let mut known = T::FIELDS.to_vec();
+        known.sort_unstable();
+
+        assert_eq!(known, declared, "{}", std::any::type_name::<T>());
+    }
+
+    #[test]
+    fn known_fields_match_structs() {
+        assert_fields_match::<MaintenanceMode>();
+        assert_fields_match::<OrganizationAdminCreate>();
+        assert_fields_match::<OrganizationBatch>();
+        assert_fields_match::<OrganizationBootstrap>();
+        assert_fields_match::<OrganizationCreate>();
+        assert_fields_match::<OrganizationUpdate>();
+        assert_fields_match::<StudyAmend>();
+        assert_fields_match::<StudyCreate>();
+        assert_fields_match::<StudyPatch>();
+        assert_fields_match::<StudyUpdate>();
+        assert_fields_match::<UserCreate>();
+        assert_fields_match::<UserStudiesTransfer>();
+        assert_fields_match::<UserStudy>();
+        assert_fields_match::<UserUpdate>();
+        assert_fields_match::<WebhookCreate>();
+    }
+
+    struct Outer;
+
+    impl KnownFields for Outer {
+        const FIELDS: &'static [&'static str] = &["name", "inner"];
+
+        fn unknown_fields(value: &Value) -> Vec<String> {
+            let mut unknown = unknown_keys(value, Self::FIELDS, "");
+            if let Some(inner) = value.get("inner") {
+                unknown.extend(unknown_keys(inner, &["value"], "inner."));
+            }
+            unknown
+        }
+    }
+
+    #[test]
+    fn known_fields_accepted() {
+        let body = json!({ "name": "a", "inner": { "value": 1 } });
+
+        assert!(reject_unknown_fields::<Outer>(&body).is_ok());
+    }
+
+    #[test]
+    fn unknown_fields_listed() {
+        let body = json!({ "name": "a", "nmae": "b", "inner": { "valeu": 1 } });
+        let err = reject_unknown_fields::<Outer>(&body)
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(err, "Unknown fields: nmae, inner.valeu");
+    }
+
+    #[test]
+    fn non_object_has_no_unknown_fields() {
+        assert!(unknown_keys(&json!([1, 2]), &["name"], "").is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+use std::{str::FromStr, thread};
+
+use dotenvy::dotenv;
+use sqlx::{
+    postgres::{PgConnectOptions, PgPool, PgPoolOptions},
+    Connection, Executor, PgConnection,
+};
+use uuid::Uuid;
+
+use crate::{config::Config, db::DbClient};
+
+/// Pool whose connections only see a schema of their own, created and migrated for one test.
+/// Rows a test adds can't collide with other tests and are dropped along with the schema when
+/// the `TestPool` goes out of scope, even if the test panics.
+pub struct TestPool {
+    pub pool: PgPool,
+    uri: String,
+    schema: String,
+}
+
+/// Creates a fresh schema, runs the migrations into it and returns a pool with `search_path` set
+/// to it, so services see an empty database.
+pub async fn test_pool() -> TestPool {
+    dotenv().ok();
+    let config = Config::new();
+    let uri = DbClient::new(
+        &config.database_address,
+        &config.database_user,
+        &config.database_password,
+        &config.database_port,
+        "open_edc",
+    )
+    .uri;
+    let schema = format!("test_{}", Uuid::new_v4().simple());
+
+    let mut conn = PgConnection::connect(&uri)
+        .await
+        .expect("Error connecting to the test database");
+    conn.execute(format!("CREATE SCHEMA {schema}").as_str())
+        .await
+        .expect("Error creating test schema");
+    conn.close().await.ok();
+
+    let connect_options = PgConnectOptions::from_str(&uri)
+        .expect("Invalid test database uri")
+        .options([("search_path", schema.as_str())]);
+    let pool = PgPoolOptions::new()
+        .max_connections(5)
+        .connect_with(connect_options)
+        .await
+        .expect("Error creating test pool");
+
+    let mut migrator = sqlx::migrate!();
+    // Each schema has its own migrations table so there is nothing to serialize on
+    migrator.set_locking(false);
+    migrator
+        .run(&pool)
+        .await
+        .expect("Error migrating test schema");
+
+    TestPool { pool, uri, schema }
+}
+
+impl Drop for TestPool {
+    fn drop(&mut self) {
+        let uri = self.uri.clone();
+        let schema = self.schema.clone();
+
+        // Drop can't await and may run inside the test's runtime, so clean up on a thread with a
+        // runtime of its own
+        let cleanup = thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("Error creating cleanup runtime");
+            runtime.block_on(async {
+                let mut conn = PgConnection::connect(&uri).await?;
+                conn.execute(format!("DROP SCHEMA IF EXISTS {schema} CASCADE").as_str())
+                    .await?;
+                conn.close().await
+            })
+        });
+
+        if let Ok(Err(e)) = cleanup.join() {
+            tracing::warn!("Error dropping test schema {}: {}", self.schema, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pool_is_isolated() {
+        let first = test_pool().await;
+        let second = test_pool().await;
+
+        sqlx::query(
+            "INSERT INTO organizations (id, name, active, date_added, date_modified)
+             VALUES ($1, 'isolated', true, now(), now())",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .execute(&first.pool)
+        .await
+        .unwrap();
+
+        let count = |pool: PgPool| async move {
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM organizations")
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        };
+
+        assert_eq!(count(first.pool.clone()).await, 1);
+        assert_eq!(count(second.pool.clone()).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_pool_schema_dropped() {
+        let test_pool = test_pool().await;
+        let schema = test_pool.schema.clone();
+        let uri = test_pool.uri.clone();
+        drop(test_pool);
+
+        let mut conn = PgConnection::connect(&uri).await.unwrap();
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM information_schema.schemata WHERE schema_name = $1)",
+        )
+        .bind(&schema)
+        .fetch_one(&mut conn)
+        .await
+        .unwrap();
+
+        assert!(!exists);
+    }
+}
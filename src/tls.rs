@@ -0,0 +1,107 @@
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use anyhow::{bail, Result};
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, HeaderMap},
+};
+use ipnet::IpNet;
+
+use crate::{client_ip::is_trusted, config::Config, state::AppState};
+
+const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+
+/// Whether the request was sent over TLS. The server only speaks plain HTTP itself, so this can
+/// only be learned from the `X-Forwarded-Proto` header of one of the `TRUSTED_PROXIES`. Requests
+/// from anyone else, or when the peer address isn't known, count as plain HTTP.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OverTls(pub bool);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for OverTls {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        Ok(Self(peer.is_some_and(|p| {
+            forwarded_over_tls(p, &parts.headers, &state.config.trusted_proxies)
+        })))
+    }
+}
+
+/// Whether a trusted proxy at `peer` says the client connected to it over HTTPS. With a chain of
+/// proxies the first `X-Forwarded-Proto` value is the one the client used.
+pub fn forwarded_over_tls(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpNet]) -> bool {
+    if !is_trusted(peer, trusted_proxies) {
+        return false;
+    }
+
+    headers
+        .get(X_FORWARDED_PROTO)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .is_some_and(|p| p.trim().eq_ignore_ascii_case("https"))
+}
+
+/// Fails when `REQUIRE_TLS_FOR_SENSITIVE` is on and `operation`, something that sends a password
+/// or other secret, arrived over plain HTTP.
+pub fn require_tls_for_sensitive(
+    config: &Config,
+    over_tls: OverTls,
+    operation: &str,
+) -> Result<()> {
+    if config.require_tls_for_sensitive && !over_tls.0 {
+        bail!(format!("{operation} must be sent over HTTPS"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn trusted() -> Vec<IpNet> {
+        vec!["10.0.0.0/8".parse().unwrap()]
+    }
+
+    fn headers(forwarded_proto: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(X_FORWARDED_PROTO, HeaderValue::from_static(forwarded_proto));
+        headers
+    }
+
+    #[test]
+    fn trusted_proxy_forwarded_https() {
+        let peer: IpAddr = "10.0.0.1".parse().unwrap();
+
+        assert!(forwarded_over_tls(peer, &headers("https"), &trusted()));
+        assert!(forwarded_over_tls(
+            peer,
+            &headers("HTTPS, http"),
+            &trusted()
+        ));
+        assert!(!forwarded_over_tls(peer, &headers("http"), &trusted()));
+        assert!(!forwarded_over_tls(peer, &HeaderMap::new(), &trusted()));
+    }
+
+    #[test]
+    fn untrusted_peer_forwarded_https() {
+        let peer: IpAddr = "203.0.113.7".parse().unwrap();
+
+        assert!(!forwarded_over_tls(peer, &headers("https"), &trusted()));
+    }
+}
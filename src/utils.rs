@@ -1,68 +1,317 @@
-use std::sync::Arc;
-
-use anyhow::Result;
-use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
-    Argon2,
-};
-use tokio::task::spawn_blocking;
+use std::sync::{Arc, OnceLock};
+
+use anyhow::{bail, Result};
+use regex::Regex;
+use serde_json::Value;
+use tokio::{sync::Semaphore, task::spawn_blocking};
 use uuid::Uuid;
 
+use crate::password_hasher::{default_hasher, hasher_for, hasher_named, PasswordHasher};
+
 pub fn generate_db_id() -> String {
     Uuid::new_v4().to_string()
 }
 
-pub async fn hash_password(password: &str) -> Result<String> {
+/// Trims surrounding whitespace and lowercases the entire address so lookups and comparisons
+/// are consistent regardless of how the user typed it.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Checks that a phone number is in E.164 format: a leading +, a non-zero country code digit,
+/// and at most 15 digits in total.
+pub fn validate_phone(phone: &str) -> Result<()> {
+    let valid = match phone.strip_prefix('+') {
+        Some(digits) => {
+            (2..=15).contains(&digits.len())
+                && !digits.starts_with('0')
+                && digits.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    };
+
+    if !valid {
+        bail!(format!(
+            "Invalid phone number {phone}, expected E.164 format such as +15555550123"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks `value` against a naming convention from the config. Anything is accepted when there
+/// is no `pattern`.
+pub fn validate_pattern(field: &str, pattern: Option<&Regex>, value: &str) -> Result<()> {
+    if let Some(p) = pattern {
+        if !p.is_match(value) {
+            bail!(format!(
+                "Invalid {field} {value}, expected to match the pattern {}",
+                p.as_str()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+static PASSWORD_HASHER: OnceLock<&'static dyn PasswordHasher> = OnceLock::new();
+
+/// Picks the algorithm new password hashes are made with, one of `PASSWORD_HASH_ALGORITHMS`.
+/// Existing hashes are verified with whichever algorithm made them. Only the first call has an
+/// effect so it should be made at startup, until then argon2 is used.
+pub fn set_password_hash_algorithm(name: &str) -> Result<()> {
+    let Some(hasher) = hasher_named(name) else {
+        bail!(format!("Unknown password hash algorithm {name}"));
+    };
+    if PASSWORD_HASHER.set(hasher).is_err() {
+        tracing::warn!("Password hash algorithm already set, ignoring {name}");
+    }
+
+    Ok(())
+}
+
+fn password_hasher() -> &'static dyn PasswordHasher {
+    *PASSWORD_HASHER.get_or_init(default_hasher)
+}
+
+/// Password hashes allowed to run at once by `PasswordHashPermits::default`
+const DEFAULT_PASSWORD_HASH_CONCURRENCY: usize = 4;
+
+/// Limits how many password hashes and verifications run at once, the rest wait for a permit.
+/// Clones share the same permits.
+#[derive(Clone)]
+pub struct PasswordHashPermits(Arc<Semaphore>);
+
+impl PasswordHashPermits {
+    pub fn new(concurrency: usize) -> Self {
+        Self(Arc::new(Semaphore::new(concurrency)))
+    }
+}
+
+impl Default for PasswordHashPermits {
+    fn default() -> Self {
+        Self::new(DEFAULT_PASSWORD_HASH_CONCURRENCY)
+    }
+}
+
+/// Runs `f` on the blocking pool once a permit is free. Each hash or verification holds a
+/// blocking thread for its whole run, so a burst of them would otherwise take over the pool.
+/// The permit goes with `f` and is only released when it finishes, even if the caller stops
+/// waiting for it.
+async fn run_bounded<T, F>(permits: &PasswordHashPermits, f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    let permit = permits.0.clone().acquire_owned().await?;
+    spawn_blocking(move || {
+        let _permit = permit;
+        f()
+    })
+    .await?
+}
+
+pub async fn hash_password(permits: &PasswordHashPermits, password: &str) -> Result<String> {
     let password_arc = Arc::new(password.to_string());
+    let hasher = password_hasher();
 
-    let hashed_password = spawn_blocking(move || -> Result<String> {
+    let hashed_password = run_bounded(permits, move || -> Result<String> {
         let password = password_arc.clone();
-        let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
-        let hashed_password = argon2
-            .hash_password(password.as_bytes(), &salt)?
-            .to_string();
-
-        Ok(hashed_password)
+        hasher.hash(&password)
     })
-    .await??;
+    .await?;
 
     Ok(hashed_password)
 }
 
 #[allow(dead_code)]
-pub async fn verify_password(password: &str, hashed_password: &str) -> Result<()> {
+pub async fn verify_password(
+    permits: &PasswordHashPermits,
+    password: &str,
+    hashed_password: &str,
+) -> Result<()> {
     let password_arc = Arc::new(password.to_string());
     let password_hash_arc = Arc::new(hashed_password.to_string());
+    // Whatever made the hash verifies it, so older hashes keep working after the algorithm
+    // for new ones changes
+    let hasher = hasher_for(hashed_password)?;
 
-    spawn_blocking(move || -> Result<()> {
+    run_bounded(permits, move || -> Result<()> {
         let password = password_arc.clone();
         let hashed_password = password_hash_arc.clone();
-        let parsed_hash = PasswordHash::new(&hashed_password)?;
-        Argon2::default().verify_password(password.as_bytes(), &parsed_hash)?;
-
-        Ok(())
+        hasher.verify(&password, &hashed_password)
     })
-    .await??;
+    .await?;
 
     Ok(())
 }
 
+/// Sets the `id` of a JSON update body to the id from the request path. A body `id` that names a
+/// different record is an error rather than being silently overwritten.
+pub fn with_path_id(mut body: Value, id: &str) -> Result<Value> {
+    let Some(fields) = body.as_object_mut() else {
+        bail!("Request body must be a JSON object");
+    };
+
+    match fields.get("id") {
+        None | Some(Value::Null) => {}
+        Some(Value::String(body_id)) if body_id == id => {}
+        Some(body_id) => bail!(format!("Body id {body_id} does not match path id {id}")),
+    }
+
+    fields.insert("id".to_string(), Value::String(id.to_string()));
+
+    Ok(body)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    #[tokio::test]
+    async fn run_bounded_limits_concurrency() {
+        let permits = PasswordHashPermits::new(2);
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let permits = permits.clone();
+                let running = running.clone();
+                let max_running = max_running.clone();
+                tokio::spawn(async move {
+                    run_bounded(&permits, move || {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_running.fetch_max(now, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(50));
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        Ok(())
+                    })
+                    .await
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert!(max_running.load(Ordering::SeqCst) <= 2);
+        assert_eq!(running.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn run_bounded_keeps_permit_when_cancelled() {
+        let permits = PasswordHashPermits::new(1);
+        let task = tokio::spawn({
+            let permits = permits.clone();
+            async move {
+                run_bounded(&permits, || {
+                    thread::sleep(Duration::from_millis(200));
+                    Ok(())
+                })
+                .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        task.abort();
+        assert!(task.await.unwrap_err().is_cancelled());
+
+        // The blocking work is still running so its permit must still be taken
+        assert!(permits.0.try_acquire().is_err());
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(permits.0.try_acquire().is_ok());
+    }
+
+    #[test]
+    fn test_normalize_email() {
+        assert_eq!(
+            normalize_email(" Arthur@HeartOfGold.com "),
+            "arthur@heartofgold.com"
+        );
+    }
+
+    #[test]
+    fn test_normalize_email_already_normalized() {
+        assert_eq!(
+            normalize_email("arthur@heartofgold.com"),
+            "arthur@heartofgold.com"
+        );
+    }
+
+    #[test]
+    fn test_validate_phone() {
+        assert!(validate_phone("+15555550123").is_ok());
+        assert!(validate_phone("+442071838750").is_ok());
+    }
+
+    #[test]
+    fn test_validate_phone_invalid() {
+        assert!(validate_phone("5555550123").is_err());
+        assert!(validate_phone("+0555550123").is_err());
+        assert!(validate_phone("+1 555 555 0123").is_err());
+        assert!(validate_phone("+1234567890123456").is_err());
+        assert!(validate_phone("+").is_err());
+    }
+
+    #[test]
+    fn test_validate_pattern() {
+        let pattern = Regex::new(r"^[A-Z]{2,4}-\d{4}$").unwrap();
+
+        assert!(validate_pattern("study_id", Some(&pattern), "ONC-0042").is_ok());
+        assert_eq!(
+            validate_pattern("study_id", Some(&pattern), "onc-42")
+                .unwrap_err()
+                .to_string(),
+            r"Invalid study_id onc-42, expected to match the pattern ^[A-Z]{2,4}-\d{4}$"
+        );
+    }
+
+    #[test]
+    fn test_validate_pattern_unrestricted() {
+        assert!(validate_pattern("user_name", None, "anything goes").is_ok());
+    }
+
+    #[test]
+    fn test_with_path_id() {
+        assert_eq!(
+            with_path_id(json!({ "name": "a" }), "1").unwrap(),
+            json!({ "id": "1", "name": "a" })
+        );
+        assert_eq!(
+            with_path_id(json!({ "id": "1", "name": "a" }), "1").unwrap(),
+            json!({ "id": "1", "name": "a" })
+        );
+    }
+
+    #[test]
+    fn test_with_path_id_mismatch() {
+        assert!(with_path_id(json!({ "id": "2" }), "1").is_err());
+        assert!(with_path_id(json!({ "id": 1 }), "1").is_err());
+        assert!(with_path_id(json!(["1"]), "1").is_err());
+    }
 
     #[tokio::test]
     async fn test_hash_password() {
+        let permits = PasswordHashPermits::default();
         let password = "some_password".to_string();
-        let hashed_password = hash_password(&password).await.unwrap();
+        let hashed_password = hash_password(&permits, &password).await.unwrap();
         assert_ne!(password, hashed_password);
     }
 
     #[tokio::test]
     async fn test_verify_password() {
+        let permits = PasswordHashPermits::default();
         let password = "some_password".to_string();
-        let hashed_password = hash_password(&password).await.unwrap();
-        assert!(verify_password(&password, &hashed_password).await.is_ok());
+        let hashed_password = hash_password(&permits, &password).await.unwrap();
+        assert!(verify_password(&permits, &password, &hashed_password)
+            .await
+            .is_ok());
     }
 }